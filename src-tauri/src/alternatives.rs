@@ -0,0 +1,89 @@
+//! Alternative texts of transcript segments, offered for correcting misrecognitions.
+//!
+//! whisper.cpp only returns the best beam, so alternatives come from decoding the audio again
+//! with sampling at increasing temperatures. The segments of such a hypothesis are aligned to
+//! the transcript by time, and their text is kept where it differs.
+
+use crate::transcript::Utterance;
+
+/// Most hypotheses decoded, each one decodes the whole audio again
+pub const MAX_HYPOTHESES: u8 = 5;
+
+/// Temperature of the first hypothesis, each further one is sampled hotter by this step
+const TEMPERATURE_STEP: f32 = 0.2;
+
+/// Sampling temperature of the hypothesis with the given index
+pub fn hypothesis_temperature(index: u8) -> f32 {
+    (TEMPERATURE_STEP * (f32::from(index) + 1.0)).min(1.0)
+}
+
+/// Add the text of the hypothesis segments overlapping each utterance as its alternative.
+///
+/// A hypothesis segment belongs to the utterance containing its middle. Texts equal to the
+/// utterance's text, or to one of its alternatives, are skipped.
+pub fn attach_alternatives(utterances: &mut [Utterance], hypothesis: &[Utterance]) {
+    for utterance in utterances {
+        let text = hypothesis
+            .iter()
+            .filter(|segment| {
+                let middle = (segment.start + segment.stop) / 2;
+                (utterance.start..utterance.stop).contains(&middle)
+            })
+            .map(|segment| segment.text.trim())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let is_new = !text.is_empty()
+            && !same_words(&text, &utterance.text)
+            && !utterance
+                .alternatives
+                .iter()
+                .any(|alternative| same_words(&text, alternative));
+        if is_new {
+            utterance.alternatives.push(text);
+        }
+    }
+}
+
+/// Whether the texts only differ in case and whitespace
+fn same_words(a: &str, b: &str) -> bool {
+    a.split_whitespace()
+        .map(str::to_lowercase)
+        .eq(b.split_whitespace().map(str::to_lowercase))
+}
+
+#[cfg(test)]
+fn segment(start: i64, stop: i64, text: &str) -> Utterance {
+    Utterance {
+        start,
+        stop,
+        text: text.into(),
+        speaker: None,
+        hallucination: None,
+        confidence: None,
+        alternatives: Vec::new(),
+        source: None,
+    }
+}
+
+#[test]
+fn test_attach_alternatives() {
+    let mut utterances = vec![
+        segment(0, 200, " I scream for ice cream."),
+        segment(200, 400, " See you."),
+    ];
+    attach_alternatives(
+        &mut utterances,
+        &[
+            segment(0, 100, " Ice cream"),
+            segment(100, 210, " for ice cream."),
+            segment(210, 400, " see  you."),
+        ],
+    );
+    attach_alternatives(
+        &mut utterances,
+        &[segment(0, 200, " Ice cream for ice cream.")],
+    );
+    assert_eq!(utterances[0].alternatives, ["Ice cream for ice cream."]);
+    assert!(utterances[1].alternatives.is_empty());
+    assert!(hypothesis_temperature(0) > 0.0 && hypothesis_temperature(9) <= 1.0);
+}
@@ -0,0 +1,273 @@
+//! Benchmark of transcription settings on this machine.
+//!
+//! A reference clip is transcribed with several thread counts, beam sizes and GPU settings. The
+//! fastest configuration that is about as accurate as the most accurate one is recommended.
+//! Without a recording of known text, a synthetic speech-like clip only measures the speed.
+
+use crate::{
+    metrics::word_error_rate,
+    mutter::{Model, ModelError, TranscribeCallbacks},
+    types::{BenchmarkOptions, TranscribeOptions},
+};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use whisper_rs::WhisperContextParameters;
+
+/// Sample rate of the audio given to whisper
+const SAMPLE_RATE: u32 = 16_000;
+
+/// Length of the synthetic clip, the length of a long dictation
+pub const SYNTHETIC_CLIP_SECONDS: u32 = 30;
+
+/// Beam sizes tried by default, close to greedy decoding and whisper's default
+const DEFAULT_BEAM_SIZES: [i32; 2] = [1, 5];
+
+/// Word error rate a configuration may lose against the most accurate one to be recommended
+const ACCURACY_TOLERANCE: f64 = 0.02;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// Settings the reference clip is transcribed with
+pub struct BenchmarkConfiguration {
+    pub threads: u16,
+    pub beam_size: i32,
+    pub use_gpu: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Type)]
+/// Speed and accuracy of one configuration
+pub struct BenchmarkResult {
+    pub configuration: BenchmarkConfiguration,
+    /// Seconds of processing per second of audio, below `1` is faster than real time
+    pub real_time_factor: f64,
+    /// Word edits needed per reference word, `None` without a reference text
+    pub word_error_rate: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Type)]
+/// Results of a benchmark with the recommended settings
+pub struct BenchmarkReport {
+    pub results: Vec<BenchmarkResult>,
+    /// Fastest configuration about as accurate as the most accurate one
+    pub recommended: Option<BenchmarkConfiguration>,
+    /// Whether the recommended settings were applied
+    pub applied: bool,
+}
+
+/// Configurations to try, every combination of the thread counts, beam sizes and GPU settings
+pub fn configurations(
+    options: &BenchmarkOptions,
+    cpu_count: u16,
+    gpu_available: bool,
+) -> Vec<BenchmarkConfiguration> {
+    let mut thread_counts = options
+        .threads
+        .clone()
+        .unwrap_or_else(|| vec![(cpu_count / 2).max(1), cpu_count.max(1)]);
+    thread_counts.dedup();
+    let beam_sizes = options
+        .beam_sizes
+        .clone()
+        .unwrap_or_else(|| DEFAULT_BEAM_SIZES.to_vec());
+    let gpu_settings: &[bool] = if gpu_available && options.try_gpu.unwrap_or(true) {
+        &[false, true]
+    } else {
+        &[false]
+    };
+    gpu_settings
+        .iter()
+        .flat_map(|&use_gpu| {
+            thread_counts.iter().flat_map(move |&threads| {
+                beam_sizes
+                    .iter()
+                    .map(move |&beam_size| BenchmarkConfiguration {
+                        threads,
+                        beam_size,
+                        use_gpu,
+                    })
+            })
+        })
+        .collect()
+}
+
+/// Speech-like 16 kHz mono audio, a voiced tone with a gliding pitch in syllable-long bursts.
+///
+/// Has no words to compare the transcript with, so it only measures the speed.
+#[allow(
+    clippy::cast_possible_truncation,
+    reason = "Samples are clamped between -1 and 1"
+)]
+pub fn synthetic_clip(seconds: u32) -> Vec<f32> {
+    use std::f64::consts::{PI, TAU};
+
+    let mut phase = 0.0_f64;
+    (0..seconds * SAMPLE_RATE)
+        .map(|i| {
+            let time = f64::from(i) / f64::from(SAMPLE_RATE);
+            let pitch = 40.0f64.mul_add((TAU * 0.5 * time).sin(), 140.0);
+            phase = (phase + TAU * pitch / f64::from(SAMPLE_RATE)) % TAU;
+            // About four syllables a second with a pause every three seconds
+            let syllable = (PI * 4.0 * time).sin().powi(2);
+            let envelope = if time % 3.0 < 2.5 { syllable } else { 0.0 };
+            // Decaying harmonics, shifted over time like changing vowels
+            let brightness = 0.5f64.mul_add((TAU * 1.5 * time).sin(), 1.5);
+            let voice = (1..=12)
+                .map(|harmonic| {
+                    let harmonic = f64::from(harmonic);
+                    (phase * harmonic).sin() / harmonic.powf(brightness)
+                })
+                .sum::<f64>();
+            (0.3 * envelope * voice).clamp(-1.0, 1.0) as f32
+        })
+        .collect()
+}
+
+/// Transcribe the 16 kHz mono audio with every configuration.
+///
+/// Configurations with the GPU setting of the `loaded` model reuse it, the model is only loaded
+/// again from `model_path` for the other setting. Each model transcribes the audio once before
+/// measuring, so setting up the backend is not counted.
+/// # Errors
+/// - [`ModelError`]
+pub fn run(
+    loaded: (&Model, bool),
+    model_path: &str,
+    audio: &[f32],
+    reference: Option<&str>,
+    language: Option<&str>,
+    configurations: &[BenchmarkConfiguration],
+) -> Result<Vec<BenchmarkResult>, ModelError> {
+    #[allow(
+        clippy::cast_precision_loss,
+        reason = "Clips are far below 2^52 samples"
+    )]
+    let audio_seconds = audio.len() as f64 / f64::from(SAMPLE_RATE);
+    let transcribe = |model: &Model, configuration: &BenchmarkConfiguration| {
+        let options = TranscribeOptions {
+            language: language.map(str::to_string),
+            threads: Some(configuration.threads),
+            beam_size: Some(configuration.beam_size),
+            ..TranscribeOptions::default()
+        };
+        model.transcribe_pcm_s16le(audio, &options, TranscribeCallbacks::default())
+    };
+    let (loaded_model, loaded_uses_gpu) = loaded;
+    let mut results = Vec::new();
+    for use_gpu in [false, true] {
+        let selected = configurations
+            .iter()
+            .filter(|configuration| configuration.use_gpu == use_gpu)
+            .collect::<Vec<_>>();
+        let Some(&first) = selected.first() else {
+            continue;
+        };
+        let reloaded;
+        let model = if use_gpu == loaded_uses_gpu {
+            loaded_model
+        } else {
+            let mut params = WhisperContextParameters::new();
+            params.use_gpu(use_gpu);
+            reloaded =
+                Model::new_with_params(model_path, params).map_err(ModelError::WhisperError)?;
+            &reloaded
+        };
+        debug!("Warming up the model with GPU use {use_gpu}");
+        transcribe(model, first)?;
+        for &configuration in selected {
+            let transcript = transcribe(model, &configuration)?;
+            let result = BenchmarkResult {
+                configuration,
+                real_time_factor: transcript.processing_time.as_secs_f64()
+                    / audio_seconds.max(f64::EPSILON),
+                word_error_rate: reference
+                    .map(|reference| word_error_rate(reference, &transcript.as_text())),
+            };
+            info!("Benchmark result: {result:?}");
+            results.push(result);
+        }
+    }
+    Ok(results)
+}
+
+/// Fastest configuration with a word error rate close to the best one, or the fastest one
+/// without word error rates
+pub fn recommend(results: &[BenchmarkResult]) -> Option<BenchmarkConfiguration> {
+    let best = results
+        .iter()
+        .filter_map(|result| result.word_error_rate)
+        .min_by(f64::total_cmp);
+    results
+        .iter()
+        .filter(|result| match (result.word_error_rate, best) {
+            (Some(word_error_rate), Some(best)) => word_error_rate <= best + ACCURACY_TOLERANCE,
+            _ => true,
+        })
+        .min_by(|a, b| a.real_time_factor.total_cmp(&b.real_time_factor))
+        .map(|result| result.configuration)
+}
+
+#[test]
+fn test_configurations() {
+    let options = BenchmarkOptions::default();
+    let cpu_only = configurations(&options, 8, false);
+    assert_eq!(cpu_only.len(), 4);
+    assert_eq!(
+        cpu_only[0],
+        BenchmarkConfiguration {
+            threads: 4,
+            beam_size: 1,
+            use_gpu: false,
+        }
+    );
+    assert_eq!(configurations(&options, 8, true).len(), 8);
+    // A single core is only tried once
+    assert_eq!(configurations(&options, 1, false).len(), 2);
+}
+
+#[test]
+fn test_recommend_prefers_fast_and_accurate() {
+    let result = |threads, real_time_factor, word_error_rate| BenchmarkResult {
+        configuration: BenchmarkConfiguration {
+            threads,
+            beam_size: 5,
+            use_gpu: false,
+        },
+        real_time_factor,
+        word_error_rate: Some(word_error_rate),
+    };
+    let results = [
+        result(1, 0.9, 0.10),
+        result(2, 0.5, 0.11),
+        result(4, 0.2, 0.30),
+    ];
+    assert_eq!(recommend(&results).map(|c| c.threads), Some(2));
+    assert_eq!(recommend(&[]), None);
+}
+
+#[test]
+fn test_recommend_fastest_without_reference() {
+    let result = |threads, real_time_factor| BenchmarkResult {
+        configuration: BenchmarkConfiguration {
+            threads,
+            beam_size: 1,
+            use_gpu: false,
+        },
+        real_time_factor,
+        word_error_rate: None,
+    };
+    let results = [result(2, 0.6), result(8, 0.3), result(4, 0.4)];
+    assert_eq!(recommend(&results).map(|c| c.threads), Some(8));
+}
+
+#[test]
+fn test_synthetic_clip() {
+    let clip = synthetic_clip(4);
+    assert_eq!(clip.len(), 4 * 16_000);
+    assert!(clip.iter().all(|sample| (-1.0..=1.0).contains(sample)));
+    // Voiced in the middle of a syllable, silent in the pause
+    let rms = |samples: &[f32]| {
+        (samples.iter().map(|sample| sample * sample).sum::<f32>() / 1_600.0).sqrt()
+    };
+    assert!(rms(&clip[1_200..2_800]) > 0.05);
+    assert!(rms(&clip[42_400..44_000]).abs() < f32::EPSILON);
+}
@@ -0,0 +1,82 @@
+//! Evaluate the accuracy of the transcription pipeline on a folder of recordings.
+//!
+//! Usage: `evaluate <model.bin> <folder> [--language <code>] [--threads <count>]
+//! [--settings <settings.json>] [--json]`
+//!
+//! Every `<name>.wav` in the folder needs its reference transcript in `<name>.txt`. The language
+//! and threads default to the app's settings, read from its config directory unless given.
+
+use std::{path::Path, process::ExitCode};
+use super_mouse_ai_lib::{
+    evaluation::evaluate,
+    settings::{default_settings_path, Settings},
+};
+
+const USAGE: &str = "Usage: evaluate <model.bin> <folder> [--language <code>] [--threads <count>] [--settings <settings.json>] [--json]";
+
+fn main() -> ExitCode {
+    let mut positional = Vec::new();
+    let mut language = None;
+    let mut threads = None;
+    let mut settings_path = None;
+    let mut json = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--language" => language = args.next(),
+            "--threads" => threads = args.next().and_then(|count| count.parse::<u16>().ok()),
+            "--settings" => settings_path = args.next().map(Into::into),
+            "--json" => json = true,
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            _ => positional.push(arg),
+        }
+    }
+    let [model_path, folder] = positional.as_slice() else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+    let settings = match settings_path {
+        Some(path) => match Settings::read(&path) {
+            Ok(settings) => settings,
+            Err(err) => {
+                eprintln!("Invalid settings: {err}");
+                return ExitCode::FAILURE;
+            }
+        },
+        // The app may never have been started
+        None => default_settings_path()
+            .filter(|path| path.exists())
+            .and_then(|path| {
+                Settings::read(&path)
+                    .map_err(|err| eprintln!("Invalid settings, using defaults: {err}"))
+                    .ok()
+            })
+            .unwrap_or_default(),
+    };
+    let defaults = settings.transcribe_options(None);
+    let language = language.or(defaults.language);
+    let threads = threads.or(defaults.threads).filter(|&threads| threads > 0);
+    match evaluate(model_path, Path::new(folder), language.as_deref(), threads) {
+        Ok(report) if json => match serde_json::to_string_pretty(&report) {
+            Ok(json) => {
+                println!("{json}");
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("Could not serialize report: {err}");
+                ExitCode::FAILURE
+            }
+        },
+        Ok(report) => {
+            print!("{}", report.as_markdown());
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("Evaluation failed: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,806 @@
+//! Input stream configuration and building, converting every supported sample format to `f32`.
+//!
+//! The stream callback only writes into a lock-free ring buffer ([`CaptureWriter`]). The recording
+//! thread drains it ([`drain_capture`]) into [`crate::types::MicrophoneDataState`], applying the
+//! recording limits, so the real-time audio thread never waits on a lock.
+
+use crate::{
+    command::update_recording_state,
+    devices::convert_audio,
+    error::{AppError, ErrorCode},
+    events::{emit_input_stream_error, emit_meter_update, RecordingLimitReachedEvent},
+    levels::LevelMeter,
+    loopback::SystemAudio,
+    types::{
+        CaptureLimits, InnerMicrophoneData, InputSampleFormat, InputStreamConfig,
+        MicrophoneDataState, MicrophoneState, OverflowPolicy, RecordingState, StreamControl,
+        SupportedInputConfig, SystemTrack,
+    },
+};
+use audrey::hound::{SampleFormat as WavSampleFormat, WavSpec, WavWriter};
+use log::{debug, error, info, warn};
+use rodio::{
+    cpal::{
+        traits::StreamTrait, BufferSize, FromSample, Sample, SampleFormat, SampleRate, SizedSample,
+        Stream, StreamConfig, StreamError, SupportedBufferSize,
+    },
+    Device, DeviceTrait,
+};
+use rtrb::{Consumer, Producer, RingBuffer};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tauri::{async_runtime::Receiver, AppHandle, Manager};
+use tauri_specta::Event;
+use tokio::sync::mpsc::error::TryRecvError;
+
+impl InputSampleFormat {
+    /// Get the format for a `cpal` sample format, `None` if it cannot be recorded
+    pub const fn from_cpal(format: SampleFormat) -> Option<Self> {
+        match format {
+            SampleFormat::F32 => Some(Self::F32),
+            SampleFormat::I16 => Some(Self::I16),
+            SampleFormat::U16 => Some(Self::U16),
+            SampleFormat::I32 => Some(Self::I32),
+            _ => None,
+        }
+    }
+}
+
+/// List all configurations of a device that can be recorded
+pub fn supported_configs(device: &Device) -> Result<Vec<SupportedInputConfig>, String> {
+    Ok(device
+        .supported_input_configs()
+        .map_err(|err| err.to_string())?
+        .filter_map(|range| {
+            let sample_format = InputSampleFormat::from_cpal(range.sample_format())?;
+            let (min_buffer_size, max_buffer_size) = match *range.buffer_size() {
+                SupportedBufferSize::Range { min, max } => (Some(min), Some(max)),
+                SupportedBufferSize::Unknown => (None, None),
+            };
+            Some(SupportedInputConfig {
+                sample_format,
+                channels: range.channels(),
+                min_sample_rate: range.min_sample_rate().0,
+                max_sample_rate: range.max_sample_rate().0,
+                min_buffer_size,
+                max_buffer_size,
+            })
+        })
+        .collect())
+}
+
+/// Get the stream configuration to record with, the device's default if none was chosen.
+///
+/// A chosen configuration is validated against what the device supports.
+pub fn resolve_stream_config(
+    device: &Device,
+    chosen: Option<InputStreamConfig>,
+) -> Result<(StreamConfig, InputSampleFormat), String> {
+    let Some(chosen) = chosen else {
+        let default = device
+            .default_input_config()
+            .map_err(|err| format!("No default input configuration: {err}"))?;
+        let format = InputSampleFormat::from_cpal(default.sample_format()).ok_or_else(|| {
+            format!(
+                "Default sample format {} is not supported",
+                default.sample_format()
+            )
+        })?;
+        return Ok((default.config(), format));
+    };
+    if !supported_configs(device)?
+        .iter()
+        .any(|supported| supported.supports(&chosen))
+    {
+        return Err(format!(
+            "Input configuration is not supported by the device: {chosen:?}"
+        ));
+    }
+    debug!("Using chosen input configuration {chosen:?}");
+    Ok((
+        StreamConfig {
+            channels: chosen.channels,
+            sample_rate: SampleRate(chosen.sample_rate),
+            buffer_size: chosen
+                .buffer_size
+                .map_or(BufferSize::Default, BufferSize::Fixed),
+        },
+        chosen.sample_format,
+    ))
+}
+
+/// Build an input stream that passes its data to `on_data` as `f32` samples
+pub fn build_input_stream<D, E>(
+    device: &Device,
+    config: &StreamConfig,
+    format: InputSampleFormat,
+    on_data: D,
+    on_error: E,
+) -> Result<Stream, String>
+where
+    D: FnMut(&[f32]) + Send + 'static,
+    E: FnMut(StreamError) + Send + 'static,
+{
+    match format {
+        InputSampleFormat::F32 => build::<f32, D, E>(device, config, on_data, on_error),
+        InputSampleFormat::I16 => build::<i16, D, E>(device, config, on_data, on_error),
+        InputSampleFormat::U16 => build::<u16, D, E>(device, config, on_data, on_error),
+        InputSampleFormat::I32 => build::<i32, D, E>(device, config, on_data, on_error),
+    }
+}
+
+fn build<T, D, E>(
+    device: &Device,
+    config: &StreamConfig,
+    mut on_data: D,
+    on_error: E,
+) -> Result<Stream, String>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+    D: FnMut(&[f32]) + Send + 'static,
+    E: FnMut(StreamError) + Send + 'static,
+{
+    let mut buffer = Vec::new();
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _info| {
+                convert_samples(&mut buffer, data);
+                on_data(&buffer);
+            },
+            on_error,
+            None,
+        )
+        .map_err(|err| err.to_string())
+}
+
+/// Seconds of audio the ring buffer holds, far more than any callback delivers between drains
+const RING_SECONDS: usize = 2;
+
+/// Time between drains of the ring buffer
+const DRAIN_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Create a ring buffer holding a few seconds of audio for the given stream
+pub fn capture_ring(config: &StreamConfig) -> (CaptureWriter, CaptureReader) {
+    let capacity = config.sample_rate.0 as usize * usize::from(config.channels) * RING_SECONDS;
+    let (producer, consumer) = RingBuffer::new(capacity);
+    let overruns = Arc::new(AtomicUsize::new(0));
+    (
+        CaptureWriter {
+            producer,
+            overruns: Arc::clone(&overruns),
+        },
+        CaptureReader {
+            consumer,
+            overruns,
+            reported_overruns: 0,
+        },
+    )
+}
+
+/// Real-time side of the capture ring buffer
+pub struct CaptureWriter {
+    producer: Producer<f32>,
+    overruns: Arc<AtomicUsize>,
+}
+
+impl CaptureWriter {
+    /// Write a block of samples without blocking, dropping the whole block if it does not fit
+    pub fn write(&mut self, data: &[f32]) {
+        match self.producer.write_chunk_uninit(data.len()) {
+            Ok(chunk) => {
+                chunk.fill_from_iter(data.iter().copied());
+            }
+            Err(_) => {
+                self.overruns.fetch_add(data.len(), Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Recording thread side of the capture ring buffer
+pub struct CaptureReader {
+    consumer: Consumer<f32>,
+    overruns: Arc<AtomicUsize>,
+    reported_overruns: usize,
+}
+
+impl CaptureReader {
+    /// Move all available samples to the end of `out`, returning how many were moved
+    pub fn drain_into(&mut self, out: &mut Vec<f32>) -> usize {
+        let available = self.consumer.slots();
+        let Ok(chunk) = self.consumer.read_chunk(available) else {
+            return 0;
+        };
+        let (first, second) = chunk.as_slices();
+        out.extend_from_slice(first);
+        out.extend_from_slice(second);
+        chunk.commit_all();
+        available
+    }
+
+    /// Number of samples dropped since the last call
+    pub fn new_overruns(&mut self) -> usize {
+        let total = self.overruns.load(Ordering::Relaxed);
+        let new = total - self.reported_overruns;
+        self.reported_overruns = total;
+        new
+    }
+}
+
+/// WAV file receiving the raw input of a recording, for keeping the original audio.
+///
+/// Samples are written as 32 bit floats in the stream's channels and sample rate. The header is
+/// updated about every second, so the file stays readable if the app exits mid-recording.
+pub struct AudioArchive {
+    path: PathBuf,
+    writer: WavWriter<BufWriter<File>>,
+    /// Channels and sample rate of the file
+    format: (u16, u32),
+    /// Channels and sample rate of the written samples, differs from the file after a device switch
+    input: (u16, u32),
+    samples_per_flush: usize,
+    unflushed: usize,
+}
+
+impl AudioArchive {
+    /// Create a new timestamped file in `directory`, creating the directory if needed
+    pub fn create(directory: &Path, config: &StreamConfig) -> Result<Self, String> {
+        std::fs::create_dir_all(directory).map_err(|err| err.to_string())?;
+        let name = chrono::Local::now().format("recording-%Y%m%d-%H%M%S%.3f.wav");
+        let path = directory.join(name.to_string());
+        let spec = WavSpec {
+            channels: config.channels,
+            sample_rate: config.sample_rate.0,
+            bits_per_sample: 32,
+            sample_format: WavSampleFormat::Float,
+        };
+        let writer = WavWriter::create(&path, spec).map_err(|err| err.to_string())?;
+        debug!("Archiving recording to {}", path.display());
+        let format = (config.channels, config.sample_rate.0);
+        Ok(Self {
+            path,
+            writer,
+            format,
+            input: format,
+            samples_per_flush: config.sample_rate.0 as usize * usize::from(config.channels),
+            unflushed: 0,
+        })
+    }
+
+    /// Keep writing to the same file from a stream with the given configuration.
+    ///
+    /// Used when a recording moves to another device, its samples are converted to the format
+    /// of the file with [`convert_audio`].
+    pub const fn continue_with(&mut self, config: &StreamConfig) {
+        self.input = (config.channels, config.sample_rate.0);
+    }
+
+    /// Append interleaved samples
+    pub fn write(&mut self, samples: &[f32]) -> Result<(), String> {
+        let converted;
+        let samples = if self.input == self.format {
+            samples
+        } else {
+            converted = convert_audio(samples, self.input, self.format);
+            &converted
+        };
+        samples
+            .iter()
+            .try_for_each(|sample| self.writer.write_sample(*sample))
+            .map_err(|err| err.to_string())?;
+        self.unflushed += samples.len();
+        if self.unflushed >= self.samples_per_flush {
+            self.unflushed = 0;
+            self.writer.flush().map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Finish the file, returning its path
+    pub fn finish(self) -> Result<PathBuf, String> {
+        self.writer.finalize().map_err(|err| err.to_string())?;
+        Ok(self.path)
+    }
+}
+
+/// Audio read and written for one recording, besides [`MicrophoneDataState`]
+pub struct Capture {
+    pub microphone: CaptureReader,
+    /// System audio recorded along with the microphone
+    pub system: Option<SystemAudio>,
+    /// File receiving a copy of the recorded audio
+    pub archive: Option<AudioArchive>,
+}
+
+impl Capture {
+    /// Write samples to the archive, if any, giving up on archiving after an error
+    fn archive_samples(&mut self, app_handle: &AppHandle, samples: &[f32]) {
+        let Some(archive) = &mut self.archive else {
+            return;
+        };
+        if let Err(err) = archive.write(samples) {
+            error!("Could not archive recording: {err}");
+            emit_input_stream_error(
+                app_handle,
+                AppError::new(ErrorCode::Io, format!("Stopped archiving recording: {err}")),
+            );
+            self.archive = None;
+        }
+    }
+
+    /// Read the audio left after the stream stopped, including the audio still waiting to be
+    /// lined up with the system audio, and write it to the archive.
+    ///
+    /// The archive is left open, see [`finish_archive`].
+    pub fn finish(mut self, app_handle: &AppHandle) -> FinishedCapture {
+        let mut microphone = Vec::new();
+        self.microphone.drain_into(&mut microphone);
+        let mut system = Vec::new();
+        let rest = match &mut self.system {
+            Some(system_audio) => {
+                let mut rest = Vec::new();
+                system_audio.finish_into(&microphone, &mut rest, &mut system);
+                rest
+            }
+            None => microphone,
+        };
+        self.archive_samples(app_handle, &rest);
+        FinishedCapture {
+            rest,
+            system,
+            archive: self.archive,
+        }
+    }
+}
+
+/// Finish the archive of a recording, returning its path
+pub fn finish_archive(app_handle: &AppHandle, archive: Option<AudioArchive>) -> Option<PathBuf> {
+    archive.and_then(|archive| match archive.finish() {
+        Ok(path) => {
+            info!("Archived recording to {}", path.display());
+            Some(path)
+        }
+        Err(err) => {
+            error!("Could not finish archived recording: {err}");
+            emit_input_stream_error(
+                app_handle,
+                AppError::new(ErrorCode::Io, format!("Could not finish archive: {err}")),
+            );
+            None
+        }
+    })
+}
+
+/// Audio left when a [`Capture`] finished
+pub struct FinishedCapture {
+    /// Microphone samples, mixed with the system audio if enabled
+    pub rest: Vec<f32>,
+    /// System audio samples lined up with `rest`, empty without system audio
+    pub system: Vec<f32>,
+    /// Archive holding all the recorded audio, still open
+    pub archive: Option<AudioArchive>,
+}
+
+impl FinishedCapture {
+    /// Add the audio to the recorded data, keeping it within the recording limit
+    pub fn store(
+        self,
+        data: &mut InnerMicrophoneData,
+        limit: Option<&RecordingLimit>,
+    ) -> LimitOutcome {
+        data.0.extend(self.rest);
+        if let Some(track) = &mut data.3 {
+            track.samples.extend(self.system);
+        }
+        limit.map_or(LimitOutcome::WithinLimit, |limit| limit.apply(data))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Why [`drain_capture`] returned
+pub enum CaptureEnd {
+    /// Stopped on request or at the recording limit
+    Stopped,
+    /// Stopped to continue the recording on another device
+    Switched,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Result of applying the recording limit to the recorded audio
+pub enum LimitOutcome {
+    WithinLimit,
+    /// Limit reached, recording must stop
+    Stop,
+    /// Oldest audio was dropped to stay within the limit
+    DroppedOldest,
+}
+
+/// Maximum length of a recording and what happens when it is reached
+pub struct RecordingLimit {
+    max_seconds: u32,
+    max_samples: usize,
+    policy: OverflowPolicy,
+    /// Whether cutting the recording was already reported
+    reported: bool,
+}
+
+impl RecordingLimit {
+    /// Limit of a recording from a stream with the given configuration, `None` if unbounded
+    pub fn new(limits: CaptureLimits, config: &StreamConfig) -> Option<Self> {
+        let max_seconds = limits.max_duration_seconds?;
+        let samples_per_second = config.sample_rate.0 as usize * usize::from(config.channels);
+        Some(Self {
+            max_seconds,
+            max_samples: samples_per_second * max_seconds as usize,
+            policy: limits.overflow.unwrap_or_default(),
+            reported: false,
+        })
+    }
+
+    /// Keep the recorded audio, including the system audio track, within the limit
+    pub fn apply(&self, data: &mut InnerMicrophoneData) -> LimitOutcome {
+        if let Some(track) = &mut data.3 {
+            apply_limit(&mut track.samples, self.max_samples, self.policy);
+        }
+        apply_limit(&mut data.0, self.max_samples, self.policy)
+    }
+
+    /// Emit [`RecordingLimitReachedEvent`] the first time the recording was cut
+    pub fn report(&mut self, app_handle: &AppHandle, outcome: LimitOutcome) {
+        if outcome == LimitOutcome::WithinLimit || self.reported {
+            return;
+        }
+        self.reported = true;
+        let _ = RecordingLimitReachedEvent::with_payload(self.max_seconds, self.policy)
+            .emit(app_handle)
+            .map_err(|err| error!("Error for recording limit event: {err}"));
+    }
+}
+
+/// Keep the recorded samples within `max_samples` following the overflow policy.
+///
+/// Dropping the oldest audio only touches the dropped samples, as the buffer is a ring.
+pub fn apply_limit(
+    samples: &mut VecDeque<f32>,
+    max_samples: usize,
+    policy: OverflowPolicy,
+) -> LimitOutcome {
+    match policy {
+        _ if samples.len() < max_samples => LimitOutcome::WithinLimit,
+        OverflowPolicy::Stop => {
+            samples.truncate(max_samples);
+            LimitOutcome::Stop
+        }
+        OverflowPolicy::DropOldest if samples.len() > max_samples => {
+            samples.drain(..samples.len() - max_samples);
+            LimitOutcome::DroppedOldest
+        }
+        OverflowPolicy::DropOldest => LimitOutcome::WithinLimit,
+    }
+}
+
+/// Drain the ring buffers into the recorded data until a stop is received or the limit is reached.
+///
+/// Pauses and resumes the stream on request. Also records the system audio, emits the input
+/// levels, writes the audio to the archive, and reports dropped samples as input stream errors.
+pub fn drain_capture(
+    app_handle: &AppHandle,
+    capture: &mut Capture,
+    control: &mut Receiver<StreamControl>,
+    stream: &Stream,
+    config: &StreamConfig,
+    limit: &mut Option<RecordingLimit>,
+) -> CaptureEnd {
+    let samples_per_second = config.sample_rate.0 as usize * usize::from(config.channels);
+    let data_state = app_handle.state::<MicrophoneDataState>();
+    let mut meter = LevelMeter::new();
+    let mut block = Vec::with_capacity(samples_per_second);
+    let mut microphone = Vec::with_capacity(samples_per_second);
+    let mut system_block = Vec::new();
+    let mut is_paused = false;
+    loop {
+        let stopping = loop {
+            match control.try_recv() {
+                Ok(StreamControl::Pause) => {
+                    debug!("Pausing input stream");
+                    is_paused = true;
+                    if let Err(err) = stream.pause() {
+                        warn!("Could not pause input stream: {err}");
+                    }
+                }
+                Ok(StreamControl::Resume) => {
+                    debug!("Resuming input stream");
+                    is_paused = false;
+                    // Do not count the pause as silence
+                    meter = LevelMeter::new();
+                    if let Err(err) = stream.play() {
+                        emit_input_stream_error(
+                            app_handle,
+                            AppError::device(format!("Could not resume: {err}")),
+                        );
+                    }
+                }
+                Ok(StreamControl::Stop) | Err(TryRecvError::Disconnected) => {
+                    break Some(CaptureEnd::Stopped)
+                }
+                Ok(StreamControl::Switch) => break Some(CaptureEnd::Switched),
+                Err(TryRecvError::Empty) => break None,
+            }
+        };
+        block.clear();
+        microphone.clear();
+        system_block.clear();
+        capture.microphone.drain_into(&mut microphone);
+        if !is_paused {
+            for update in meter.push(&microphone, Instant::now()) {
+                emit_meter_update(app_handle, update);
+            }
+        }
+        match &mut capture.system {
+            // The system audio keeps playing while the microphone is paused
+            Some(system) if is_paused => system.discard(),
+            Some(system) => {
+                for update in system.mix_into(&microphone, &mut block, &mut system_block) {
+                    emit_meter_update(app_handle, update);
+                }
+            }
+            None => std::mem::swap(&mut block, &mut microphone),
+        }
+        capture.archive_samples(app_handle, &block);
+        let outcome = match data_state.lock() {
+            Ok(mut data) => {
+                data.0.extend(&block);
+                if let Some(system) = &capture.system {
+                    let track = data.3.get_or_insert_with(|| SystemTrack {
+                        samples: VecDeque::new(),
+                        mixed: system.mixes_with_microphone(),
+                    });
+                    track.samples.extend(&system_block);
+                }
+                limit
+                    .as_ref()
+                    .map_or(LimitOutcome::WithinLimit, |limit| limit.apply(&mut data))
+            }
+            Err(err) => {
+                error!("Could not get Microphone Data State lock: {err}");
+                LimitOutcome::WithinLimit
+            }
+        };
+        let overruns = capture.microphone.new_overruns()
+            + capture.system.as_mut().map_or(0, SystemAudio::new_overruns);
+        if overruns > 0 {
+            warn!("Capture buffer overrun, dropped {overruns} samples");
+            emit_input_stream_error(
+                app_handle,
+                AppError::new(
+                    ErrorCode::Internal,
+                    format!("Dropped {overruns} audio samples"),
+                ),
+            );
+        }
+        if let Some(limit) = limit {
+            limit.report(app_handle, outcome);
+        }
+        if outcome == LimitOutcome::Stop {
+            debug!("Recording limit reached, stopping");
+            match app_handle.state::<MicrophoneState>().lock() {
+                Ok(mut mic_state) => drop(mic_state.stream_sender.take()),
+                Err(err) => error!("Could not get mic state lock: {err}"),
+            }
+            update_recording_state(app_handle, RecordingState::Stopped);
+            return CaptureEnd::Stopped;
+        }
+        if let Some(end) = stopping {
+            return end;
+        }
+        std::thread::sleep(DRAIN_INTERVAL);
+    }
+}
+
+/// Replace the buffer's content with the samples converted to `f32`
+pub fn convert_samples<T: Sample>(buffer: &mut Vec<f32>, data: &[T])
+where
+    f32: FromSample<T>,
+{
+    buffer.clear();
+    buffer.extend(data.iter().map(|sample| sample.to_sample::<f32>()));
+}
+
+#[test]
+fn test_convert_samples() {
+    let mut buffer = Vec::new();
+    convert_samples(&mut buffer, &[i16::MIN, 0, i16::MAX]);
+    assert!((buffer[0] + 1.0).abs() < 1e-4);
+    assert!(buffer[1].abs() < 1e-4);
+    assert!((buffer[2] - 1.0).abs() < 1e-4);
+    convert_samples(&mut buffer, &[0_u16, 32_768]);
+    assert_eq!(buffer.len(), 2);
+    assert!((buffer[0] + 1.0).abs() < 1e-4);
+    assert!(buffer[1].abs() < 1e-4);
+}
+
+#[test]
+fn test_supported_config_range() {
+    let supported = SupportedInputConfig {
+        sample_format: InputSampleFormat::I16,
+        channels: 2,
+        min_sample_rate: 8_000,
+        max_sample_rate: 48_000,
+        min_buffer_size: Some(64),
+        max_buffer_size: Some(4_096),
+    };
+    let mut config = InputStreamConfig {
+        sample_format: InputSampleFormat::I16,
+        sample_rate: 16_000,
+        channels: 2,
+        buffer_size: None,
+    };
+    assert!(supported.supports(&config));
+    config.buffer_size = Some(8_192);
+    assert!(!supported.supports(&config));
+    config.buffer_size = Some(256);
+    config.sample_rate = 96_000;
+    assert!(!supported.supports(&config));
+}
+
+#[test]
+fn test_apply_limit() {
+    let mut samples = VecDeque::from(vec![0.0; 9]);
+    assert_eq!(
+        apply_limit(&mut samples, 10, OverflowPolicy::DropOldest),
+        LimitOutcome::WithinLimit
+    );
+    samples.extend([1.0; 2]);
+    assert_eq!(
+        apply_limit(&mut samples, 10, OverflowPolicy::DropOldest),
+        LimitOutcome::DroppedOldest
+    );
+    assert_eq!(samples.len(), 10);
+    assert!((samples[9] - 1.0).abs() < f32::EPSILON);
+    assert_eq!(
+        apply_limit(&mut samples, 8, OverflowPolicy::Stop),
+        LimitOutcome::Stop
+    );
+    assert_eq!(samples.len(), 8);
+}
+
+#[test]
+fn test_capture_ring_counts_overruns() {
+    let config = StreamConfig {
+        channels: 1,
+        sample_rate: SampleRate(4),
+        buffer_size: BufferSize::Default,
+    };
+    let (mut writer, mut reader) = capture_ring(&config);
+    writer.write(&[0.25; 6]);
+    // Only two slots are left, so the whole block is dropped
+    writer.write(&[0.5; 3]);
+    writer.write(&[0.75; 2]);
+    let mut recorded = Vec::new();
+    assert_eq!(reader.drain_into(&mut recorded), 8);
+    assert_eq!(recorded[5..], [0.25, 0.75, 0.75]);
+    assert_eq!(reader.new_overruns(), 3);
+    assert_eq!(reader.new_overruns(), 0);
+}
+
+#[test]
+fn test_capture_ring_has_no_overruns_under_load() {
+    use std::sync::atomic::AtomicBool;
+
+    const BLOCKS: usize = 400;
+    const BLOCK_SIZE: usize = 480;
+    let config = StreamConfig {
+        channels: 1,
+        sample_rate: SampleRate(48_000),
+        buffer_size: BufferSize::Default,
+    };
+    let (mut writer, mut reader) = capture_ring(&config);
+    // Keep other cores busy, like a machine running a model at the same time
+    let busy = Arc::new(AtomicBool::new(true));
+    let load = (0..4)
+        .map(|_| {
+            let busy = Arc::clone(&busy);
+            std::thread::spawn(move || {
+                let mut x = 0_u64;
+                while busy.load(Ordering::Relaxed) {
+                    x = x.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+                }
+                x
+            })
+        })
+        .collect::<Vec<_>>();
+    // Deliver 10ms blocks four times faster than a real device
+    let producer = std::thread::spawn(move || {
+        let block = [0.25_f32; BLOCK_SIZE];
+        for _ in 0..BLOCKS {
+            writer.write(&block);
+            std::thread::sleep(Duration::from_micros(2_500));
+        }
+    });
+    let mut recorded = Vec::new();
+    let start = Instant::now();
+    while recorded.len() < BLOCKS * BLOCK_SIZE && start.elapsed() < Duration::from_secs(10) {
+        reader.drain_into(&mut recorded);
+        std::thread::sleep(DRAIN_INTERVAL);
+    }
+    producer.join().expect("Producer should finish");
+    busy.store(false, Ordering::Relaxed);
+    for thread in load {
+        let _ = thread.join();
+    }
+    reader.drain_into(&mut recorded);
+    assert_eq!(reader.new_overruns(), 0);
+    assert_eq!(recorded.len(), BLOCKS * BLOCK_SIZE);
+}
+
+#[test]
+fn test_limit_applies_to_samples_drained_at_stop() {
+    let config = StreamConfig {
+        channels: 2,
+        sample_rate: SampleRate(4),
+        buffer_size: BufferSize::Default,
+    };
+    let limits = CaptureLimits {
+        max_duration_seconds: None,
+        overflow: None,
+    };
+    assert!(RecordingLimit::new(limits, &config).is_none());
+    let limits = CaptureLimits {
+        max_duration_seconds: Some(2),
+        ..limits
+    };
+    let limit = RecordingLimit::new(limits, &config).expect("Limit should be set");
+    let mut data = InnerMicrophoneData::new();
+    data.0.extend([0.0; 14]);
+    data.3 = Some(SystemTrack {
+        samples: VecDeque::from(vec![0.0; 14]),
+        mixed: false,
+    });
+    assert_eq!(limit.apply(&mut data), LimitOutcome::WithinLimit);
+    let finished = FinishedCapture {
+        rest: vec![0.5; 5],
+        system: vec![0.5; 5],
+        archive: None,
+    };
+    assert_eq!(finished.store(&mut data, Some(&limit)), LimitOutcome::Stop);
+    assert_eq!(data.0.len(), 16);
+    assert_eq!(data.3.map(|track| track.samples.len()), Some(16));
+}
+
+#[test]
+fn test_audio_archive_round_trip() {
+    let directory = std::env::temp_dir().join(format!("archive-test-{}", std::process::id()));
+    let config = StreamConfig {
+        channels: 2,
+        sample_rate: SampleRate(8_000),
+        buffer_size: BufferSize::Default,
+    };
+    let mut archive = AudioArchive::create(&directory, &config).expect("Archive should be created");
+    let samples = (0..20_000)
+        .map(|i| f32::from(i16::try_from(i % 200).unwrap_or_default()) / 200.0)
+        .collect::<Vec<_>>();
+    for block in samples.chunks(1_000) {
+        archive.write(block).expect("Samples should be written");
+    }
+    let path = archive.finish().expect("Archive should be finished");
+    let mut reader = audrey::hound::WavReader::open(&path).expect("Archive should be readable");
+    assert_eq!(reader.spec().channels, 2);
+    assert_eq!(reader.spec().sample_rate, 8_000);
+    let read = reader
+        .samples::<f32>()
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Samples should be readable");
+    assert_eq!(read, samples);
+    let _ = std::fs::remove_dir_all(directory);
+}
@@ -0,0 +1,293 @@
+//! Transcription of long audio in chunks.
+//!
+//! The audio is split at pauses where possible, and into overlapping fixed windows otherwise.
+//! Every chunk is prompted with the end of the text so far, and its segments are shifted into
+//! global time, dropping the segments and words repeated from the overlap.
+
+use crate::{
+    history::{combine_prompt, tail_of},
+    transcript::{Transcript, Utterance},
+    types::ChunkingOptions,
+};
+use log::{debug, trace};
+use std::{ops::Range, time::Duration};
+
+/// Sample rate of the audio given to whisper
+const SAMPLE_RATE: usize = 16_000;
+
+/// Timestamps are in hundredths of a second
+const SAMPLES_PER_TIMESTAMP: usize = SAMPLE_RATE / 100;
+
+/// Default maximum length of a chunk, the window whisper works on
+const DEFAULT_WINDOW_SECONDS: u32 = 30;
+
+/// Default length of the overlap of chunks split without a pause
+const DEFAULT_OVERLAP_SECONDS: u32 = 2;
+
+/// Part at the end of a window searched for a pause, in seconds
+const PAUSE_SEARCH_SECONDS: usize = 5;
+
+/// Length of the frames compared when searching for a pause (100 ms)
+const PAUSE_FRAME: usize = SAMPLE_RATE / 10;
+
+/// RMS below which a frame counts as a pause
+const PAUSE_RMS: f32 = 0.01;
+
+/// Characters of the previous text carried into the prompt of the next chunk
+const CARRIED_PROMPT_CHARACTERS: usize = 200;
+
+/// Split 16 kHz mono audio into chunks of at most the window length.
+///
+/// Each chunk ends at the quietest pause near the end of its window. Without a pause it ends at
+/// the window, and the next chunk starts the overlap earlier so no word is lost at the cut.
+pub fn plan_chunks(audio: &[f32], options: ChunkingOptions) -> Vec<Range<usize>> {
+    let window_seconds = options
+        .window_seconds
+        .unwrap_or(DEFAULT_WINDOW_SECONDS)
+        .max(1);
+    let window = window_seconds as usize * SAMPLE_RATE;
+    let overlap = options
+        .overlap_seconds
+        .unwrap_or(DEFAULT_OVERLAP_SECONDS)
+        .min(window_seconds / 2) as usize
+        * SAMPLE_RATE;
+    let search = (PAUSE_SEARCH_SECONDS * SAMPLE_RATE).min(window / 4);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start + window < audio.len() {
+        let end = start + window;
+        let (cut, next) = find_pause(&audio[end - search..end]).map_or_else(
+            || (end, end - overlap),
+            |pause| (end - search + pause, end - search + pause),
+        );
+        chunks.push(start..cut);
+        start = next;
+    }
+    chunks.push(start..audio.len());
+    debug!("Split {} samples into {} chunks", audio.len(), chunks.len());
+    chunks
+}
+
+/// Offset of the middle of the quietest frame, if quiet enough to be a pause
+fn find_pause(audio: &[f32]) -> Option<usize> {
+    #[allow(clippy::cast_precision_loss, reason = "Frames are short")]
+    let (index, rms) = audio
+        .chunks_exact(PAUSE_FRAME)
+        .map(|frame| (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt())
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+    (rms < PAUSE_RMS).then_some(index * PAUSE_FRAME + PAUSE_FRAME / 2)
+}
+
+/// Transcribe the chunks one after the other and stitch them into one transcript.
+///
+/// `transcribe` gets the audio and prompt of each chunk. `on_chunk` gets the chunk's index and
+/// its new utterances in global time, as soon as the chunk is done.
+pub fn transcribe_in_chunks<E>(
+    audio: &[f32],
+    chunks: &[Range<usize>],
+    initial_prompt: Option<&str>,
+    mut transcribe: impl FnMut(&[f32], Option<&str>) -> Result<Transcript, E>,
+    mut on_chunk: impl FnMut(usize, &[Utterance]),
+) -> Result<Transcript, E> {
+    let mut stitched = Transcript {
+        processing_time: Duration::ZERO,
+        utterances: Vec::new(),
+        word_utterances: None,
+        language: None,
+    };
+    for (index, chunk) in chunks.iter().enumerate() {
+        let context = stitched.as_text().replace('\n', " ");
+        let prompt = combine_prompt(
+            initial_prompt,
+            Some(tail_of(context.trim(), CARRIED_PROMPT_CHARACTERS))
+                .filter(|tail| !tail.is_empty()),
+        );
+        trace!("Transcribing chunk {index} ({chunk:?}) with prompt {prompt:?}");
+        let part = transcribe(&audio[chunk.clone()], prompt.as_deref())?;
+        let offset = i64::try_from(chunk.start / SAMPLES_PER_TIMESTAMP).unwrap_or(i64::MAX);
+        let known = stitched.utterances.len();
+        let end = stitched.utterances.last().map(|last| last.stop);
+        stitch(&mut stitched.utterances, shift(part.utterances, offset));
+        if let Some(words) = part.word_utterances {
+            stitched
+                .word_utterances
+                .get_or_insert_with(Vec::new)
+                .extend(
+                    shift(words, offset)
+                        .into_iter()
+                        .filter(|word| end.is_none_or(|end| word.start >= end)),
+                );
+        }
+        stitched.processing_time += part.processing_time;
+        stitched.language = stitched.language.or(part.language);
+        on_chunk(
+            index,
+            &stitched.utterances[known.min(stitched.utterances.len())..],
+        );
+    }
+    Ok(stitched)
+}
+
+/// Move the utterances of a chunk into global time
+fn shift(mut utterances: Vec<Utterance>, offset: i64) -> Vec<Utterance> {
+    for utterance in &mut utterances {
+        utterance.start += offset;
+        utterance.stop += offset;
+    }
+    utterances
+}
+
+/// Append the utterances of the next chunk, skipping what was already transcribed in the overlap.
+///
+/// Utterances ending before the current end are dropped. From an utterance crossing the current
+/// end, the words repeating the end of the text so far are removed.
+fn stitch(utterances: &mut Vec<Utterance>, next: Vec<Utterance>) {
+    let Some(end) = utterances.last().map(|last| last.stop) else {
+        utterances.extend(next);
+        return;
+    };
+    let mut next = next
+        .into_iter()
+        .filter(|utterance| utterance.stop > end)
+        .filter(|utterance| !utterance.text.trim().is_empty())
+        .peekable();
+    if let Some(first) = next.next_if(|first| first.start < end) {
+        let previous = utterances
+            .iter()
+            .rev()
+            .take(2)
+            .rev()
+            .map(|utterance| utterance.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let text = strip_repeated_words(&previous, &first.text);
+        if !text.is_empty() {
+            utterances.push(Utterance {
+                start: end.max(first.start),
+                text,
+                ..first
+            });
+        }
+    }
+    utterances.extend(next);
+}
+
+/// Remove the longest start of `text` that repeats the end of `previous`, comparing words only
+fn strip_repeated_words(previous: &str, text: &str) -> String {
+    let normalize = |word: &str| {
+        word.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect::<String>()
+    };
+    let previous = previous
+        .split_whitespace()
+        .map(normalize)
+        .collect::<Vec<_>>();
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    let repeated = (1..=words.len().min(previous.len()))
+        .rev()
+        .find(|&count| {
+            previous[previous.len() - count..]
+                .iter()
+                .zip(&words[..count])
+                .all(|(previous, word)| *previous == normalize(word))
+        })
+        .unwrap_or(0);
+    words[repeated..].join(" ")
+}
+
+#[cfg(test)]
+fn utterance(start: i64, stop: i64, text: &str) -> Utterance {
+    Utterance {
+        start,
+        stop,
+        text: text.into(),
+        speaker: None,
+        hallucination: None,
+        confidence: None,
+        alternatives: Vec::new(),
+        source: None,
+    }
+}
+
+#[test]
+fn test_plan_chunks_prefers_pauses() {
+    let options = ChunkingOptions {
+        window_seconds: Some(10),
+        overlap_seconds: Some(1),
+    };
+    // Speech everywhere, except a pause around 8.5 s
+    let mut audio = vec![0.5; 25 * SAMPLE_RATE];
+    audio[8 * SAMPLE_RATE..9 * SAMPLE_RATE].fill(0.0);
+    let chunks = plan_chunks(&audio, options);
+    assert_eq!(chunks.len(), 3);
+    assert!(chunks[0].end > 8 * SAMPLE_RATE && chunks[0].end < 9 * SAMPLE_RATE);
+    assert_eq!(chunks[1].start, chunks[0].end);
+    // No pause in the second window, so the third chunk overlaps it
+    assert_eq!(chunks[1].end, chunks[1].start + 10 * SAMPLE_RATE);
+    assert_eq!(chunks[2].start, chunks[1].end - SAMPLE_RATE);
+    assert_eq!(chunks[2].end, audio.len());
+    assert_eq!(
+        plan_chunks(&audio[..SAMPLE_RATE], options),
+        [0..SAMPLE_RATE]
+    );
+}
+
+#[test]
+fn test_stitch_removes_overlap() {
+    let mut utterances = vec![utterance(0, 900, " The quick brown fox jumps")];
+    stitch(
+        &mut utterances,
+        vec![
+            utterance(800, 850, " fox"),
+            utterance(850, 1_000, " Brown fox jumps over the"),
+            utterance(1_000, 1_200, " lazy dog."),
+        ],
+    );
+    let texts = utterances
+        .iter()
+        .map(|utterance| utterance.text.trim())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        texts,
+        ["The quick brown fox jumps", "over the", "lazy dog."]
+    );
+    assert_eq!(utterances[1].start, 900);
+}
+
+#[test]
+fn test_transcribe_in_chunks_carries_prompt_and_shifts_time() {
+    let audio = vec![0.0; 4 * SAMPLE_RATE];
+    let chunks = [0..2 * SAMPLE_RATE, 2 * SAMPLE_RATE..4 * SAMPLE_RATE];
+    let mut prompts = Vec::new();
+    let mut progress = Vec::new();
+    let transcript = transcribe_in_chunks(
+        &audio,
+        &chunks,
+        Some("Names: Ada."),
+        |chunk, prompt| {
+            prompts.push(prompt.map(str::to_string));
+            Ok::<_, ()>(Transcript {
+                processing_time: Duration::from_secs(1),
+                utterances: vec![utterance(0, 200, &format!(" {} samples", chunk.len()))],
+                word_utterances: None,
+                language: None,
+            })
+        },
+        |index, new| progress.push((index, new.len())),
+    )
+    .expect("Fake transcription never fails");
+    assert_eq!(
+        prompts,
+        [
+            Some("Names: Ada.".to_string()),
+            Some("Names: Ada. 32000 samples".to_string())
+        ]
+    );
+    assert_eq!(progress, [(0, 1), (1, 1)]);
+    assert_eq!(transcript.utterances[1].start, 200);
+    assert_eq!(transcript.utterances[1].stop, 400);
+    assert_eq!(transcript.processing_time, Duration::from_secs(2));
+}
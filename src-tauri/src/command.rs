@@ -7,29 +7,50 @@
 
 // Crate level use (imports)
 use crate::{
+    benchmark::{self, BenchmarkReport},
+    capture::{
+        build_input_stream, capture_ring, drain_capture, finish_archive, resolve_stream_config,
+        supported_configs, AudioArchive, Capture, CaptureEnd, RecordingLimit,
+    },
+    confidence::transcript_confidence,
+    devices::{find_input_device, input_device_names, missing_input_device, select_input_device},
+    error::{AppError, ErrorCode},
     events::{
-        new_lossy_transcript_segment_event, new_transcript_segment_event, MouseClickEvent,
-        TranscriptionProgressEvent,
+        emit_input_stream_error, emit_recording_state, new_lossy_transcript_segment_event,
+        new_transcript_segment_event, ActiveProfileChangedEvent, LanguageDetectedEvent,
+        MouseClickEvent, MouseReleaseEvent, TranscriptionProgressEvent,
     },
-    mutter::ModelError,
+    history::{combine_prompt, CorrectionCount, TranscriptHistoryState},
+    hotkeys::{HotkeyBinding, HotkeyState},
+    language::should_detect,
+    loopback::{merge_source_transcripts, monitor_sources, SourceEnergies, SystemAudio},
+    mutter::TranscribeCallbacks,
+    output::{send_text, send_to_target, OutputSink, PasteShortcut, SystemOutputSink},
+    profiles::{ActiveProfile, ApplicationProfile, ProfileState, WindowInfo},
+    server::{ApiServerInfo, ApiServerState},
+    settings::{self, ApiServerSettings, Settings, SettingsState},
     types::{
-        AppState, AudioProcessingOptions, MicrophoneDataState, MicrophoneState, MouseButtonType,
-        SoundMapState, SystemInfo, TextPostProcessing, TextProcessOptions, TranscribeOptions,
+        AppState, AudioProcessingOptions, BenchmarkOptions, CaptureLimits, DictationOptions,
+        InputStreamConfig, MicrophoneDataState, MicrophoneState, MouseButtonType, OutputMethod,
+        OutputOptions, OutputTarget, RecordingState, SoundMapState, StreamControl,
+        SupportedInputConfig, SystemAudioOptions, SystemInfo, TextPostProcessing,
+        TextProcessOptions, TranscribeOptions, TranscriptionResult,
     },
-    utils::change_send_to_sentry,
+    utils::{change_send_to_sentry, gpu_supported},
 };
-use enigo::{Enigo, Keyboard, Settings};
 use log::{debug, error, info, trace, warn};
 use mouce::{common::MouseEvent, Mouse, MouseActions};
-use rodio::{
-    cpal::traits::{HostTrait, StreamTrait},
-    Decoder, DeviceTrait, OutputStream, Sink,
+use rodio::{cpal::traits::StreamTrait, Decoder, DeviceTrait, OutputStream, Sink};
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    time::Duration,
 };
-use std::{fs::File, io::BufReader, time::Duration};
 use sysinfo::{CpuRefreshKind, MemoryRefreshKind, RefreshKind, System};
-use tauri::{AppHandle, Manager, State, Wry};
+use tauri::{async_runtime::Sender, AppHandle, Manager, State, Wry};
 use tauri_specta::{collect_commands, Commands, Event};
-use whisper_rs::SegmentCallbackData;
+use tokio::sync::oneshot;
 
 #[tauri::command]
 #[specta::specta]
@@ -42,8 +63,9 @@ pub async fn transcribe(
     audio_data: Vec<u8>,
     whisper_options: Option<TranscribeOptions>,
     decode_options: Option<AudioProcessingOptions>,
-) -> Result<(String, f64), String> {
-    let options = whisper_options.unwrap_or_default();
+) -> Result<(String, f64), AppError> {
+    let settings = settings::current(&app_handle);
+    let options = settings.transcribe_options(whisper_options);
     log::info!("Transcribing with parameters: translate={:?}, use_timestamp={:?}, threads={:?}, prompt={:?}, lang={:?}, fmt={:?}, patience={:?}",
         options.translate,
         options.individual_word_timestamps,
@@ -57,82 +79,49 @@ pub async fn transcribe(
     let app_state = app_state.lock().map_err(|err| err.to_string())?;
     let model = app_state.get_model();
     info!("Transcribe using {}", app_state.get_model_info());
-    trace!("Creating abort transcription callback");
-    let abort_callback: Option<fn() -> bool> =
-        if options.include_callback.is_some_and(|is_true| is_true) {
+    let callbacks = if options.include_callback.is_some_and(|is_true| is_true) {
+        trace!("Creating transcription callbacks");
+        let progress_handle = app_handle.clone();
+        let lossy_segment_handle = app_handle.clone();
+        let segment_handle = app_handle.clone();
+        TranscribeCallbacks {
             // TODO: Figure out how to send off via an event from JS side
-            Some(|| {
+            abort: Some(Box::new(|| {
                 trace!("Evaluating abort transcription => false");
                 false
-            })
-        } else {
-            None
-        };
-    let progress_callback = if options.include_callback.is_some_and(|is_true| is_true) {
-        trace!("Creating transcript progress callback");
-        let handle = app_handle.clone();
-        trace!("Cloned app handle");
-        Some(move |precentage| {
-            trace!("Creating transcription progress event");
-            let event = TranscriptionProgressEvent::with_payload(precentage);
-            trace!("Emitting transcription progress event");
-            let _ = event
-                .emit(&handle)
-                .map_err(|err| error!("Transcription Progress event error: {err}"));
-        })
-    } else {
-        None
-    };
-    let lossy_segment_callback = if options.include_callback.is_some_and(|is_true| is_true) {
-        let handle = app_handle.clone();
-        Some(move |segment: whisper_rs::SegmentCallbackData| {
-            let _ = new_lossy_transcript_segment_event(segment)
-                .emit(&handle)
-                .map_err(|err| error!("Transcription Segment event error: {err}"));
-        })
-    } else {
-        None
-    };
-    let not_lossy_segment_callback = if options.include_callback.is_some_and(|is_true| is_true) {
-        #[allow(
-            clippy::redundant_clone,
-            reason = "May want to use app handle later on"
-        )]
-        let handle = app_handle.clone();
-        Some(move |segment: whisper_rs::SegmentCallbackData| {
-            let _ = new_transcript_segment_event(segment)
-                .emit(&handle)
-                .map_err(|err| error!("Transcription Segment event error: {err}"));
-        })
+            })),
+            progress: Some(Box::new(move |precentage| {
+                trace!("Creating transcription progress event");
+                let event = TranscriptionProgressEvent::with_payload(precentage);
+                trace!("Emitting transcription progress event");
+                let _ = event
+                    .emit(&progress_handle)
+                    .map_err(|err| error!("Transcription Progress event error: {err}"));
+            })),
+            new_segment_lossy: Some(Box::new(move |segment| {
+                let _ = new_lossy_transcript_segment_event(segment)
+                    .emit(&lossy_segment_handle)
+                    .map_err(|err| error!("Transcription Segment event error: {err}"));
+            })),
+            new_segment: Some(Box::new(move |segment| {
+                let _ = new_transcript_segment_event(segment)
+                    .emit(&segment_handle)
+                    .map_err(|err| error!("Transcription Segment event error: {err}"));
+            })),
+        }
     } else {
-        None
+        TranscribeCallbacks::default()
     };
     let transcription = model
         .transcribe_audio(
             &audio_data,
-            options.translate.unwrap_or(false),
-            options.individual_word_timestamps.unwrap_or(false),
-            options.initial_prompt.as_deref(),
-            options.language.as_deref(),
-            // Make sure not to pass 0 for CPU thread,
-            // otherwise model crashes
-            match options.threads {
-                Some(0) => None,
-                threads => threads,
-            },
-            options.patience,
-            decode_options.unwrap_or_default(),
-            abort_callback,
-            progress_callback,
-            lossy_segment_callback,
-            not_lossy_segment_callback,
+            &options,
+            settings.decode_options(decode_options),
+            callbacks,
         )
         .map_err(|err| {
             log::error!("Transcription Error: {err:?}");
-            match err {
-                ModelError::WhisperError(whisper_error) => whisper_error.to_string(),
-                ModelError::DecodingError(decoder_error) => decoder_error.to_string(),
-            }
+            AppError::from(err)
         })?;
     Ok((
         options
@@ -147,12 +136,13 @@ pub async fn transcribe(
 #[specta::specta]
 /// Process the text
 pub async fn process_text(
+    app_handle: AppHandle,
     text: String,
     options: Option<TextProcessOptions>,
-) -> Result<(String, f64), String> {
+) -> Result<(String, f64), AppError> {
     info!("Running processing text command");
     let mut updated_text = text;
-    let options = options.unwrap_or_default();
+    let options = settings::current(&app_handle).text_process_options(options);
     log::info!("Processing text with parameters: replace_inter_sentence_newlines={:?}, removed_words={:?}, decorated_words={:?}", 
         options.removed_words,
         options.decorated_words,
@@ -186,17 +176,17 @@ pub async fn transcribe_with_post_process(
     transcribe_options: Option<TranscribeOptions>,
     processing_options: Option<TextProcessOptions>,
     decode_options: Option<AudioProcessingOptions>,
-) -> Result<(String, f64), String> {
+) -> Result<(String, f64), AppError> {
     info!("Running transcription & processing command");
     let (text, transcription_time) = transcribe(
         app_state,
-        app_handle,
+        app_handle.clone(),
         audio_data,
         transcribe_options,
         decode_options,
     )
     .await?;
-    let (new_text, processing_time) = process_text(text, processing_options).await?;
+    let (new_text, processing_time) = process_text(app_handle, text, processing_options).await?;
     Ok((new_text, transcription_time + processing_time))
 }
 
@@ -206,21 +196,43 @@ pub async fn transcribe_with_post_process(
 pub async fn play_sound(
     app_state: State<'_, SoundMapState>,
     sound_name: String,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     info!("Running play sound command");
     // Get sound source
-    let source = app_state
+    let path = app_state
         .lock()
         .map_err(|err| err.to_string())?
         .get_sound_path(&sound_name)
-        .ok_or_else(|| format!("Could not find sound with name: {sound_name}"))
-        .and_then(|path| File::open(path).map_err(|err| err.to_string()))
-        .map(BufReader::new)
-        .and_then(|file| Decoder::new(file).map_err(|err| err.to_string()))?;
+        .cloned()
+        .ok_or_else(|| {
+            AppError::new(
+                ErrorCode::NotFound,
+                format!("Could not find sound with name: {sound_name}"),
+            )
+            .with_hint("Use one of the sounds alert, start, stop or finish.")
+        })?;
+    let file = File::open(&path).map_err(|err| match err.kind() {
+        std::io::ErrorKind::NotFound => AppError::new(
+            ErrorCode::NotFound,
+            format!("Sound file {} is missing", path.display()),
+        )
+        .with_hint("Reinstall the app to restore its sounds."),
+        _ => AppError::from(err),
+    })?;
+    let source = Decoder::new(BufReader::new(file)).map_err(|err| {
+        AppError::new(
+            ErrorCode::Decode,
+            format!("Could not decode sound file {}: {err}", path.display()),
+        )
+    })?;
 
     // Create sound player
-    let (_stream, sound_handle) = OutputStream::try_default().map_err(|err| err.to_string())?;
-    let sink = Sink::try_new(&sound_handle).map_err(|err| err.to_string())?;
+    let output_error = |err: &dyn std::fmt::Display| {
+        AppError::new(ErrorCode::Device, format!("Could not play sound: {err}"))
+            .with_hint("Connect or select an audio output device.")
+    };
+    let (_stream, sound_handle) = OutputStream::try_default().map_err(|err| output_error(&err))?;
+    let sink = Sink::try_new(&sound_handle).map_err(|err| output_error(&err))?;
 
     // Play sound on new thread to prevent app pauses
     sink.append(source);
@@ -231,10 +243,11 @@ pub async fn play_sound(
 
 /// Function to listen for any clicks from mouse and emits Tauri event.
 ///
-/// Returns either the callback id or an error message.
+/// Returns either the callback id or an error, most likely because the system denied access to
+/// the mouse events.
 ///
 /// Adapted from <https://github.com/crabnebula-dev/koi-pond/blob/main/src-tauri/src/lib.rs> under MIT License
-pub fn listen_for_mouse_click(app_handle: AppHandle) -> Result<u8, String> {
+pub fn listen_for_mouse_click(app_handle: AppHandle) -> Result<u8, AppError> {
     Mouse::new()
         .hook(Box::new(move |e| match e {
             MouseEvent::Press(button) => MouseClickEvent::with_payload(MouseButtonType::from(button))
@@ -243,7 +256,12 @@ pub fn listen_for_mouse_click(app_handle: AppHandle) -> Result<u8, String> {
                     error!("App Handle expected to emit press event with button playload but could not: {e}");
                 })
                 .unwrap_or_default(),
-            MouseEvent::Release(_button) => { /* Do Nothing Yet */ }
+            MouseEvent::Release(button) => MouseReleaseEvent::with_payload(MouseButtonType::from(button))
+                .emit(&app_handle)
+                .map_err(|e| {
+                    error!("App Handle expected to emit release event with button playload but could not: {e}");
+                })
+                .unwrap_or_default(),
             // TODO: Remove
             // MouseEvent::AbsoluteMove(x, y) =>  MouseMoveEvent::with_payload(*x, *y).emit(&app_handle).map_err(|e| {
             //     error!("App Handle expected to emit mouse move event but could not: {e}");
@@ -251,57 +269,34 @@ pub fn listen_for_mouse_click(app_handle: AppHandle) -> Result<u8, String> {
             // .unwrap_or_default(),
             _ => (),
         }))
-        .map_err(|err| err.to_string())
+        .map_err(|err| AppError::new(ErrorCode::Permission, err))
 }
 
 #[tauri::command]
 #[specta::specta]
-/// Paste text from clipboard
-pub async fn write_text(text: String) -> Result<(), String> {
+/// Type the text into the focused application
+pub async fn write_text(app_handle: AppHandle, text: String) -> Result<(), AppError> {
     info!("Running auto-write text command");
-    let mut enigo = Enigo::new(&Settings::default()).unwrap();
-    trace!("Enigo setup: {enigo:?}");
-    enigo.text(&text).map_err(|e| e.to_string())?;
-    // Use len rather then actual text to prevent leaking info in logs
-    trace!("Enigo Wrote {} bytes", text.len());
-    Ok(())
+    output_text(
+        app_handle,
+        text,
+        Some(OutputOptions {
+            method: Some(OutputMethod::Type),
+            target: Some(OutputTarget::FocusedApplication),
+            ..Default::default()
+        }),
+    )
+    .await
 }
 
 #[tauri::command]
 #[specta::specta]
 /// Paste text from clipboard
-pub fn paste_text() -> Result<(), String> {
+pub fn paste_text(app_handle: AppHandle) -> Result<(), AppError> {
     info!("Running paste from clipboard command");
-    let mut enigo = Enigo::new(&Settings::default()).unwrap();
-    trace!("Enigo setup: {enigo:?}");
-    let cmd_or_ctrl = match std::env::consts::OS {
-        "macos" => enigo::Key::Meta,
-        "windows" | "linux" => enigo::Key::Control,
-        _ => {
-            error!("Pasting from an unsupported/unknown target");
-            return Err("Unsupported action for your machine!".to_string());
-        }
-    };
-    enigo
-        .key(cmd_or_ctrl, enigo::Direction::Press)
-        .map_err(|e| {
-            error!("Input error: {e}");
-            e.to_string()
-        })?;
-    enigo
-        .key(enigo::Key::Unicode('v'), enigo::Direction::Click)
-        .map_err(|e| {
-            error!("Input error: {e}");
-            e.to_string()
-        })?;
-    enigo
-        .key(cmd_or_ctrl, enigo::Direction::Release)
-        .map_err(|e| {
-            error!("Input error: {e}");
-            e.to_string()
-        })?;
-    trace!("Enigo Pasted text");
-    Ok(())
+    SystemOutputSink::new(&app_handle)
+        .and_then(|mut sink| sink.paste(PasteShortcut::Standard))
+        .map_err(AppError::from)
 }
 
 #[tauri::command]
@@ -310,13 +305,13 @@ pub fn paste_text() -> Result<(), String> {
 pub async fn set_window_top(
     webview_window: tauri::WebviewWindow,
     override_value: Option<bool>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     info!("Running set window float command");
     webview_window
         .set_always_on_top(override_value.unwrap_or(true))
         .map_err(|err| {
             log::error!("Could not set window to top value: {err}");
-            err.to_string()
+            AppError::from(err)
         })
 }
 
@@ -324,20 +319,19 @@ pub async fn set_window_top(
 #[specta::specta]
 /// Update the custom model information
 pub async fn update_model(
+    app_handle: AppHandle,
     app_state: State<'_, AppState>,
     path: Option<String>,
     use_gpu: Option<bool>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     info!("Updating model to use");
+    let use_gpu = use_gpu
+        .or(settings::current(&app_handle).use_gpu)
+        .unwrap_or_else(gpu_supported);
     let mut app_state = app_state.lock().map_err(|err| err.to_string())?;
     if let Some(path) = path {
         info!("Replacing Custom Model");
-        app_state
-            .replace_custom_model(
-                path,
-                use_gpu.unwrap_or(cfg!(any(feature = "vulkan", target_os = "macos"))),
-            )
-            .map_err(|err| err.to_string())?;
+        app_state.replace_custom_model(path, use_gpu)?;
     } else {
         info!("Removing Custom Model");
         app_state.remove_custom_model();
@@ -410,10 +404,120 @@ pub async fn get_system_info() -> SystemInfo {
     }
 }
 
+#[tauri::command]
+#[specta::specta]
+/// Transcribe a reference clip with several thread counts, beam sizes and GPU settings,
+/// measuring the real-time factor and word error rate of each.
+///
+/// Recommends the fastest settings about as accurate as the most accurate ones, and applies
+/// them to hotkey dictation and the model if asked to.
+pub async fn run_benchmark(
+    app_handle: AppHandle,
+    options: Option<BenchmarkOptions>,
+) -> Result<BenchmarkReport, AppError> {
+    let options = options.unwrap_or_default();
+    let (audio, reference, language, source) = match (&options.audio_path, &options.reference_text)
+    {
+        (Some(path), Some(text)) => {
+            if text.trim().is_empty() {
+                return Err(AppError::new(
+                    ErrorCode::InvalidInput,
+                    "Benchmark reference text is empty",
+                )
+                .with_hint("Give the text spoken in the benchmark audio."));
+            }
+            let audio = std::fs::read(path).map_err(|err| match err.kind() {
+                std::io::ErrorKind::NotFound => AppError::new(
+                    ErrorCode::NotFound,
+                    format!("Benchmark audio {path} does not exist"),
+                )
+                .with_hint("Give the path of an existing recording, or none for a synthetic clip."),
+                _ => AppError::new(
+                    ErrorCode::Io,
+                    format!("Could not read benchmark audio {path}: {err}"),
+                ),
+            })?;
+            (
+                crate::mutter::decode(audio)?,
+                Some(text.clone()),
+                options.language.clone(),
+                path.clone(),
+            )
+        }
+        (None, None) => (
+            benchmark::synthetic_clip(benchmark::SYNTHETIC_CLIP_SECONDS),
+            None,
+            options.language.clone().or_else(|| Some("en".into())),
+            "the synthetic clip".into(),
+        ),
+        _ => {
+            return Err(AppError::new(
+                ErrorCode::InvalidInput,
+                "Benchmark audio and reference text must be given together",
+            ))
+        }
+    };
+    let loaded_uses_gpu = settings::current(&app_handle)
+        .use_gpu
+        .unwrap_or_else(gpu_supported);
+    let configurations = benchmark::configurations(
+        &options,
+        u16::try_from(num_cpus::get()).unwrap_or(u16::MAX),
+        gpu_supported(),
+    );
+    info!(
+        "Benchmarking {} configurations on {source}",
+        configurations.len()
+    );
+    let handle = app_handle.clone();
+    let results = tauri::async_runtime::spawn_blocking(move || {
+        // Transcriptions wait for the benchmark, which uses the loaded model
+        let state = handle.state::<AppState>();
+        let app_state = state.lock().map_err(|err| err.to_string())?;
+        benchmark::run(
+            (app_state.get_model(), loaded_uses_gpu),
+            app_state.get_model_path(),
+            &audio,
+            reference.as_deref(),
+            language.as_deref(),
+            &configurations,
+        )
+        .map_err(|err| {
+            error!("Benchmark Error: {err:?}");
+            AppError::from(err)
+        })
+    })
+    .await
+    .map_err(|err| AppError::new(ErrorCode::Internal, format!("Benchmark task failed: {err}")))??;
+    let recommended = benchmark::recommend(&results);
+    info!("Recommended settings: {recommended:?}");
+    let applied = match recommended {
+        Some(configuration) if options.apply.unwrap_or(false) => {
+            // Reloads the model when the GPU setting changed
+            settings::update(&app_handle, |settings| {
+                let transcribe_options = settings
+                    .dictation
+                    .transcribe_options
+                    .get_or_insert_with(TranscribeOptions::default);
+                transcribe_options.threads = Some(configuration.threads);
+                transcribe_options.beam_size = Some(configuration.beam_size);
+                settings.use_gpu = Some(configuration.use_gpu);
+            })?;
+            true
+        }
+        _ => false,
+    };
+    Ok(BenchmarkReport {
+        results,
+        recommended,
+        applied,
+    })
+}
+
 #[tauri::command]
 #[specta::specta]
 /// Initialize/De-initialize the sentry plugin depending on the toggled value
-pub async fn sentry_crash_reporter_update(enable: bool) -> Result<(), String> {
+pub async fn sentry_crash_reporter_update(enable: bool) -> Result<(), AppError> {
     change_send_to_sentry(enable);
     Ok(())
 }
@@ -421,8 +525,23 @@ pub async fn sentry_crash_reporter_update(enable: bool) -> Result<(), String> {
 #[tauri::command]
 #[specta::specta]
 /// Start recording microphone from backend, creating a new thread to listen for audio data.
-pub async fn start_microphone_recording(app_handle: AppHandle) -> Result<bool, String> {
-    let (tx, mut rx) = tauri::async_runtime::channel(1);
+pub async fn start_microphone_recording(app_handle: AppHandle) -> Result<bool, AppError> {
+    activate_profile_for_focused_window(&app_handle);
+    // Left behind by a recording that could not continue after a device switch
+    let continued = app_handle
+        .state::<MicrophoneState>()
+        .lock()
+        .map_err(|err| err.to_string())?
+        .continued_archive
+        .take();
+    finish_archive(&app_handle, continued);
+    start_recording(app_handle)
+}
+
+/// Start the audio thread recording the selected device into [`MicrophoneDataState`]
+pub fn start_recording(app_handle: AppHandle) -> Result<bool, AppError> {
+    let (tx, mut rx) = tauri::async_runtime::channel(4);
+    let (finished_tx, finished_rx) = oneshot::channel();
     let handle_clone = app_handle.clone();
     let mic_state = handle_clone.state::<MicrophoneState>();
     let is_recording =
@@ -432,64 +551,75 @@ pub async fn start_microphone_recording(app_handle: AppHandle) -> Result<bool, S
             .and_then(|mut mic_state| {
                 debug!("Replacing stream sender");
                 let old_sender = mic_state.stream_sender.replace(tx);
+                mic_state.recording_finished = Some(finished_rx);
                 let close_old_result = old_sender.map_or(Ok(()), |prev| {
                     debug!("Stopping old sender");
-                    prev.blocking_send(()).map_err(|err| err.to_string())
+                    prev.blocking_send(StreamControl::Stop)
+                        .map_err(|err| err.to_string())
                 });
                 debug!("Created Sender {:?}", mic_state.stream_sender);
                 close_old_result.map(|_success| mic_state.is_recording())
             })?;
+    update_recording_state(&app_handle, RecordingState::Recording);
     tauri::async_runtime::spawn_blocking(move || {
         let handle_clone = app_handle.clone();
         let mic_state = handle_clone.state::<MicrophoneState>();
-        let mic_state = match mic_state.lock() {
+        let mut mic_state = match mic_state.lock() {
             Ok(inner) => inner,
             Err(err) => {
-                error!("Error on getting mic state: {err}");
+                let message = format!("Error on getting mic state: {err}");
+                // The poisoned guard must be released before resetting the state
+                drop(err);
+                fail_recording(&app_handle, AppError::from(message));
                 return;
             }
         };
         let Some(ref microphone) = mic_state.device else {
-            error!("No device provide");
+            drop(mic_state);
+            fail_recording(
+                &app_handle,
+                AppError::new(ErrorCode::Device, "No input device selected"),
+            );
             return;
         };
-        // let buffer_size = microphone
-        //     .default_input_config()
-        //     .map(|config| config.buffer_size().clone())
-        //     .unwrap_or(SupportedBufferSize::Unknown);
-        let support = microphone
-            .default_input_config()
-            .map_err(|err| error!("Default config not given: {err}"))
-            .expect("Default config not found");
-        let config = support.config();
+        let (config, format) = match resolve_stream_config(microphone, mic_state.stream_config) {
+            Ok(resolved) => resolved,
+            Err(err) => {
+                drop(mic_state);
+                fail_recording(&app_handle, AppError::device(err));
+                return;
+            }
+        };
         let _ = app_handle
             .state::<MicrophoneDataState>()
             .lock()
             .map(|mut data| data.update_from_config(&config))
             .map_err(|err| error!("Error on getting lock from microphone data: {err}"));
-        // SupportedStreamConfig::new(1, SampleRate(16_000), buffer_size, SampleFormat::F32);
-        let build = microphone.build_input_stream(
+        let limits = mic_state.capture_limits;
+        let archive_directory = mic_state.archive_directory.clone();
+        let continued_archive = mic_state.continued_archive.take();
+        let system_audio = mic_state.system_audio.clone();
+        let (mut writer, reader) = capture_ring(&config);
+        let error_handle = app_handle.clone();
+        let build = build_input_stream(
+            microphone,
             &config,
-            move |data, _info| {
-                let state = app_handle.state::<MicrophoneDataState>();
-                let mut audio_data = match state.lock() {
-                    Ok(inner_data) => inner_data,
-                    Err(err) => {
-                        error!("Could not get Microphone Data State lock: {err}");
-                        return;
-                    }
-                };
-                audio_data.0.extend_from_slice(data);
+            format,
+            move |data| writer.write(data),
+            move |err| {
+                error!("Error on microphone stream: {err}");
+                emit_input_stream_error(&error_handle, AppError::device(err));
             },
-            |err| error!("Error on microphone stream: {err}"),
-            None,
         );
         debug!("Unlock the mic state mutex from audio thread");
         drop(mic_state);
         let stream = match build {
             Ok(stream) => stream,
             Err(err) => {
-                error!("Could not build stream: {err}");
+                fail_recording(
+                    &app_handle,
+                    AppError::device(format!("Could not build stream: {err}")),
+                );
                 return;
             }
         };
@@ -498,17 +628,88 @@ pub async fn start_microphone_recording(app_handle: AppHandle) -> Result<bool, S
         match stream.play() {
             Ok(()) => {
                 debug!("Playing Microphone Stream");
-                if rx.blocking_recv().is_some() {
-                    stream.pause().unwrap_or_else(|err| {
-                        warn!("Error when pausing stream, will still drop it: {err}");
+                let archive = continued_archive
+                    .map(|mut archive| {
+                        debug!("Continuing the archive of the recording on this device");
+                        archive.continue_with(&config);
+                        archive
+                    })
+                    .or_else(|| {
+                        archive_directory.and_then(|directory| {
+                            AudioArchive::create(&directory, &config)
+                                .map_err(|err| {
+                                    error!("Could not create archive for recording: {err}");
+                                    emit_input_stream_error(
+                                        &app_handle,
+                                        AppError::new(
+                                            ErrorCode::Io,
+                                            format!("Could not archive: {err}"),
+                                        ),
+                                    );
+                                })
+                                .ok()
+                        })
                     });
+                let system = system_audio.and_then(|options| {
+                    SystemAudio::start(&options.source, &config, options.mix_with_microphone)
+                        .map_err(|err| {
+                            error!("Could not capture system audio: {err}");
+                            emit_input_stream_error(
+                                &app_handle,
+                                AppError::device(format!("Could not capture system audio: {err}")),
+                            );
+                        })
+                        .ok()
+                });
+                let mut capture = Capture {
+                    microphone: reader,
+                    system,
+                    archive,
+                };
+                let mut limit = RecordingLimit::new(limits, &config);
+                let end = drain_capture(
+                    &app_handle,
+                    &mut capture,
+                    &mut rx,
+                    &stream,
+                    &config,
+                    &mut limit,
+                );
+                stream.pause().unwrap_or_else(|err| {
+                    warn!("Error when pausing stream, will still drop it: {err}");
+                });
+                // Keep the samples that arrived while stopping
+                let mut finished = capture.finish(&app_handle);
+                let archive = finished.archive.take();
+                let outcome = app_handle
+                    .state::<MicrophoneDataState>()
+                    .lock()
+                    .map(|mut data| finished.store(&mut data, limit.as_ref()))
+                    .map_err(|err| error!("Error on getting lock from microphone data: {err}"));
+                if let (Some(limit), Ok(outcome)) = (&mut limit, outcome) {
+                    limit.report(&app_handle, outcome);
                 }
+                let archive_path = match end {
+                    CaptureEnd::Stopped => finish_archive(&app_handle, archive),
+                    CaptureEnd::Switched => {
+                        match app_handle.state::<MicrophoneState>().lock() {
+                            Ok(mut mic_state) => mic_state.continued_archive = archive,
+                            Err(err) => error!("Could not hand over the archive: {err}"),
+                        }
+                        None
+                    }
+                };
+                // All audio is stored now, so the stop can return
+                let _ = finished_tx.send(archive_path);
                 debug!("Dropping Microphone Stream");
                 rx.close();
                 drop(stream);
             }
             Err(err) => {
-                error!("Error attempting to play stream: {err}");
+                fail_recording(
+                    &app_handle,
+                    AppError::device(format!("Could not play stream: {err}")),
+                );
             }
         }
         debug!("Dropping reciever");
@@ -518,70 +719,216 @@ pub async fn start_microphone_recording(app_handle: AppHandle) -> Result<bool, S
     Ok(is_recording)
 }
 
+/// Report a recording that could not be started and reset the recording state
+fn fail_recording(app_handle: &AppHandle, error: AppError) {
+    error!("Recording failed: {error}");
+    match app_handle.state::<MicrophoneState>().lock() {
+        Ok(mut mic_state) => drop(mic_state.stream_sender.take()),
+        Err(err) => error!("Could not get mic state lock: {err}"),
+    }
+    emit_input_stream_error(app_handle, error);
+    update_recording_state(app_handle, RecordingState::Stopped);
+}
+
+/// Move to a new recording state, emitting it when it changed
+pub fn set_recording_state(app_handle: &AppHandle, next: RecordingState) -> Result<(), AppError> {
+    let changed = app_handle
+        .state::<MicrophoneState>()
+        .lock()
+        .map_err(|err| err.to_string())?
+        .transition(next)
+        .map_err(|err| AppError::new(ErrorCode::Busy, err))?;
+    if changed {
+        emit_recording_state(app_handle, next);
+    }
+    Ok(())
+}
+
+/// Like [`set_recording_state`], but only logs a failure
+pub fn update_recording_state(app_handle: &AppHandle, next: RecordingState) {
+    let _ = set_recording_state(app_handle, next)
+        .map_err(|err| warn!("Could not update recording state: {err}"));
+}
+
+/// Send a control message to the running input stream, then move to the matching state
+async fn control_recording(
+    app_handle: &AppHandle,
+    control: StreamControl,
+    next: RecordingState,
+) -> Result<(), AppError> {
+    let sender = {
+        let mic_state = app_handle.state::<MicrophoneState>();
+        let mic_state = mic_state.lock().map_err(|err| err.to_string())?;
+        let current = mic_state.recording_state;
+        if !current.can_transition_to(next) || current == RecordingState::Processing {
+            return Err(AppError::new(
+                ErrorCode::Busy,
+                format!("Cannot go from {current:?} to {next:?}"),
+            ));
+        }
+        mic_state
+            .stream_sender
+            .clone()
+            .ok_or_else(|| AppError::new(ErrorCode::NotRecording, "Not recording"))?
+    };
+    sender
+        .send(control)
+        .await
+        .map_err(|err| AppError::new(ErrorCode::NotRecording, err))?;
+    set_recording_state(app_handle, next)
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Pause the current recording, keeping the audio recorded so far
+pub async fn pause_microphone_recording(app_handle: AppHandle) -> Result<(), AppError> {
+    info!("Pausing recording");
+    control_recording(&app_handle, StreamControl::Pause, RecordingState::Paused).await
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Resume a paused recording, appending to the same audio
+pub async fn resume_microphone_recording(app_handle: AppHandle) -> Result<(), AppError> {
+    info!("Resuming recording");
+    control_recording(
+        &app_handle,
+        StreamControl::Resume,
+        RecordingState::Recording,
+    )
+    .await
+}
+
 #[tauri::command]
 #[specta::specta]
-/// Send a stop signal, after an optional delay, to the audio thread to finish recording
+/// Send a stop signal, after an optional delay, to the audio thread to finish recording.
+///
+/// Waits until all audio is stored, returning the path of the archived audio, if any, to pass
+/// on to [`transcribe_current_data`].
 pub async fn stop_microphone_recording(
+    app_handle: AppHandle,
     mic_state: State<'_, MicrophoneState>,
     delay: Option<u32>,
-) -> Result<(), String> {
+) -> Result<Option<String>, AppError> {
+    let stopped = stop_recording(mic_state, delay).await?;
+    if stopped.is_some() {
+        update_recording_state(&app_handle, RecordingState::Stopped);
+    }
+    Ok(stopped
+        .flatten()
+        .map(|path| path.to_string_lossy().into_owned()))
+}
+
+/// Longest wait for the audio thread to store the recorded audio after a stop
+pub const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Stop the audio thread after an optional delay and wait for it to store all audio.
+///
+/// Returns `None` if there was no recording, otherwise the archive path of the recording.
+async fn stop_recording(
+    mic_state: State<'_, MicrophoneState>,
+    delay: Option<u32>,
+) -> Result<Option<Option<PathBuf>>, AppError> {
     tokio::time::sleep(delay.map_or(Duration::ZERO, |ms| Duration::from_millis(u64::from(ms))))
         .await;
-    let sender = {
+    let (sender, finished) = {
         debug!("Getting mic state lock");
         let mut mic_state = mic_state.lock().map_err(|err| {
             error!("Getting lock issue: {err}");
             err.to_string()
         })?;
         debug!("Calling Sender {:?}", mic_state.stream_sender);
-        mic_state.stream_sender.take()
+        (
+            mic_state.stream_sender.take(),
+            mic_state.recording_finished.take(),
+        )
     };
     debug!("Unlock mic state from stop command");
-    // drop(mic_state);
+    let Some(finished) = finished else {
+        return Ok(None);
+    };
+    // Without a sender, the recording stopped on its own (e.g. at the limit)
     if let Some(stopper) = sender {
-        debug!("Sending stop to Microphone Stream");
-        let mut count = 0;
-        while let Err(e) = stopper.send_timeout((), Duration::from_secs(5)).await {
-            count += 1;
-            warn!("Run #{count}: {e}");
-            if count >= 100 {
-                error!("Could not send to stopper");
-                break;
-            }
+        send_stop(&stopper).await;
+    }
+    match tokio::time::timeout(STOP_TIMEOUT, finished).await {
+        Ok(Ok(archive_path)) => Ok(Some(archive_path)),
+        // The thread ended without audio to store, e.g. as the stream failed
+        Ok(Err(_)) => Ok(Some(None)),
+        Err(_) => Err(AppError::new(
+            ErrorCode::Internal,
+            "Recording thread did not finish in time",
+        )),
+    }
+}
+
+/// Send the stop message to the audio thread, retrying while its queue is full
+async fn send_stop(stopper: &Sender<StreamControl>) {
+    debug!("Sending stop to Microphone Stream");
+    let mut count = 0;
+    while let Err(e) = stopper
+        .send_timeout(StreamControl::Stop, Duration::from_secs(5))
+        .await
+    {
+        count += 1;
+        warn!("Run #{count}: {e}");
+        if count >= 100 {
+            error!("Could not send to stopper");
+            break;
         }
-        // stopper.blocking_send(()).map_err(|err| err.to_string())?;
-        debug!("Stopper has sent successfully.");
     }
-    Ok(())
+    debug!("Stopper has sent successfully.");
 }
 
 #[tauri::command]
 #[specta::specta]
 /// Transcribe the current data that is in [`MicrophoneDataState`], fully consuming it on read.
 ///
-/// ### Returns
-/// (final_text, total_transcribing_time)
+/// `archive_path` is the path returned by [`stop_microphone_recording`], returned along with
+/// the transcript.
 pub async fn transcribe_current_data(
     app_handle: AppHandle,
     app_state: State<'_, AppState>,
     transcribe_options: Option<TranscribeOptions>,
     decode_options: Option<AudioProcessingOptions>,
-) -> Result<(String, f64), String> {
+    archive_path: Option<String>,
+) -> Result<TranscriptionResult, AppError> {
     debug!("Getting data");
+    let history_state = app_handle.state::<TranscriptHistoryState>();
     let state = app_handle.state::<MicrophoneDataState>();
-    let audio = {
+    let (audio, system) = {
         let mut data = state.lock().map_err(|err| err.to_string())?;
-        let audio = std::mem::take(&mut data.0);
+        let audio = Vec::from(std::mem::take(&mut data.0));
         debug!(
             "Old Data len: {} -> current audio len: {}",
             data.0.len(),
             audio.len()
         );
-        (audio, data.1, data.2)
+        ((audio, data.1, data.2), data.3.take())
+    };
+    // Mixed system audio is told apart by loudness, otherwise it is transcribed on its own
+    let samples_per_second = audio.2 as usize * usize::from(audio.1);
+    let (energies, system_audio) = match system {
+        Some(track) if track.samples.is_empty() => (None, None),
+        Some(mut track) if track.mixed => (
+            Some(SourceEnergies::measure(
+                &audio.0,
+                track.samples.make_contiguous(),
+                true,
+                samples_per_second,
+            )),
+            None,
+        ),
+        Some(track) => (None, Some(Vec::from(track.samples))),
+        None => (None, None),
     };
     let res = {
-        let options = transcribe_options.unwrap_or_default();
-        log::info!("Transcribing with parameters: translate={:?}, use_timestamp={:?}, threads={:?}, prompt={:?}, lang={:?}, fmt={:?}, patience={:?}",
+        let settings = settings::current(&app_handle);
+        let options = with_active_profile_options(
+            &app_handle,
+            settings.transcribe_options(transcribe_options),
+        );
+        log::info!("Transcribing with parameters: translate={:?}, use_timestamp={:?}, threads={:?}, prompt={:?}, lang={:?}, fmt={:?}, patience={:?}, context={:?}, app={:?}",
         options.translate,
         options.individual_word_timestamps,
         options.threads,
@@ -589,55 +936,101 @@ pub async fn transcribe_current_data(
         options.language,
         options.format,
         options.patience,
+        options.previous_context,
+        options.target_application,
     );
         info!("Running transcription command");
+        let prompt = {
+            let context = options
+                .previous_context
+                .as_ref()
+                .and_then(|context_options| {
+                    history_state
+                        .lock()
+                        .map_err(|err| error!("Could not get transcript history lock: {err}"))
+                        .ok()
+                        .and_then(|history| {
+                            history.context_prompt(
+                                context_options,
+                                options.target_application.as_deref(),
+                                options
+                                    .language
+                                    .as_deref()
+                                    .filter(|&language| !should_detect(Some(language))),
+                            )
+                        })
+                });
+            trace!("Context prompt from previous transcripts: {context:?}");
+            combine_prompt(options.initial_prompt.as_deref(), context.as_deref())
+        };
         let app_state = app_state.lock().map_err(|err| err.to_string())?;
         let model = app_state.get_model();
         info!("Transcribe using {}", app_state.get_model_info());
-        trace!("Creating abort transcription callback");
-
-        let transcription = crate::mutter::directly_denoise(
-            audio.0,
-            audio.1,
-            audio.2,
-            decode_options.unwrap_or_default(),
-        )
-        .and_then(|processed_audio| {
-            model.transcribe_pcm_s16le(
-                &processed_audio,
-                options.translate.unwrap_or(false),
-                options.individual_word_timestamps.unwrap_or(false),
-                options.initial_prompt.as_deref(),
-                options.language.as_deref(),
-                // Make sure not to pass 0 for CPU thread,
-                // otherwise model crashes
-                match options.threads {
-                    Some(0) => None,
-                    threads => threads,
+        let format = options.format.unwrap_or_default();
+        let prompted_options = TranscribeOptions {
+            initial_prompt: prompt,
+            // Only the JSON format has room for alternatives, skip their extra decodes otherwise
+            alternatives: options.alternatives.filter(|_| format.is_structured()),
+            ..options.clone()
+        };
+        let decode_options = settings.decode_options(decode_options);
+        let transcribe = |samples: Vec<f32>| {
+            crate::mutter::directly_denoise(samples, audio.1, audio.2, decode_options).and_then(
+                |processed_audio| {
+                    model.transcribe_pcm_s16le(
+                        &processed_audio,
+                        &prompted_options,
+                        TranscribeCallbacks::default(),
+                    )
                 },
-                options.patience,
-                None::<fn() -> bool>,
-                None::<fn(i32)>,
-                None::<fn(SegmentCallbackData)>,
-                None::<fn(SegmentCallbackData)>,
-                None,
             )
-        })
-        .map_err(|err| {
-            log::error!("Transcription Error: {err:?}");
-            match err {
-                ModelError::WhisperError(whisper_error) => whisper_error.to_string(),
-                ModelError::DecodingError(decoder_error) => decoder_error.to_string(),
-            }
-        })?;
+        };
+        let transcription = transcribe(audio.0)
+            .and_then(|microphone| match system_audio {
+                Some(system) => {
+                    transcribe(system).map(|system| merge_source_transcripts(microphone, system))
+                }
+                None => Ok(microphone),
+            })
+            .map(|mut transcription| {
+                if let Some(energies) = &energies {
+                    energies.tag(&mut transcription.utterances);
+                    if let Some(words) = &mut transcription.word_utterances {
+                        energies.tag(words);
+                    }
+                }
+                transcription
+            })
+            .map_err(|err| {
+                log::error!("Transcription Error: {err:?}");
+                AppError::from(err)
+            })?;
+        drop(app_state);
+        let language = transcription.language.clone().map(|detection| {
+            let language = detection.language.clone();
+            let _ = LanguageDetectedEvent::with_payload(detection)
+                .emit(&app_handle)
+                .map_err(|err| error!("Error for language detected event: {err}"));
+            language
+        });
+        let _ = history_state
+            .lock()
+            .map(|mut history| {
+                history.push(
+                    &transcription.as_text(),
+                    options.target_application.clone(),
+                    language.or_else(|| options.language.clone()),
+                )
+            })
+            .map_err(|err| error!("Could not add transcript to history: {err}"));
 
-        (
-            options
-                .format
-                .unwrap_or_default()
-                .convert_transcript(&transcription),
-            transcription.processing_time.as_secs_f64(),
-        )
+        TranscriptionResult {
+            text: format.convert_transcript(&transcription),
+            format,
+            processing_time: transcription.processing_time.as_secs_f64(),
+            archive_path,
+            confidence: transcript_confidence(&transcription),
+        }
     };
     debug!("Result of transcription: {res:?}");
     Ok(res)
@@ -645,24 +1038,95 @@ pub async fn transcribe_current_data(
 
 #[tauri::command]
 #[specta::specta]
-/// Transcribe the data from [`MicrophoneDataState`], then process the resulting text.
+/// Forget all previous transcripts used as context for the next prompt.
+pub async fn clear_transcript_history(
+    history_state: State<'_, TranscriptHistoryState>,
+) -> Result<(), AppError> {
+    info!("Clearing transcript history");
+    history_state.lock().map_err(|err| err.to_string())?.clear();
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Replace `original` with `replacement`, e.g. an alternative of a segment, in a remembered
+/// transcript, the last one if `entry_id` is `None`.
 ///
 /// ### Returns
-/// (final_text, total_processing_time)
+/// The corrected text of the transcript
+pub async fn correct_transcript(
+    history_state: State<'_, TranscriptHistoryState>,
+    original: String,
+    replacement: String,
+    entry_id: Option<u32>,
+) -> Result<String, AppError> {
+    info!("Correcting transcript {entry_id:?}");
+    history_state
+        .lock()
+        .map_err(|err| err.to_string())?
+        .correct(entry_id, &original, &replacement)
+        .ok_or_else(|| {
+            AppError::new(
+                ErrorCode::NotFound,
+                "Text to correct not found in transcript",
+            )
+        })
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Get how often misrecognized words were corrected, most frequent first.
+pub async fn get_corrections(
+    history_state: State<'_, TranscriptHistoryState>,
+) -> Result<Vec<CorrectionCount>, AppError> {
+    Ok(history_state
+        .lock()
+        .map_err(|err| err.to_string())?
+        .corrections())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Transcribe the data from [`MicrophoneDataState`], then process the resulting text.
+///
+/// `archive_path` is the path returned by [`stop_microphone_recording`]. Structured formats
+/// like JSON are returned without processing.
 pub async fn transcribe_current_then_process(
     app_state: State<'_, AppState>,
     app_handle: AppHandle,
     transcribe_options: Option<TranscribeOptions>,
     processing_options: TextPostProcessing,
     decode_options: Option<AudioProcessingOptions>,
-) -> Result<(String, f64), String> {
+    archive_path: Option<String>,
+) -> Result<TranscriptionResult, AppError> {
     debug!("Now transcribing audio data");
-    let transcript =
-        transcribe_current_data(app_handle, app_state, transcribe_options, decode_options).await?;
+    let processing_options = app_handle
+        .state::<ProfileState>()
+        .lock()
+        .map_err(|err| err.to_string())?
+        .active()
+        .processing_options(processing_options);
+    let transcript = transcribe_current_data(
+        app_handle.clone(),
+        app_state,
+        transcribe_options,
+        decode_options,
+        archive_path,
+    )
+    .await?;
+    if transcript.format.is_structured() {
+        debug!("Not processing {:?} transcript", transcript.format);
+        return Ok(transcript);
+    }
     debug!("Finish processing");
     Ok(if let Some(options) = processing_options.into_options() {
-        let processed = process_text(transcript.0, Some(options)).await?;
-        (processed.0, transcript.1 + processed.1)
+        let (text, processing_time) =
+            process_text(app_handle, transcript.text, Some(options)).await?;
+        TranscriptionResult {
+            text,
+            processing_time: transcript.processing_time + processing_time,
+            ..transcript
+        }
     } else {
         transcript
     })
@@ -671,9 +1135,6 @@ pub async fn transcribe_current_then_process(
 #[tauri::command]
 #[specta::specta]
 /// Stop the microphone, then transcribe the audio, and finally post-processing the text.
-///
-/// ### Returns
-/// (final_text, total_processing_time)
 pub async fn stop_transcribe_and_process_data(
     app_state: State<'_, AppState>,
     mic_state: State<'_, MicrophoneState>,
@@ -682,80 +1143,174 @@ pub async fn stop_transcribe_and_process_data(
     transcribe_options: Option<TranscribeOptions>,
     processing_options: TextPostProcessing,
     decode_options: Option<AudioProcessingOptions>,
-) -> Result<(String, f64), String> {
+) -> Result<TranscriptionResult, AppError> {
     debug!("Running stop first");
-    if let Some(time) = stop_mic_time {
-        stop_microphone_recording(mic_state, Some(time)).await?;
-    }
-    transcribe_current_then_process(
+    let archive_path = match stop_mic_time {
+        Some(time) => stop_recording(mic_state, Some(time)).await?.flatten(),
+        None => None,
+    };
+    update_recording_state(&app_handle, RecordingState::Processing);
+    let result = transcribe_current_then_process(
         app_state,
-        app_handle,
+        app_handle.clone(),
         transcribe_options,
         processing_options,
         decode_options,
+        archive_path.map(|path| path.to_string_lossy().into_owned()),
     )
-    .await
+    .await;
+    // A new recording may have started in the meantime
+    let is_recording = app_handle
+        .state::<MicrophoneState>()
+        .lock()
+        .map_err(|err| err.to_string())?
+        .is_recording();
+    if !is_recording {
+        update_recording_state(&app_handle, RecordingState::Stopped);
+    }
+    result
 }
 
 #[tauri::command]
 #[specta::specta]
-/// Set the current input device on user's system from Host
-pub async fn set_input_device(
+/// Set the current input device on user's system from Host by its position in [`get_input_devices`].
+///
+/// Prefer [`set_input_device_by_name`], since positions change when devices are plugged in or out.
+pub async fn set_input_device(app_handle: AppHandle, index: u8) -> Result<bool, AppError> {
+    let name = input_device_names()
+        .map_err(AppError::device)?
+        .into_iter()
+        .nth(usize::from(index));
+    select_input_device(&app_handle, name)
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Set the current input device by name, using the default device when `None` or not found.
+///
+/// The device is remembered and selected again whenever it is available.
+pub async fn set_input_device_by_name(
+    app_handle: AppHandle,
+    name: Option<String>,
+) -> Result<bool, AppError> {
+    select_input_device(&app_handle, name)
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Get the stream configurations supported by the named input device, or the current one if `None`
+pub async fn get_input_stream_configs(
+    mic_state: State<'_, MicrophoneState>,
+    name: Option<String>,
+) -> Result<Vec<SupportedInputConfig>, AppError> {
+    let device = match name {
+        Some(name) => find_input_device(&name).ok_or_else(|| missing_input_device(&name))?,
+        None => mic_state
+            .lock()
+            .map_err(|err| err.to_string())?
+            .device
+            .clone()
+            .ok_or_else(|| AppError::new(ErrorCode::Device, "No input device selected"))?,
+    };
+    supported_configs(&device).map_err(AppError::device)
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Set the stream configuration of the current input device, `None` for the device's default.
+///
+/// Used from the next recording on.
+pub async fn set_input_stream_config(
     mic_state: State<'_, MicrophoneState>,
     data_state: State<'_, MicrophoneDataState>,
-    index: u8,
-) -> Result<bool, String> {
+    config: Option<InputStreamConfig>,
+) -> Result<(), AppError> {
     let mut mic_state = mic_state.lock().map_err(|err| err.to_string())?;
-    debug!("Find input device");
-    let mut devices = mic_state
-        .host
-        .input_devices()
-        .map_err(|err| err.to_string())?;
-    let (is_custom, device) = if let Some(custom) = devices.nth(index as usize) {
-        debug!("Set custom device");
-        (true, custom)
-    } else if let Some(default) = mic_state.host.default_input_device() {
-        debug!("Use default device");
-        (false, default)
-    } else {
-        error!("No device at #{index} and no fallback default input.");
-        return Err("Could not select input device.".into());
-    };
-    debug!("Update Microphone Data with new device config, then update device");
-    data_state
-        .lock()
-        .map_err(|err| err.to_string())?
-        .replace_with_config(
-            &device
-                .default_input_config()
-                .map_err(|err| err.to_string())?
-                .config(),
-        );
-    mic_state.device.replace(device);
-    drop(mic_state);
-    Ok(is_custom)
+    let device = mic_state
+        .device
+        .as_ref()
+        .ok_or_else(|| AppError::new(ErrorCode::Device, "No input device selected"))?;
+    let (stream_config, _) = resolve_stream_config(device, config).map_err(AppError::device)?;
+    if !mic_state.is_recording() {
+        data_state
+            .lock()
+            .map_err(|err| err.to_string())?
+            .replace_with_config(&stream_config);
+    }
+    info!("Set input stream config to {config:?}");
+    mic_state.stream_config = config;
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Set the maximum duration of a recording and what happens when it is reached, saving them in
+/// the settings.
+///
+/// Used from the next recording on.
+pub async fn set_capture_limits(
+    app_handle: AppHandle,
+    limits: CaptureLimits,
+) -> Result<(), AppError> {
+    if limits.max_duration_seconds == Some(0) {
+        return Err(AppError::new(
+            ErrorCode::InvalidInput,
+            "Maximum recording duration must be at least one second",
+        ));
+    }
+    info!("Set capture limits to {limits:?}");
+    settings::update(&app_handle, |settings| {
+        settings.recording.capture_limits = limits;
+    })?;
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Archive the raw audio of every recording as a WAV file in `directory`, or stop if `None`.
+///
+/// The path of the file is returned with the transcript of the recording. Saved in the settings
+/// and used from the next recording on.
+pub async fn set_recording_archive(
+    app_handle: AppHandle,
+    directory: Option<String>,
+) -> Result<(), AppError> {
+    info!("Set recording archive directory to {directory:?}");
+    settings::update(&app_handle, |settings| {
+        settings.recording.archive_directory = directory;
+    })?;
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Get the monitor sources of PulseAudio or PipeWire, which record the audio played on an output
+pub async fn get_system_audio_sources() -> Result<Vec<String>, AppError> {
+    monitor_sources().map_err(AppError::device)
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Record system audio from a monitor source along with the microphone, or stop if `None`.
+///
+/// Saved in the settings and used from the next recording on.
+pub async fn set_system_audio_capture(
+    app_handle: AppHandle,
+    options: Option<SystemAudioOptions>,
+) -> Result<(), AppError> {
+    info!("Set system audio capture to {options:?}");
+    settings::update(&app_handle, |settings| {
+        settings.recording.system_audio = options;
+    })?;
+    Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 /// Get all possible input devices on user's system.
-pub async fn get_input_devices(
-    mic_state: State<'_, MicrophoneState>,
-) -> Result<Vec<String>, String> {
-    let mic_state = mic_state.lock().map_err(|err| err.to_string())?;
+pub async fn get_input_devices() -> Result<Vec<String>, AppError> {
     debug!("Find and get input devices");
-    Ok(mic_state
-        .host
-        .input_devices()
-        .map_err(|err| err.to_string())?
-        .enumerate()
-        .map(|(i, d)| {
-            d.name().unwrap_or_else(|err| {
-                warn!("Could not get device name, use placeholder: {err}");
-                format!("Unknown Input Device #{}", i + 1)
-            })
-        })
-        .collect::<Vec<_>>())
+    input_device_names().map_err(AppError::device)
 }
 
 #[tauri::command]
@@ -763,16 +1318,306 @@ pub async fn get_input_devices(
 /// Get the currently active input device (the one that will record audio data).
 pub async fn get_current_input_device(
     mic_state: State<'_, MicrophoneState>,
-) -> Result<Option<String>, String> {
+) -> Result<Option<String>, AppError> {
     let mic_state = mic_state.lock().map_err(|err| err.to_string())?;
     debug!("Get current input device from mic state");
     mic_state
         .device
         .as_ref()
-        .map(|device| device.name().map_err(|err| err.to_string()))
+        .map(|device| device.name().map_err(AppError::device))
         .transpose()
 }
 
+/// Detect the focused window, select its application profile and notify the frontend.
+fn activate_profile_for_focused_window(app_handle: &AppHandle) {
+    let state = app_handle.state::<ProfileState>();
+    let Ok(detector) = state.lock().map(|profiles| profiles.detector()) else {
+        error!("Could not get profile state lock");
+        return;
+    };
+    // Detecting can take a while, so do not block the state meanwhile
+    let window = detector.active_window();
+    let active = match state.lock() {
+        Ok(mut profiles) => profiles.activate(window).clone(),
+        Err(err) => {
+            error!("Could not get profile state lock: {err}");
+            return;
+        }
+    };
+    let _ = ActiveProfileChangedEvent::with_payload(active)
+        .emit(app_handle)
+        .map_err(|err| error!("Error for active profile changed event: {err}"));
+}
+
+/// Apply the transcription overrides of the active application profile.
+///
+/// Also fills in the target application from the focused window when not given.
+fn with_active_profile_options(
+    app_handle: &AppHandle,
+    options: TranscribeOptions,
+) -> TranscribeOptions {
+    let state = app_handle.state::<ProfileState>();
+    let Ok(profiles) = state.lock() else {
+        error!("Could not get profile state lock, use options as given");
+        return options;
+    };
+    profiles.active().transcribe_options(options)
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Replace all application profiles, saving them in the settings
+pub async fn set_application_profiles(
+    app_handle: AppHandle,
+    profiles: Vec<ApplicationProfile>,
+) -> Result<(), AppError> {
+    info!("Setting {} application profiles", profiles.len());
+    settings::update(&app_handle, |settings| settings.profiles = profiles)?;
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Get all application profiles
+pub async fn get_application_profiles(
+    profile_state: State<'_, ProfileState>,
+) -> Result<Vec<ApplicationProfile>, AppError> {
+    Ok(profile_state
+        .lock()
+        .map_err(|err| err.to_string())?
+        .profiles()
+        .to_vec())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Get the currently focused window, `None` if it cannot be detected on this system
+pub async fn get_active_window(
+    profile_state: State<'_, ProfileState>,
+) -> Result<Option<WindowInfo>, AppError> {
+    let detector = profile_state
+        .lock()
+        .map_err(|err| err.to_string())?
+        .detector();
+    Ok(detector.active_window())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Get the window and profile selected when the current (or last) dictation started
+pub async fn get_active_profile(
+    profile_state: State<'_, ProfileState>,
+) -> Result<ActiveProfile, AppError> {
+    Ok(profile_state
+        .lock()
+        .map_err(|err| err.to_string())?
+        .active()
+        .clone())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Send the text to the focused application.
+///
+/// Uses the method from the options, or the active profile's method if not given, then the
+/// one from the settings.
+pub async fn output_text(
+    app_handle: AppHandle,
+    text: String,
+    options: Option<OutputOptions>,
+) -> Result<(), AppError> {
+    let mut options = options.unwrap_or_default();
+    if options.method.is_none() || options.target.is_none() {
+        let profiles = app_handle.state::<ProfileState>();
+        let profiles = profiles.lock().map_err(|err| err.to_string())?;
+        if let Some(profile) = profiles.active().profile.as_ref() {
+            options.method = options.method.or(profile.output_method);
+            options.target = options.target.or_else(|| profile.output_target.clone());
+        }
+    }
+    let options = settings::current(&app_handle).output_options(options);
+    info!(
+        "Output text to {:?} using {:?}",
+        options.target, options.method
+    );
+    check_output_target(options.target.as_ref())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        match options.target.as_ref() {
+            None | Some(OutputTarget::FocusedApplication) => SystemOutputSink::new(&app_handle)
+                .and_then(|mut sink| send_text(&mut sink, &text, &options)),
+            Some(target) => send_to_target(target, &text),
+        }
+        .map_err(AppError::from)
+    })
+    .await
+    .map_err(|err| AppError::new(ErrorCode::Internal, format!("Output task failed: {err}")))?
+}
+
+/// Check that the target of [`output_text`] exists, as writing to it would only fail with IO errors
+fn check_output_target(target: Option<&OutputTarget>) -> Result<(), AppError> {
+    let missing = |path: &str, hint: &str| {
+        Err(AppError::new(
+            ErrorCode::NotFound,
+            format!("Output target {path} does not exist"),
+        )
+        .with_hint(hint))
+    };
+    match target {
+        Some(OutputTarget::File { path, .. }) => {
+            let folder = Path::new(path)
+                .parent()
+                .filter(|folder| !folder.as_os_str().is_empty());
+            if folder.is_some_and(|folder| !folder.is_dir()) {
+                return missing(
+                    path,
+                    "Create the folder of the file, or choose another file.",
+                );
+            }
+        }
+        Some(OutputTarget::NamedPipe { path } | OutputTarget::UnixSocket { path })
+            if !Path::new(path).exists() =>
+        {
+            return missing(
+                path,
+                "Start the program reading the text, then output again.",
+            );
+        }
+        Some(OutputTarget::Command { program, .. }) if program.trim().is_empty() => {
+            return Err(AppError::new(
+                ErrorCode::InvalidInput,
+                "Output command has no program to run",
+            ));
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Start the local HTTP/WebSocket API on `127.0.0.1`, replacing a running one.
+///
+/// A random token is generated if none is given. The API is saved in the settings as enabled,
+/// so it is started with the app from now on.
+pub async fn start_api_server(
+    app_handle: AppHandle,
+    server_state: State<'_, ApiServerState>,
+    port: Option<u16>,
+    token: Option<String>,
+) -> Result<ApiServerInfo, AppError> {
+    info!("Starting local API server");
+    let info = crate::server::start(app_handle.clone(), port, token.clone())
+        .await
+        .map_err(|err| AppError::new(ErrorCode::Io, err))?;
+    // Without a token a new one is generated on every start, like this time
+    let saved = settings::update(&app_handle, |settings| {
+        settings.api_server = ApiServerSettings {
+            enabled: true,
+            port: port.map(|_| info.port),
+            token: token.filter(|token| !token.is_empty()),
+        };
+    });
+    if let Err(err) = saved {
+        server_state.lock().map_err(|err| err.to_string())?.stop();
+        return Err(err);
+    }
+    Ok(info)
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Stop the local API, returns whether it was running.
+///
+/// The API is saved in the settings as disabled, so it is no longer started with the app.
+pub async fn stop_api_server(
+    app_handle: AppHandle,
+    server_state: State<'_, ApiServerState>,
+) -> Result<bool, AppError> {
+    let was_running = server_state.lock().map_err(|err| err.to_string())?.stop();
+    settings::update(&app_handle, |settings| settings.api_server.enabled = false)?;
+    Ok(was_running)
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Get the port and token of the local API, `None` if it is not running
+pub async fn get_api_server_info(
+    server_state: State<'_, ApiServerState>,
+) -> Result<Option<ApiServerInfo>, AppError> {
+    Ok(server_state
+        .lock()
+        .map_err(|err| err.to_string())?
+        .info()
+        .cloned())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Replace all global hotkey and mouse-button bindings, saving them in the settings
+pub async fn set_hotkey_bindings(
+    app_handle: AppHandle,
+    bindings: Vec<HotkeyBinding>,
+) -> Result<(), AppError> {
+    info!("Setting {} hotkey bindings", bindings.len());
+    settings::update(&app_handle, |settings| settings.hotkey_bindings = bindings)?;
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Get all global hotkey and mouse-button bindings
+pub async fn get_hotkey_bindings(
+    hotkey_state: State<'_, HotkeyState>,
+) -> Result<Vec<HotkeyBinding>, AppError> {
+    Ok(hotkey_state
+        .lock()
+        .map_err(|err| err.to_string())?
+        .bindings())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Set the options used for dictations run by hotkey bindings, saving them in the settings.
+///
+/// They are also the defaults of the other commands and the local API.
+pub async fn set_dictation_options(
+    app_handle: AppHandle,
+    options: DictationOptions,
+) -> Result<(), AppError> {
+    debug!("Setting dictation options: {options:?}");
+    settings::update(&app_handle, |settings| settings.dictation = options)?;
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Get the settings shared by the commands, the local API, hotkey dictation and the CLI
+pub async fn get_settings(settings_state: State<'_, SettingsState>) -> Result<Settings, AppError> {
+    Ok(settings_state
+        .lock()
+        .map_err(|err| err.to_string())?
+        .clone())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Replace all settings, `None` to reset them to the defaults.
+///
+/// The settings are validated and saved, then the hotkey bindings, model and local API are
+/// updated to match.
+///
+/// ### Returns
+/// The settings after the change
+pub async fn update_settings(
+    app_handle: AppHandle,
+    settings: Option<Settings>,
+) -> Result<Settings, AppError> {
+    info!("Updating settings");
+    settings::update(&app_handle, |current| {
+        *current = settings.unwrap_or_default();
+    })
+}
+
 /// Gets all collected commands for Super Mouse AI application to be used by builder
 #[must_use]
 pub fn get_collected_commands() -> Commands<Wry> {
@@ -786,6 +1631,7 @@ pub fn get_collected_commands() -> Commands<Wry> {
         write_text,
         update_model,
         get_system_info,
+        run_benchmark,
         sentry_crash_reporter_update,
         start_microphone_recording,
         stop_microphone_recording,
@@ -793,7 +1639,32 @@ pub fn get_collected_commands() -> Commands<Wry> {
         transcribe_current_then_process,
         stop_transcribe_and_process_data,
         set_input_device,
+        set_input_device_by_name,
         get_input_devices,
         get_current_input_device,
+        get_input_stream_configs,
+        set_input_stream_config,
+        set_capture_limits,
+        set_recording_archive,
+        get_system_audio_sources,
+        set_system_audio_capture,
+        pause_microphone_recording,
+        resume_microphone_recording,
+        clear_transcript_history,
+        correct_transcript,
+        get_corrections,
+        set_application_profiles,
+        get_application_profiles,
+        get_active_window,
+        get_active_profile,
+        output_text,
+        start_api_server,
+        stop_api_server,
+        get_api_server_info,
+        set_hotkey_bindings,
+        get_hotkey_bindings,
+        set_dictation_options,
+        get_settings,
+        update_settings,
     ]
 }
@@ -0,0 +1,137 @@
+//! Confidence of words and segments, aggregated from whisper's token probabilities.
+//!
+//! The confidence of a word or segment is the geometric mean of its token probabilities, so a
+//! single doubtful token lowers it noticeably while long words are not punished for their length.
+
+use crate::transcript::{Transcript, Utterance};
+
+/// A text token of whisper with its timestamps and probability
+pub struct Token {
+    pub text: String,
+    pub start: i64,
+    pub stop: i64,
+    pub probability: f32,
+}
+
+/// Geometric mean of the probabilities, `None` if there are none
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "Token counts are far below 2^23"
+)]
+pub fn mean_confidence(probabilities: impl IntoIterator<Item = f32>) -> Option<f32> {
+    let (sum, count) = probabilities
+        .into_iter()
+        .fold((0.0, 0), |(sum, count), probability| {
+            (sum + probability.max(f32::EPSILON).ln(), count + 1)
+        });
+    (count > 0).then(|| (sum / count as f32).exp())
+}
+
+/// Join the tokens of a segment into words, each with the confidence of its tokens.
+///
+/// A token starting with whitespace begins a new word, all others continue the previous one.
+pub fn group_words(tokens: Vec<Token>) -> Vec<Utterance> {
+    let mut words: Vec<(Utterance, Vec<f32>)> = Vec::new();
+    for token in tokens {
+        match words.last_mut() {
+            Some((word, probabilities)) if !token.text.starts_with(char::is_whitespace) => {
+                word.text.push_str(&token.text);
+                word.stop = token.stop;
+                probabilities.push(token.probability);
+            }
+            _ => words.push((
+                Utterance {
+                    start: token.start,
+                    stop: token.stop,
+                    text: token.text,
+                    speaker: None,
+                    hallucination: None,
+                    confidence: None,
+                    alternatives: Vec::new(),
+                    source: None,
+                },
+                vec![token.probability],
+            )),
+        }
+    }
+    words
+        .into_iter()
+        .map(|(word, probabilities)| Utterance {
+            confidence: mean_confidence(probabilities),
+            ..word
+        })
+        .collect()
+}
+
+/// Confidence of the whole transcript, the mean of its segments weighted by their length
+pub fn transcript_confidence(transcript: &Transcript) -> Option<f32> {
+    #[allow(clippy::cast_precision_loss, reason = "Segments are short")]
+    let (weighted, total) = transcript
+        .utterances
+        .iter()
+        .filter_map(|utterance| {
+            let length = utterance.text.trim().chars().count().max(1) as f32;
+            utterance
+                .confidence
+                .map(|confidence| (confidence * length, length))
+        })
+        .fold((0.0, 0.0), |(weighted, total), (confidence, length)| {
+            (weighted + confidence, total + length)
+        });
+    (total > 0.0).then(|| weighted / total)
+}
+
+#[cfg(test)]
+fn token(text: &str, start: i64, probability: f32) -> Token {
+    Token {
+        text: text.into(),
+        start,
+        stop: start + 10,
+        probability,
+    }
+}
+
+#[test]
+fn test_group_words() {
+    let words = group_words(vec![
+        token(" Hello", 0, 0.9),
+        token(",", 10, 0.8),
+        token(" wonder", 20, 0.9),
+        token("ful", 30, 0.4),
+        token(" world", 40, 0.99),
+    ]);
+    let texts = words
+        .iter()
+        .map(|word| word.text.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(texts, [" Hello,", " wonderful", " world"]);
+    assert_eq!((words[1].start, words[1].stop), (20, 40));
+    let confidence = words[1].confidence.expect("Word has tokens");
+    assert!((confidence - 0.6).abs() < 1e-3);
+    assert!(mean_confidence([]).is_none());
+}
+
+#[test]
+fn test_transcript_confidence() {
+    let transcript = Transcript {
+        processing_time: std::time::Duration::ZERO,
+        utterances: [(" A long and clear sentence.", 0.9), (" Hm?", 0.2)]
+            .into_iter()
+            .map(|(text, confidence)| Utterance {
+                start: 0,
+                stop: 100,
+                text: text.into(),
+                speaker: None,
+                hallucination: None,
+                confidence: Some(confidence),
+                alternatives: Vec::new(),
+                source: None,
+            })
+            .collect(),
+        word_utterances: None,
+        language: None,
+    };
+    let confidence = transcript_confidence(&transcript).expect("Segments have confidences");
+    // The long sentence counts more than the short one
+    assert!((confidence - 0.8276).abs() < 1e-3);
+}
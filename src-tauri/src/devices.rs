@@ -0,0 +1,455 @@
+//! Input device selection by name and hot-plug watching. The preferred device is saved in the
+//! settings, see [`crate::settings::RecordingSettings`].
+//!
+//! `cpal` has no device-change notifications, so a watcher thread compares the device list
+//! periodically and reacts when the selected microphone disappears or the preferred one returns.
+
+use crate::{
+    capture::resolve_stream_config,
+    command::{set_recording_state, start_recording, update_recording_state, STOP_TIMEOUT},
+    error::{AppError, ErrorCode},
+    events::InputDevicesChangedEvent,
+    settings,
+    types::{
+        InnerMicrophoneData, MicrophoneDataState, MicrophoneState, RecordingState, StreamControl,
+    },
+};
+use log::{debug, error, info, warn};
+use rodio::{
+    cpal::{default_host, traits::HostTrait, StreamConfig},
+    Device, DeviceTrait,
+};
+use std::{
+    sync::{
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Mutex,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+
+/// Time between checks of the available input devices
+pub const DEVICE_WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// What to do with the selected device after the device list changed
+enum DeviceChoice {
+    /// Selected device is fine
+    Keep,
+    /// Switch to the named device
+    Switch(String),
+    /// Switch to the host's default device
+    Default,
+}
+
+/// Names of all input devices currently available, devices without a name are skipped.
+///
+/// Devices are identified by name, as indices shift when devices come and go. Listing can take
+/// seconds, so this uses its own host and must not be called while holding [`MicrophoneState`].
+pub fn input_device_names() -> Result<Vec<String>, String> {
+    Ok(default_host()
+        .input_devices()
+        .map_err(|err| err.to_string())?
+        .filter_map(|device| {
+            device
+                .name()
+                .map_err(|err| warn!("Could not get device name, skipping it: {err}"))
+                .ok()
+        })
+        .collect())
+}
+
+/// Find an available input device by its name, see [`input_device_names`]
+pub fn find_input_device(name: &str) -> Option<Device> {
+    default_host()
+        .input_devices()
+        .map_err(|err| warn!("Could not list input devices: {err}"))
+        .ok()?
+        .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+}
+
+/// Error for a named input device that is not connected
+#[must_use]
+pub fn missing_input_device(name: &str) -> AppError {
+    AppError::new(ErrorCode::NotFound, format!("No input device named {name}"))
+        .with_hint("Reconnect the device, or select one of the listed input devices.")
+}
+
+/// Decide which device to use given the available devices.
+///
+/// The preferred device wins when available, except mid-recording where the current device is kept
+/// as long as it works.
+fn choose_device(
+    available: &[String],
+    current: Option<&str>,
+    preferred: Option<&str>,
+    is_recording: bool,
+) -> DeviceChoice {
+    let is_available = |name: &str| available.iter().any(|device| device == name);
+    let current_available = current.is_some_and(is_available);
+    match preferred {
+        Some(preferred) if current == Some(preferred) && current_available => DeviceChoice::Keep,
+        Some(preferred) if is_available(preferred) && !(is_recording && current_available) => {
+            DeviceChoice::Switch(preferred.to_string())
+        }
+        _ if current_available => DeviceChoice::Keep,
+        _ => DeviceChoice::Default,
+    }
+}
+
+/// Select an input device by name, or the default device if `None` or not found.
+///
+/// The choice is saved as the preferred device. Returns whether the named device was used.
+pub fn select_input_device(app_handle: &AppHandle, name: Option<String>) -> Result<bool, AppError> {
+    let is_custom = use_input_device(app_handle, name.clone())?;
+    settings::update(app_handle, |settings| {
+        settings.recording.preferred_device = name;
+    })?;
+    Ok(is_custom)
+}
+
+/// Switch to an input device by name, or the default device if `None` or not found.
+///
+/// The name becomes the preferred device of the watcher without saving it. Returns whether the
+/// named device was used.
+pub fn use_input_device(app_handle: &AppHandle, name: Option<String>) -> Result<bool, AppError> {
+    let custom = name.as_deref().and_then(find_input_device);
+    let (is_custom, device) = match custom {
+        Some(device) => (true, device),
+        None => {
+            debug!("Use default device instead of {name:?}");
+            let device = default_host().default_input_device().ok_or_else(|| {
+                AppError::new(ErrorCode::Device, "Could not select input device.")
+            })?;
+            (false, device)
+        }
+    };
+    let (config, _) = resolve_stream_config(&device, None).map_err(AppError::device)?;
+    let mic_state = app_handle.state::<MicrophoneState>();
+    let mut mic_state = mic_state.lock().map_err(|err| err.to_string())?;
+    debug!("Update Microphone Data with new device config, then update device");
+    app_handle
+        .state::<MicrophoneDataState>()
+        .lock()
+        .map_err(|err| err.to_string())?
+        .replace_with_config(&config);
+    mic_state.device.replace(device);
+    mic_state.stream_config = None;
+    mic_state.preferred_device = name;
+    drop(mic_state);
+    emit_devices_changed(app_handle);
+    Ok(is_custom)
+}
+
+fn emit_devices_changed(app_handle: &AppHandle) {
+    let devices = input_device_names().unwrap_or_default();
+    let current = match app_handle.state::<MicrophoneState>().lock() {
+        Ok(mic_state) => mic_state.device_name(),
+        Err(err) => {
+            error!("Could not get mic state lock: {err}");
+            return;
+        }
+    };
+    let _ = InputDevicesChangedEvent::with_payload(devices, current)
+        .emit(app_handle)
+        .map_err(|err| error!("Error for input devices changed event: {err}"));
+}
+
+/// React to a changed device list, switching devices when needed
+fn on_devices_changed(app_handle: &AppHandle, available: &[String]) -> Result<(), AppError> {
+    let choice = {
+        let mic_state = app_handle.state::<MicrophoneState>();
+        let mic_state = mic_state.lock().map_err(|err| err.to_string())?;
+        choose_device(
+            available,
+            mic_state.device_name().as_deref(),
+            mic_state.preferred_device.as_deref(),
+            mic_state.is_recording(),
+        )
+    };
+    // Looking up devices is slow, so it is done without holding the microphone state
+    let device = match &choice {
+        DeviceChoice::Keep => None,
+        DeviceChoice::Switch(name) => find_input_device(name),
+        DeviceChoice::Default => default_host().default_input_device(),
+    };
+    let Some(device) = device else {
+        emit_devices_changed(app_handle);
+        return match choice {
+            DeviceChoice::Keep => Ok(()),
+            DeviceChoice::Switch(name) => Err(missing_input_device(&name)),
+            DeviceChoice::Default => Err(AppError::new(
+                ErrorCode::NotFound,
+                "No input device left to fall back to",
+            )
+            .with_hint("Connect a microphone.")),
+        };
+    };
+    info!("Input devices changed, switching to {:?}", device.name());
+    let (config, _) = resolve_stream_config(&device, None).map_err(AppError::device)?;
+    let mic_state = app_handle.state::<MicrophoneState>();
+    let mut mic_state = mic_state.lock().map_err(|err| err.to_string())?;
+    let was_paused = mic_state.recording_state == RecordingState::Paused;
+    mic_state.device.replace(device);
+    mic_state.stream_config = None;
+    let old_stream = mic_state.stream_sender.take();
+    // A recording stopped at its limit is waited for by the stop instead
+    let finished = old_stream
+        .as_ref()
+        .and_then(|_| mic_state.recording_finished.take());
+    drop(mic_state);
+    if let Some(stopper) = &old_stream {
+        // The old stream thread may already be gone with its device
+        let _ = stopper.blocking_send(StreamControl::Switch);
+    }
+    if let Some(finished) = finished {
+        // Its last samples are stored and the archive handed over once it finished
+        let waited = tauri::async_runtime::block_on(tokio::time::timeout(STOP_TIMEOUT, finished));
+        if waited.is_err() {
+            update_recording_state(app_handle, RecordingState::Stopped);
+            return Err(AppError::new(
+                ErrorCode::Internal,
+                "Recording thread did not finish in time for the device switch",
+            ));
+        }
+    }
+    let data_state = app_handle.state::<MicrophoneDataState>();
+    let mut data = data_state.lock().map_err(|err| err.to_string())?;
+    if old_stream.is_some() {
+        // Keep what was recorded so far, in the format of the new device
+        convert_recording(&mut data, &config);
+        drop(data);
+        start_recording(app_handle.clone())?;
+        if was_paused {
+            pause_new_stream(app_handle)?;
+        }
+    } else {
+        data.replace_with_config(&config);
+        drop(data);
+    }
+    emit_devices_changed(app_handle);
+    Ok(())
+}
+
+/// Pause the stream restarted after a device switch, as the recording was paused before
+fn pause_new_stream(app_handle: &AppHandle) -> Result<(), AppError> {
+    debug!("Keeping the recording paused on the new device");
+    let sender = app_handle
+        .state::<MicrophoneState>()
+        .lock()
+        .map_err(|err| err.to_string())?
+        .stream_sender
+        .clone();
+    if let Some(sender) = sender {
+        // Handled before the new stream's first samples are drained
+        sender
+            .blocking_send(StreamControl::Pause)
+            .map_err(|err| AppError::new(ErrorCode::Internal, err))?;
+    }
+    set_recording_state(app_handle, RecordingState::Paused)
+}
+
+/// Convert the recorded audio, including the system audio track, to the stream's format
+fn convert_recording(data: &mut InnerMicrophoneData, config: &StreamConfig) {
+    let InnerMicrophoneData(samples, channels, rate, system) = data;
+    let from = (*channels, *rate);
+    let to = (config.channels, config.sample_rate.0);
+    *samples = convert_audio(samples.make_contiguous(), from, to).into();
+    if let Some(track) = system {
+        track.samples = convert_audio(track.samples.make_contiguous(), from, to).into();
+    }
+    data.update_from_config(config);
+}
+
+/// Convert interleaved audio between channel counts and sample rates.
+///
+/// Channels are mixed down to mono before resampling with linear interpolation, then duplicated.
+/// This is meant for keeping a partial recording when switching devices, not for high quality.
+pub fn convert_audio(samples: &[f32], from: (u16, u32), to: (u16, u32)) -> Vec<f32> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let (from_channels, from_rate) = (usize::from(from.0.max(1)), f64::from(from.1));
+    let (to_channels, to_rate) = (usize::from(to.0.max(1)), f64::from(to.1));
+    #[allow(clippy::cast_precision_loss, reason = "Channel counts are tiny")]
+    let mono = samples
+        .chunks(from_channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect::<Vec<_>>();
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "Recordings are far shorter than 2^52 frames and positions are never negative"
+    )]
+    let resampled = {
+        let ratio = from_rate / to_rate;
+        let frames = (mono.len() as f64 / ratio).round() as usize;
+        (0..frames)
+            .map(|i| {
+                let position = i as f64 * ratio;
+                let index = position.floor() as usize;
+                let fraction = (position - position.floor()) as f32;
+                let current = mono[index.min(mono.len() - 1)];
+                let next = mono[(index + 1).min(mono.len() - 1)];
+                current + (next - current) * fraction
+            })
+            .collect::<Vec<_>>()
+    };
+    resampled
+        .into_iter()
+        .flat_map(|sample| std::iter::repeat_n(sample, to_channels))
+        .collect()
+}
+
+/// Background thread watching for added and removed input devices
+pub struct DeviceWatcher {
+    stop: Sender<()>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DeviceWatcher {
+    /// Spawn the watcher, checking the device list every `interval`
+    pub fn start(app_handle: AppHandle, interval: Duration) -> Self {
+        let (stop, stopped) = channel();
+        let thread = std::thread::Builder::new()
+            .name("device-watcher".into())
+            .spawn(move || {
+                let mut known = Vec::new();
+                loop {
+                    let available = input_device_names().unwrap_or_else(|err| {
+                        error!("Could not list input devices: {err}");
+                        Vec::new()
+                    });
+                    if available != known {
+                        debug!("Input devices changed: {available:?}");
+                        if let Err(err) = on_devices_changed(&app_handle, &available) {
+                            error!("Could not handle device change: {err}");
+                        }
+                        known = available;
+                    }
+                    match stopped.recv_timeout(interval) {
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                debug!("Device watcher stopped");
+            })
+            .map_err(|err| error!("Could not spawn device watcher thread: {err}"))
+            .ok();
+        Self { stop, thread }
+    }
+
+    /// Stop the watcher and wait for it to finish
+    pub fn shutdown(&mut self) {
+        let Some(thread) = self.thread.take() else {
+            return;
+        };
+        let _ = self.stop.send(());
+        if thread.join().is_err() {
+            error!("Device watcher thread panicked");
+        }
+    }
+}
+
+impl Drop for DeviceWatcher {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+pub type DeviceWatcherState = Mutex<DeviceWatcher>;
+
+#[test]
+fn test_choose_device() {
+    let available = vec!["USB Mic".to_string(), "Built-in".to_string()];
+    let choose = |current, preferred, is_recording| {
+        choose_device(&available, current, preferred, is_recording)
+    };
+    assert_eq!(choose(Some("Built-in"), None, false), DeviceChoice::Keep);
+    assert_eq!(
+        choose(Some("Built-in"), Some("USB Mic"), false),
+        DeviceChoice::Switch("USB Mic".into())
+    );
+    // Do not interrupt a working recording for the preferred device
+    assert_eq!(
+        choose(Some("Built-in"), Some("USB Mic"), true),
+        DeviceChoice::Keep
+    );
+    assert_eq!(choose(Some("Headset"), None, true), DeviceChoice::Default);
+    assert_eq!(
+        choose(Some("Headset"), Some("Headset"), false),
+        DeviceChoice::Default
+    );
+    assert_eq!(
+        choose(Some("Headset"), Some("USB Mic"), true),
+        DeviceChoice::Switch("USB Mic".into())
+    );
+}
+
+#[test]
+fn test_convert_audio() {
+    let stereo = [0.0, 1.0, 0.5, 0.5, 1.0, 0.0, 0.5, 0.5];
+    assert_eq!(convert_audio(&stereo, (2, 4), (1, 4)), vec![0.5; 4]);
+    let mono = [0.0, 1.0, 0.0, 1.0];
+    let upsampled = convert_audio(&mono, (1, 2), (2, 4));
+    assert_eq!(upsampled.len(), 16);
+    assert!((upsampled[2] - 0.5).abs() < f32::EPSILON);
+    assert_eq!(convert_audio(&mono, (1, 4), (1, 4)), mono.to_vec());
+}
+
+#[test]
+fn test_switch_mid_recording_keeps_one_archive() {
+    use crate::capture::AudioArchive;
+    use rodio::cpal::{BufferSize, SampleRate};
+
+    let directory = std::env::temp_dir().join(format!("switch-test-{}", std::process::id()));
+    let first = StreamConfig {
+        channels: 2,
+        sample_rate: SampleRate(8_000),
+        buffer_size: BufferSize::Default,
+    };
+    let second = StreamConfig {
+        channels: 1,
+        sample_rate: SampleRate(16_000),
+        buffer_size: BufferSize::Default,
+    };
+    let mut data = InnerMicrophoneData::new();
+    data.update_from_config(&first);
+    let mut archive = AudioArchive::create(&directory, &first).expect("Archive should be created");
+    // A second of stereo audio on the first device
+    let recorded = vec![0.5; 16_000];
+    data.0.extend(&recorded);
+    archive.write(&recorded).expect("Samples should be written");
+
+    // The switch converts the recording and continues the archive on the second device
+    convert_recording(&mut data, &second);
+    assert_eq!((data.1, data.2), (1, 16_000));
+    assert_eq!(data.0.len(), 16_000);
+    archive.continue_with(&second);
+    // Half a second of mono audio on the second device
+    let recorded = vec![0.25; 8_000];
+    data.0.extend(&recorded);
+    archive.write(&recorded).expect("Samples should be written");
+
+    let path = archive.finish().expect("Archive should be finished");
+    assert!(path.starts_with(&directory));
+    let mut reader = audrey::hound::WavReader::open(&path).expect("Archive should be readable");
+    assert_eq!(reader.spec().channels, 2);
+    assert_eq!(reader.spec().sample_rate, 8_000);
+    let archived = reader
+        .samples::<f32>()
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Samples should be readable");
+    // One and a half seconds of stereo audio at the first device's rate
+    assert_eq!(archived.len(), 24_000);
+    assert!((archived[0] - 0.5).abs() < f32::EPSILON);
+    assert!((archived[23_999] - 0.25).abs() < f32::EPSILON);
+    assert_eq!(
+        std::fs::read_dir(&directory).map(Iterator::count).ok(),
+        Some(1)
+    );
+    let _ = std::fs::remove_dir_all(directory);
+}
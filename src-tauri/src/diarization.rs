@@ -0,0 +1,297 @@
+//! Speaker diarization by clustering the voice features of transcript segments on the CPU.
+//!
+//! Each utterance is described by its pitch (from the autocorrelation), zero crossing rate and
+//! spectral tilt. Utterances are then merged bottom-up into clusters of similar voices, one
+//! cluster per speaker. This is no match for neural speaker embeddings, but it needs no extra
+//! model and tells apart voices as different as in most meetings.
+
+use crate::transcript::Utterance;
+use log::{debug, trace};
+
+/// Sample rate of the audio given to whisper
+const SAMPLE_RATE: usize = 16_000;
+
+/// Audio is decimated by this factor before looking for the pitch
+const DECIMATION: usize = 2;
+
+/// Length of an analysis frame in decimated samples (32 ms)
+const FRAME: usize = 256;
+
+/// Distance between analysis frames in decimated samples (20 ms)
+const HOP: usize = 160;
+
+/// Lags of the pitch search in decimated samples, 400 Hz to 60 Hz
+const MIN_LAG: usize = SAMPLE_RATE / DECIMATION / 400;
+const MAX_LAG: usize = SAMPLE_RATE / DECIMATION / 60;
+
+/// Minimum normalized autocorrelation for a frame to count as voiced
+const VOICED_CORRELATION: f32 = 0.5;
+
+/// Frames quieter than this RMS are skipped
+const MIN_FRAME_RMS: f32 = 0.005;
+
+/// Clusters closer than this (in standard deviations of the features) are the same speaker
+const MERGE_DISTANCE: f32 = 1.0;
+
+/// Features of one utterance
+type Features = [f32; 4];
+
+/// Label the utterances with speaker numbers starting at 1, in order of first appearance.
+///
+/// `audio` is the 16 kHz mono audio the utterances were transcribed from. Utterances without
+/// voiced audio get the speaker of the previous utterance.
+pub fn assign_speakers(utterances: &mut [Utterance], audio: &[f32], max_speakers: u8) {
+    let features = utterances
+        .iter()
+        .map(|utterance| {
+            // Whisper may give a stop before the start
+            let stop = sample_index(utterance.stop).min(audio.len());
+            let start = sample_index(utterance.start).min(stop);
+            voice_features(&audio[start..stop])
+        })
+        .collect::<Vec<_>>();
+    let voiced = features.iter().flatten().copied().collect::<Vec<_>>();
+    let mut clusters = cluster(&normalize(&voiced), usize::from(max_speakers.max(1))).into_iter();
+    debug!("Diarized {} voiced utterances", voiced.len());
+    let mut speaker = None;
+    for (utterance, features) in utterances.iter_mut().zip(&features) {
+        if features.is_some() {
+            speaker = clusters.next();
+        }
+        utterance.speaker = speaker.or(Some(1));
+    }
+}
+
+/// Sample index of a timestamp, which is given in hundredths of a second
+fn sample_index(timestamp: i64) -> usize {
+    usize::try_from(timestamp).unwrap_or_default() * (SAMPLE_RATE / 100)
+}
+
+/// Describe the voice in the audio, `None` if there is no voiced frame
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "Frame lengths, lags and counts are far below 2^23"
+)]
+fn voice_features(audio: &[f32]) -> Option<Features> {
+    let decimated = audio
+        .chunks_exact(DECIMATION)
+        .map(|pair| pair.iter().sum::<f32>() / pair.len() as f32)
+        .collect::<Vec<_>>();
+    let mut pitches = Vec::new();
+    let (mut crossings, mut tilt, mut frames) = (0.0, 0.0, 0.0);
+    for start in (0..decimated.len().saturating_sub(FRAME)).step_by(HOP) {
+        let frame = &decimated[start..start + FRAME];
+        let energy = frame.iter().map(|sample| sample * sample).sum::<f32>();
+        if (energy / FRAME as f32).sqrt() < MIN_FRAME_RMS {
+            continue;
+        }
+        let Some(lag) = pitch_lag(frame) else {
+            continue;
+        };
+        pitches.push((SAMPLE_RATE / DECIMATION) as f32 / lag as f32);
+        crossings += frame
+            .windows(2)
+            .filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0))
+            .count() as f32
+            / FRAME as f32;
+        let difference = frame
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).powi(2))
+            .sum::<f32>();
+        tilt += (difference / energy).ln();
+        frames += 1.0;
+    }
+    if pitches.is_empty() {
+        return None;
+    }
+    pitches.sort_by(f32::total_cmp);
+    let log_pitches = pitches.iter().map(|pitch| pitch.ln()).collect::<Vec<_>>();
+    let mean = log_pitches.iter().sum::<f32>() / frames;
+    let spread = (log_pitches
+        .iter()
+        .map(|pitch| (pitch - mean).powi(2))
+        .sum::<f32>()
+        / frames)
+        .sqrt();
+    trace!(
+        "Voice of {frames} frames has median pitch {}",
+        pitches[pitches.len() / 2]
+    );
+    Some([
+        pitches[pitches.len() / 2].ln(),
+        spread,
+        crossings / frames,
+        tilt / frames,
+    ])
+}
+
+/// Lag of the pitch period, the shortest one close to the highest autocorrelation
+fn pitch_lag(frame: &[f32]) -> Option<usize> {
+    let correlations = (MIN_LAG..=MAX_LAG)
+        .map(|lag| {
+            let (head, tail) = (&frame[..frame.len() - lag], &frame[lag..]);
+            let product = head.iter().zip(tail).map(|(a, b)| a * b).sum::<f32>();
+            let energy =
+                head.iter().map(|a| a * a).sum::<f32>() * tail.iter().map(|b| b * b).sum::<f32>();
+            (lag, product / energy.sqrt().max(f32::EPSILON))
+        })
+        .collect::<Vec<_>>();
+    let best = correlations
+        .iter()
+        .map(|(_, correlation)| *correlation)
+        .fold(f32::MIN, f32::max);
+    if best < VOICED_CORRELATION {
+        return None;
+    }
+    // Multiples of the period correlate about as well, so take the peak of the shortest lag
+    let start = correlations
+        .iter()
+        .position(|&(_, correlation)| correlation >= best * 0.9)?;
+    let rising = correlations[start..]
+        .windows(2)
+        .take_while(|pair| pair[1].1 >= pair[0].1)
+        .count();
+    Some(correlations[start + rising].0)
+}
+
+/// Scale every feature to zero mean and unit variance
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "Utterance counts are far below 2^23"
+)]
+fn normalize(features: &[Features]) -> Vec<Features> {
+    let count = features.len().max(1) as f32;
+    let mut normalized = features.to_vec();
+    for dimension in 0..4 {
+        let mean = features.iter().map(|f| f[dimension]).sum::<f32>() / count;
+        let deviation = (features
+            .iter()
+            .map(|f| (f[dimension] - mean).powi(2))
+            .sum::<f32>()
+            / count)
+            .sqrt();
+        for feature in &mut normalized {
+            feature[dimension] = (feature[dimension] - mean) / deviation.max(0.1);
+        }
+    }
+    normalized
+}
+
+/// Cluster the features bottom-up with average linkage, returning speaker numbers starting at 1.
+///
+/// The distances between clusters are kept in a matrix and updated with the Lance-Williams formula
+/// on every merge, instead of averaging over all pairs of members again.
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "Utterance counts are far below 2^23"
+)]
+fn cluster(features: &[Features], max_clusters: usize) -> Vec<u32> {
+    let distance = |a: &Features, b: &Features| {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| (a - b).powi(2))
+            .sum::<f32>()
+            .sqrt()
+    };
+    let mut distances = features
+        .iter()
+        .map(|a| features.iter().map(|b| distance(a, b)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    // Members of each cluster, empty once merged into another one
+    let mut clusters = (0..features.len()).map(|i| vec![i]).collect::<Vec<_>>();
+    let mut remaining = clusters.len();
+    while remaining > 1 {
+        let mut closest = (f32::MAX, 0, 0);
+        for (i, row) in distances.iter().enumerate() {
+            if clusters[i].is_empty() {
+                continue;
+            }
+            for (j, &average) in row.iter().enumerate().skip(i + 1) {
+                if !clusters[j].is_empty() && average < closest.0 {
+                    closest = (average, i, j);
+                }
+            }
+        }
+        let (average, i, j) = closest;
+        if average > MERGE_DISTANCE && remaining <= max_clusters {
+            break;
+        }
+        let (size_i, size_j) = (clusters[i].len() as f32, clusters[j].len() as f32);
+        let merged_row = distances[i]
+            .iter()
+            .zip(&distances[j])
+            .map(|(to_i, to_j)| size_i.mul_add(*to_i, size_j * to_j) / (size_i + size_j))
+            .collect::<Vec<_>>();
+        for (row, &average) in distances.iter_mut().zip(&merged_row) {
+            row[i] = average;
+        }
+        distances[i] = merged_row;
+        let merged = std::mem::take(&mut clusters[j]);
+        clusters[i].extend(merged);
+        remaining -= 1;
+    }
+    clusters.retain(|members| !members.is_empty());
+    // Number the speakers in order of their first utterance
+    clusters.sort_by_key(|members| members.iter().min().copied());
+    let mut speakers = vec![0; features.len()];
+    for (speaker, members) in (1..).zip(&clusters) {
+        for &member in members {
+            speakers[member] = speaker;
+        }
+    }
+    speakers
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "Test signals are short"
+)]
+/// Voice-like signal with a few harmonics of the pitch
+fn synthetic_voice(pitch: f32, seconds: f32) -> Vec<f32> {
+    let rate = SAMPLE_RATE as f32;
+    (0..(seconds * rate) as usize)
+        .map(|i| {
+            let phase = std::f32::consts::TAU * pitch * i as f32 / rate;
+            let harmonics = 0.5_f32.mul_add((2.0 * phase).sin(), phase.sin());
+            0.3 * 0.25_f32.mul_add((3.0 * phase).sin(), harmonics)
+        })
+        .collect()
+}
+
+#[test]
+fn test_pitch_of_synthetic_voice() {
+    let features = voice_features(&synthetic_voice(200.0, 0.5)).expect("Voice should be found");
+    assert!((features[0].exp() - 200.0).abs() < 10.0);
+    assert!(voice_features(&[0.0; 8_000]).is_none());
+}
+
+#[test]
+fn test_assign_speakers() {
+    let mut audio = Vec::new();
+    let mut utterances = Vec::new();
+    for (i, pitch) in [110.0, 220.0, 115.0, 0.0, 210.0].into_iter().enumerate() {
+        let start = i64::try_from(i).unwrap_or_default() * 100;
+        audio.extend(synthetic_voice(pitch, 1.0));
+        utterances.push(Utterance {
+            start,
+            stop: start + 100,
+            text: format!("Utterance {i}"),
+            speaker: None,
+            hallucination: None,
+            confidence: None,
+            alternatives: Vec::new(),
+            source: None,
+        });
+    }
+    // Backwards timestamps are skipped instead of panicking
+    utterances[3].stop = utterances[3].start - 50;
+    assign_speakers(&mut utterances, &audio, 4);
+    let speakers = utterances
+        .iter()
+        .map(|utterance| utterance.speaker)
+        .collect::<Vec<_>>();
+    assert_eq!(speakers, [1, 2, 1, 1, 2].map(Some));
+}
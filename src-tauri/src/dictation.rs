@@ -0,0 +1,244 @@
+//! Dictation pipeline run by the backend (record, transcribe, process and output),
+//! used by hotkey bindings so they work without the webview.
+
+use crate::{
+    command::{
+        output_text, start_microphone_recording, stop_microphone_recording,
+        stop_transcribe_and_process_data,
+    },
+    error::{AppError, ErrorCode},
+    events::{DictationCompletedEvent, HotkeyTriggeredEvent},
+    history::TranscriptHistoryState,
+    hotkeys::{BindingAction, HotkeyState, HotkeyTrigger, InputEvent, TriggerPhase},
+    settings::SettingsState,
+    types::{AppState, DictationOptions, MicrophoneDataState, MicrophoneState},
+};
+use log::{debug, error, info, trace};
+use std::{sync::Mutex, time::Instant};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+use tauri_specta::Event;
+use tokio::sync::mpsc;
+
+/// Queue running the actions of triggered bindings one after another, in trigger order.
+///
+/// A quick press and release must not stop or cancel a recording before it has started.
+pub struct DictationQueue {
+    sender: mpsc::UnboundedSender<HotkeyTrigger>,
+    /// Processed text of the last dictation, also when it was held back for low confidence
+    last_text: Mutex<Option<String>>,
+}
+
+impl DictationQueue {
+    /// Start the worker task running the queued actions
+    pub fn start(app_handle: AppHandle) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<HotkeyTrigger>();
+        tauri::async_runtime::spawn(async move {
+            while let Some(trigger) = receiver.recv().await {
+                if let Err(err) = run_trigger(&app_handle, trigger).await {
+                    error!("Hotkey action {:?} failed: {err}", trigger.action);
+                }
+            }
+            debug!("Dictation queue finished");
+        });
+        Self {
+            sender,
+            last_text: Mutex::new(None),
+        }
+    }
+
+    /// Remember the text of a dictation for re-pasting
+    fn remember(&self, text: &str) {
+        match self.last_text.lock() {
+            Ok(mut last_text) => *last_text = Some(text.to_string()),
+            Err(err) => error!("Could not remember dictated text: {err}"),
+        }
+    }
+}
+
+/// Pass an input event to the hotkey engine and run every triggered action.
+pub fn handle_input_event(app_handle: &AppHandle, event: &InputEvent) {
+    let triggers = match app_handle.state::<HotkeyState>().lock() {
+        Ok(mut engine) => engine.handle(event, Instant::now()),
+        Err(err) => {
+            error!("Could not get hotkey engine lock: {err}");
+            return;
+        }
+    };
+    for trigger in triggers {
+        debug!("Hotkey triggered: {trigger:?}");
+        let _ = HotkeyTriggeredEvent::with_payload(trigger)
+            .emit(app_handle)
+            .map_err(|err| error!("Error for hotkey triggered event: {err}"));
+        if app_handle
+            .state::<DictationQueue>()
+            .sender
+            .send(trigger)
+            .is_err()
+        {
+            error!(
+                "Dictation queue stopped, dropping hotkey action {:?}",
+                trigger.action
+            );
+        }
+    }
+}
+
+/// Execute the action of a triggered binding
+async fn run_trigger(app_handle: &AppHandle, trigger: HotkeyTrigger) -> Result<(), AppError> {
+    match (trigger.action, trigger.phase) {
+        (BindingAction::ToggleRecording, TriggerPhase::Pressed) => {
+            if is_recording(app_handle)? {
+                finish(app_handle).await
+            } else {
+                start(app_handle).await
+            }
+        }
+        (BindingAction::StartRecording | BindingAction::PushToTalk, TriggerPhase::Pressed) => {
+            start(app_handle).await
+        }
+        (BindingAction::StopRecording, TriggerPhase::Pressed)
+        | (BindingAction::PushToTalk, TriggerPhase::Released) => finish(app_handle).await,
+        (BindingAction::Cancel, TriggerPhase::Pressed)
+        | (BindingAction::PushToTalk, TriggerPhase::Cancelled) => cancel(app_handle).await,
+        (BindingAction::RePaste, TriggerPhase::Pressed) => repaste(app_handle).await,
+        (action, TriggerPhase::Released | TriggerPhase::Cancelled) => {
+            trace!("Nothing to do on release for {action:?}");
+            Ok(())
+        }
+    }
+}
+
+fn is_recording(app_handle: &AppHandle) -> Result<bool, AppError> {
+    Ok(app_handle
+        .state::<MicrophoneState>()
+        .lock()
+        .map_err(|err| err.to_string())?
+        .is_recording())
+}
+
+fn options(app_handle: &AppHandle) -> Result<DictationOptions, AppError> {
+    Ok(app_handle
+        .state::<SettingsState>()
+        .lock()
+        .map_err(|err| err.to_string())?
+        .dictation
+        .clone())
+}
+
+/// Start recording, unless already recording
+pub async fn start(app_handle: &AppHandle) -> Result<(), AppError> {
+    if is_recording(app_handle)? {
+        debug!("Already recording, ignore start");
+        return Ok(());
+    }
+    info!("Starting dictation");
+    start_microphone_recording(app_handle.clone())
+        .await
+        .map(|_| ())
+}
+
+/// Stop recording, then transcribe, process and output the text
+pub async fn finish(app_handle: &AppHandle) -> Result<(), AppError> {
+    if !is_recording(app_handle)? {
+        debug!("Not recording, ignore finish");
+        return Ok(());
+    }
+    info!("Finishing dictation");
+    let options = options(app_handle)?;
+    let result = stop_transcribe_and_process_data(
+        app_handle.state::<AppState>(),
+        app_handle.state::<MicrophoneState>(),
+        app_handle.clone(),
+        Some(0),
+        options.transcribe_options,
+        options.processing_options.unwrap_or_default(),
+        options.decode_options,
+    )
+    .await?;
+    let _ = DictationCompletedEvent::with_payload(
+        result.text.clone(),
+        result.processing_time,
+        result.archive_path,
+        result.confidence,
+    )
+    .emit(app_handle)
+    .map_err(|err| error!("Error for dictation completed event: {err}"));
+    let text = result.text;
+    if text.trim().is_empty() {
+        debug!("Empty transcript, nothing to output");
+        return Ok(());
+    }
+    if result.format.is_structured() {
+        debug!("Not outputting {:?} transcript", result.format);
+        return Ok(());
+    }
+    app_handle.state::<DictationQueue>().remember(&text);
+    if let Some((confidence, threshold)) = result.confidence.zip(options.min_confidence) {
+        if confidence < threshold {
+            info!("Transcript confidence {confidence} below {threshold}, not outputting it");
+            return app_handle
+                .notification()
+                .builder()
+                .title("Transcript not pasted")
+                .body(format!(
+                    "Only {:.0}% confident, re-paste to output anyway: {}",
+                    confidence * 100.0,
+                    text.trim()
+                ))
+                .show()
+                .map_err(|err| AppError::new(ErrorCode::Internal, err));
+        }
+    }
+    output_text(app_handle.clone(), text, options.output_options).await
+}
+
+/// Stop recording and throw away the recorded audio
+pub async fn cancel(app_handle: &AppHandle) -> Result<(), AppError> {
+    info!("Cancelling dictation");
+    stop_microphone_recording(
+        app_handle.clone(),
+        app_handle.state::<MicrophoneState>(),
+        None,
+    )
+    .await?;
+    app_handle
+        .state::<MicrophoneDataState>()
+        .lock()
+        .map_err(|err| err.to_string())?
+        .clear();
+    Ok(())
+}
+
+/// Output the text of the last dictation again, or the last transcript if there was none
+pub async fn repaste(app_handle: &AppHandle) -> Result<(), AppError> {
+    let dictated = app_handle
+        .state::<DictationQueue>()
+        .last_text
+        .lock()
+        .map_err(|err| err.to_string())?
+        .clone();
+    let last = match dictated {
+        Some(text) => Some(text),
+        None => app_handle
+            .state::<TranscriptHistoryState>()
+            .lock()
+            .map_err(|err| err.to_string())?
+            .last()
+            .map(|entry| entry.text.clone()),
+    };
+    match last {
+        Some(text) => {
+            output_text(
+                app_handle.clone(),
+                text,
+                options(app_handle)?.output_options,
+            )
+            .await
+        }
+        None => {
+            debug!("No previous transcript to paste");
+            Ok(())
+        }
+    }
+}
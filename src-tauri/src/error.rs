@@ -0,0 +1,214 @@
+//! Errors returned by the commands, typed so the frontend can react to the kind of failure.
+//!
+//! Every error carries an [`ErrorCode`], the message of what went wrong, and a hint for the user
+//! on how to fix it. Plain `String` errors of internal helpers become [`ErrorCode::Internal`]
+//! when passed on with `?`, unless they are wrapped with a more precise code first.
+
+use crate::{mutter::ModelError, output::OutputError};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::{fmt::Display, sync::PoisonError};
+use whisper_rs::WhisperError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// Kind of failure, telling the frontend what the user can do about it
+pub enum ErrorCode {
+    /// The whisper model could not be loaded
+    ModelLoad,
+    /// The audio could not be decoded
+    Decode,
+    /// whisper.cpp failed while transcribing
+    Transcription,
+    /// The input device is missing, unplugged or does not support the configuration
+    Device,
+    /// The system denied access to the microphone or to sending input
+    Permission,
+    /// Another recording or transcription is in progress
+    Busy,
+    /// The recording was stopped or cancelled before the action could run
+    Cancelled,
+    /// The action needs a recording, but none is running
+    NotRecording,
+    /// Reading or writing a file, socket or other resource failed
+    Io,
+    /// Typing or pasting the text into the focused application failed
+    InputInjection,
+    /// A setting is out of range
+    InvalidSettings,
+    /// An argument of the command is invalid, e.g. out of range or missing its counterpart
+    InvalidInput,
+    /// The item the command refers to does not exist
+    NotFound,
+    /// Any other failure, e.g. a poisoned lock
+    Internal,
+}
+
+impl ErrorCode {
+    /// What the user can do to fix the failure
+    #[must_use]
+    pub const fn hint(self) -> &'static str {
+        match self {
+            Self::ModelLoad => {
+                "Check that the file is a whisper model, or switch back to the default model."
+            }
+            Self::Decode => "Record again, or use a WAV, MP3, FLAC or OGG file.",
+            Self::Transcription => "Try again, or choose a smaller model or fewer threads.",
+            Self::Device => "Select another microphone, or reconnect this one.",
+            Self::Permission => {
+                "Allow microphone and accessibility access for the app in the system settings."
+            }
+            Self::Busy => "Wait until the current recording or transcription has finished.",
+            Self::Cancelled => "Start a new recording.",
+            Self::NotRecording => "Start a recording first.",
+            Self::Io => "Check that the file or folder exists and can be written to.",
+            Self::InputInjection => {
+                "Focus the application to output to, or copy the text from the history instead."
+            }
+            Self::InvalidSettings => "Correct the named settings and save again.",
+            Self::InvalidInput => "Correct the named value and try again.",
+            Self::NotFound => "Refresh the view, the item may have changed or been removed.",
+            Self::Internal => "Try again, and restart the app if it keeps failing.",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// Error returned by a command
+pub struct AppError {
+    pub code: ErrorCode,
+    /// Description of what went wrong
+    pub message: String,
+    /// What the user can do about it, see [`ErrorCode::hint`]
+    pub hint: String,
+}
+
+impl AppError {
+    /// Error with the hint of its code
+    #[must_use]
+    pub fn new(code: ErrorCode, message: impl Display) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+            hint: code.hint().to_string(),
+        }
+    }
+
+    /// Replace the hint of the code with one specific to this failure
+    #[must_use]
+    pub fn with_hint(mut self, hint: impl Display) -> Self {
+        self.hint = hint.to_string();
+        self
+    }
+
+    /// Error of an input device, or [`ErrorCode::Permission`] if the message says access was
+    /// denied, as audio backends only report that in their messages.
+    #[must_use]
+    pub fn device(message: impl Display) -> Self {
+        let message = message.to_string();
+        let lowercase = message.to_lowercase();
+        let is_denied = ["permission", "denied", "not authorized"]
+            .iter()
+            .any(|phrase| lowercase.contains(phrase));
+        Self::new(
+            if is_denied {
+                ErrorCode::Permission
+            } else {
+                ErrorCode::Device
+            },
+            message,
+        )
+    }
+}
+
+impl Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+}
+
+impl<T> From<PoisonError<T>> for AppError {
+    fn from(err: PoisonError<T>) -> Self {
+        Self::new(ErrorCode::Internal, err)
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(err: tauri::Error) -> Self {
+        Self::new(ErrorCode::Internal, err)
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(err: std::io::Error) -> Self {
+        let code = match err.kind() {
+            std::io::ErrorKind::PermissionDenied => ErrorCode::Permission,
+            _ => ErrorCode::Io,
+        };
+        Self::new(code, err)
+    }
+}
+
+impl From<ModelError> for AppError {
+    fn from(err: ModelError) -> Self {
+        match err {
+            ModelError::WhisperError(whisper_error) => {
+                Self::new(ErrorCode::Transcription, whisper_error)
+            }
+            ModelError::DecodingError(decoder_error) => Self::new(ErrorCode::Decode, decoder_error),
+            ModelError::UnknownLanguages(allowed) => Self::new(
+                ErrorCode::InvalidSettings,
+                format!("None of the allowed languages {allowed:?} is known"),
+            ),
+        }
+    }
+}
+
+impl From<WhisperError> for AppError {
+    /// Loading is the only whisper call made outside of [`ModelError`]
+    fn from(err: WhisperError) -> Self {
+        Self::new(ErrorCode::ModelLoad, err)
+    }
+}
+
+impl From<OutputError> for AppError {
+    fn from(err: OutputError) -> Self {
+        let code = match err {
+            OutputError::NoBackend(_) => ErrorCode::Permission,
+            OutputError::Input(_) | OutputError::Clipboard(_) | OutputError::Unsupported(_) => {
+                ErrorCode::InputInjection
+            }
+            OutputError::Io(_) | OutputError::Command(_) => ErrorCode::Io,
+        };
+        Self::new(code, err)
+    }
+}
+
+#[test]
+fn test_error_codes() {
+    let denied = AppError::device("The user denied microphone Permission");
+    assert_eq!(denied.code, ErrorCode::Permission);
+    assert_eq!(AppError::device("Device unplugged").code, ErrorCode::Device);
+    let output = AppError::from(OutputError::Input("Key press failed".into()));
+    assert_eq!(output.code, ErrorCode::InputInjection);
+    assert_eq!(output.hint, ErrorCode::InputInjection.hint());
+    let io = AppError::from(std::io::Error::from(std::io::ErrorKind::NotFound));
+    assert_eq!(io.code, ErrorCode::Io);
+    assert_eq!(AppError::from("Lock poisoned").code, ErrorCode::Internal);
+    let missing = AppError::new(ErrorCode::NotFound, "No sound named beep").with_hint("Use start");
+    assert_eq!(missing.code, ErrorCode::NotFound);
+    assert_eq!(missing.hint, "Use start");
+}
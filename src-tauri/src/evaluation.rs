@@ -0,0 +1,288 @@
+//! Accuracy evaluation of the transcription pipeline on a folder of recordings.
+//!
+//! Every `<name>.wav` in the folder is paired with its reference transcript `<name>.txt`. All
+//! recordings are transcribed with several audio-processing variants through
+//! [`Model::transcribe_audio`] like in the app, so that changes to the defaults of
+//! [`crate::mutter::decode_and_denoise`] are judged by their error rates.
+
+use crate::{
+    metrics::{character_error_rate, word_error_rate},
+    mutter::{Model, ModelError, TranscribeCallbacks},
+    types::{AudioProcessingOptions, TranscribeOptions},
+};
+use audrey::hound::WavReader;
+use log::{debug, info};
+use serde::Serialize;
+use std::{
+    fmt::Write,
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+/// A recording with the text spoken in it
+struct Sample {
+    name: String,
+    wav: Vec<u8>,
+    seconds: f64,
+    reference: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+/// Result of transcribing one recording
+pub struct SampleResult {
+    pub name: String,
+    pub word_error_rate: f64,
+    pub character_error_rate: f64,
+    /// Seconds spent decoding, processing and transcribing the audio
+    pub processing_seconds: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+/// Results of all recordings with one audio-processing variant
+pub struct VariantReport {
+    pub name: String,
+    pub options: AudioProcessingOptions,
+    /// Mean word error rate of the recordings
+    pub word_error_rate: f64,
+    /// Mean character error rate of the recordings
+    pub character_error_rate: f64,
+    /// Processing seconds per second of audio
+    pub real_time_factor: f64,
+    pub samples: Vec<SampleResult>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+/// Results of an evaluation, one report per audio-processing variant
+pub struct EvaluationReport {
+    pub variants: Vec<VariantReport>,
+}
+
+impl EvaluationReport {
+    /// Returns the report as a Markdown table, one row per variant.
+    #[must_use]
+    pub fn as_markdown(&self) -> String {
+        self.variants.iter().fold(
+            "| Variant | WER | CER | RTF |\n|---|---|---|---|\n".to_string(),
+            |mut table, variant| {
+                let _ = writeln!(
+                    table,
+                    "| {} | {:.1}% | {:.1}% | {:.2} |",
+                    variant.name,
+                    variant.word_error_rate * 100.0,
+                    variant.character_error_rate * 100.0,
+                    variant.real_time_factor
+                );
+                table
+            },
+        )
+    }
+}
+
+/// Audio-processing variants compared by the evaluation, the current defaults first
+fn variants() -> Vec<(&'static str, AudioProcessingOptions)> {
+    let wide_filter = AudioProcessingOptions {
+        low_pass_value: Some(8_000),
+        high_pass_value: Some(80),
+        ..Default::default()
+    };
+    vec![
+        ("default", AudioProcessingOptions::default()),
+        (
+            "no denoise",
+            AudioProcessingOptions {
+                denoise_audio: Some(false),
+                ..Default::default()
+            },
+        ),
+        (
+            "normalize",
+            AudioProcessingOptions {
+                normalize_result: Some(true),
+                ..Default::default()
+            },
+        ),
+        ("wide filter", wide_filter),
+        (
+            "no denoise, wide filter",
+            AudioProcessingOptions {
+                denoise_audio: Some(false),
+                ..wide_filter
+            },
+        ),
+    ]
+}
+
+/// Load the WAV files of the folder that have a reference transcript, sorted by name
+fn load_samples(folder: &Path) -> Result<Vec<Sample>, String> {
+    let mut paths = fs::read_dir(folder)
+        .map_err(|err| format!("Could not read {}: {err}", folder.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("wav"))
+        })
+        .collect::<Vec<PathBuf>>();
+    paths.sort();
+    let mut samples = Vec::new();
+    for path in paths {
+        let Ok(reference) = fs::read_to_string(path.with_extension("txt")) else {
+            debug!(
+                "Skipping {}, it has no reference transcript",
+                path.display()
+            );
+            continue;
+        };
+        let wav = fs::read(&path).map_err(|err| err.to_string())?;
+        let reader = WavReader::new(Cursor::new(&wav))
+            .map_err(|err| format!("Could not read {}: {err}", path.display()))?;
+        let spec = reader.spec();
+        let seconds = f64::from(reader.duration()) / f64::from(spec.sample_rate.max(1));
+        samples.push(Sample {
+            name: path
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned()),
+            wav,
+            seconds,
+            reference,
+        });
+    }
+    Ok(samples)
+}
+
+/// Transcribe every recording of the folder with every audio-processing variant.
+///
+/// # Errors
+///
+/// Returns an error if the model or a recording can not be loaded, or the transcription fails.
+pub fn evaluate(
+    model_path: &str,
+    folder: &Path,
+    language: Option<&str>,
+    threads: Option<u16>,
+) -> Result<EvaluationReport, String> {
+    let samples = load_samples(folder)?;
+    if samples.is_empty() {
+        return Err(format!(
+            "No WAV files with reference transcripts in {}",
+            folder.display()
+        ));
+    }
+    info!("Evaluating {} recordings", samples.len());
+    let model = Model::new(model_path).map_err(|err| err.to_string())?;
+    let total_seconds = samples.iter().map(|sample| sample.seconds).sum::<f64>();
+    let mut reports = Vec::new();
+    for (name, options) in variants() {
+        let results = samples
+            .iter()
+            .map(|sample| transcribe_sample(&model, sample, options, language, threads))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| match err {
+                ModelError::WhisperError(whisper_error) => whisper_error.to_string(),
+                ModelError::DecodingError(decoder_error) => decoder_error.to_string(),
+                ModelError::UnknownLanguages(allowed) => {
+                    format!("None of the allowed languages {allowed:?} is known")
+                }
+            })?;
+        #[allow(clippy::cast_precision_loss, reason = "Far less than 2^52 recordings")]
+        let count = results.len() as f64;
+        let report = VariantReport {
+            name: name.to_string(),
+            options,
+            word_error_rate: results.iter().map(|r| r.word_error_rate).sum::<f64>() / count,
+            character_error_rate: results.iter().map(|r| r.character_error_rate).sum::<f64>()
+                / count,
+            real_time_factor: results.iter().map(|r| r.processing_seconds).sum::<f64>()
+                / total_seconds.max(f64::EPSILON),
+            samples: results,
+        };
+        info!(
+            "Variant {name}: WER={:.3}, CER={:.3}",
+            report.word_error_rate, report.character_error_rate
+        );
+        reports.push(report);
+    }
+    Ok(EvaluationReport { variants: reports })
+}
+
+/// Run a recording through the pipeline and compare the transcript to its reference
+fn transcribe_sample(
+    model: &Model,
+    sample: &Sample,
+    options: AudioProcessingOptions,
+    language: Option<&str>,
+    threads: Option<u16>,
+) -> Result<SampleResult, ModelError> {
+    let start = Instant::now();
+    let transcribe_options = TranscribeOptions {
+        language: language.map(str::to_string),
+        threads,
+        ..TranscribeOptions::default()
+    };
+    // The same entry as the app, which skips all processing without denoising
+    let transcript = model.transcribe_audio(
+        &sample.wav,
+        &transcribe_options,
+        options,
+        TranscribeCallbacks::default(),
+    )?;
+    let text = transcript.as_text();
+    debug!("Transcript of {}: {text}", sample.name);
+    Ok(SampleResult {
+        name: sample.name.clone(),
+        word_error_rate: word_error_rate(&sample.reference, &text),
+        character_error_rate: character_error_rate(&sample.reference, &text),
+        processing_seconds: start.elapsed().as_secs_f64(),
+    })
+}
+
+#[test]
+fn test_load_samples_pairs_references() {
+    use audrey::hound::{SampleFormat, WavSpec, WavWriter};
+
+    let folder = std::env::temp_dir().join(format!("evaluation-{}", std::process::id()));
+    fs::create_dir_all(&folder).expect("Temporary folder should be writable");
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 16_000,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    for name in ["b", "a", "unpaired"] {
+        let mut writer =
+            WavWriter::create(folder.join(format!("{name}.wav")), spec).expect("Create WAV");
+        for _ in 0..8_000 {
+            writer.write_sample(0_i16).expect("Write sample");
+        }
+        writer.finalize().expect("Finish WAV");
+    }
+    fs::write(folder.join("a.txt"), "First.").expect("Write reference");
+    fs::write(folder.join("b.txt"), "Second.").expect("Write reference");
+    let samples = load_samples(&folder).expect("Samples should load");
+    fs::remove_dir_all(&folder).expect("Temporary folder should be removable");
+    let names = samples
+        .iter()
+        .map(|sample| sample.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["a", "b"]);
+    assert!((samples[0].seconds - 0.5).abs() < 1e-9);
+    assert_eq!(samples[1].reference, "Second.");
+}
+
+#[test]
+fn test_report_as_markdown() {
+    let report = EvaluationReport {
+        variants: vec![VariantReport {
+            name: "default".into(),
+            options: AudioProcessingOptions::default(),
+            word_error_rate: 0.125,
+            character_error_rate: 0.05,
+            real_time_factor: 0.25,
+            samples: Vec::new(),
+        }],
+    };
+    assert!(report
+        .as_markdown()
+        .ends_with("| default | 12.5% | 5.0% | 0.25 |\n"));
+}
@@ -1,12 +1,21 @@
 //! Module holding all events for Super Mouse AI, as well as all associated functions that either react to or emit events.
 
-use log::{debug, trace};
+use log::{debug, error, trace};
 use serde::{Deserialize, Serialize};
 use specta::Type;
+use tauri::AppHandle;
 use tauri_specta::{collect_events, Event, Events};
 use whisper_rs::SegmentCallbackData;
 
-use crate::types::{ModKeyPayload, MouseButtonType};
+use crate::{
+    error::AppError,
+    hotkeys::HotkeyTrigger,
+    language::LanguageDetection,
+    levels::{InputLevel, MeterUpdate},
+    profiles::ActiveProfile,
+    settings::Settings,
+    types::{ModKeyPayload, MouseButtonType, OverflowPolicy, RecordingState},
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
 /// Tauri event representing mouse click globally
@@ -31,6 +40,21 @@ impl MouseClickEvent {
 /// [`ModKeyPayload`] : The modifer key that is pressed/released
 pub struct ModKeyEvent(ModKeyPayload);
 
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+/// Tauri event representing mouse button release globally
+///
+/// ### Payload
+///
+/// [`MouseButtonType`] : Which button was released
+pub struct MouseReleaseEvent(MouseButtonType);
+
+impl MouseReleaseEvent {
+    pub fn with_payload(payload: MouseButtonType) -> Self {
+        trace!("Mouse release EVENT with following payload: {payload:?}");
+        Self(payload)
+    }
+}
+
 impl ModKeyEvent {
     pub fn with_payload(payload: ModKeyPayload) -> Self {
         trace!("Modifier key EVENT with following payload: {payload:?}");
@@ -144,13 +168,247 @@ pub fn new_transcript_segment_event(segment: SegmentCallbackData) -> Transcripti
     )
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+/// Event representing the application profile chosen when a dictation starts
+///
+/// ### Payload
+///
+/// [`ActiveProfile`] : The focused window and its matching profile (if any)
+pub struct ActiveProfileChangedEvent(ActiveProfile);
+
+impl ActiveProfileChangedEvent {
+    pub fn with_payload(payload: ActiveProfile) -> Self {
+        trace!("Active profile changed EVENT with following payload: {payload:?}");
+        Self(payload)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+/// Event representing a hotkey binding that was activated
+///
+/// ### Payload
+///
+/// [`HotkeyTrigger`] : The bound action and whether the chord was pressed or released
+pub struct HotkeyTriggeredEvent(HotkeyTrigger);
+
+impl HotkeyTriggeredEvent {
+    pub fn with_payload(payload: HotkeyTrigger) -> Self {
+        trace!("Hotkey triggered EVENT with following payload: {payload:?}");
+        Self(payload)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+/// Event representing a dictation finished by a hotkey binding
+///
+/// ### Payload
+///
+/// - `text` [`String`] : The processed transcript
+/// - `processing_time` [f64] : Seconds spent transcribing and processing
+/// - `archive_path` [`Option<String>`] : File the recording was archived to, if archiving
+/// - `confidence` [`Option<f32>`] : Confidence of the transcript between 0 and 1, if known
+pub struct DictationCompletedEvent {
+    pub text: String,
+    pub processing_time: f64,
+    pub archive_path: Option<String>,
+    pub confidence: Option<f32>,
+}
+
+impl DictationCompletedEvent {
+    pub fn with_payload(
+        text: String,
+        processing_time: f64,
+        archive_path: Option<String>,
+        confidence: Option<f32>,
+    ) -> Self {
+        debug!(
+            "Dictation completed EVENT (text.len={}, time={processing_time}, archive={archive_path:?}, confidence={confidence:?})",
+            text.len()
+        );
+        Self {
+            text,
+            processing_time,
+            archive_path,
+            confidence,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+/// Event representing a change of the recording state
+///
+/// ### Payload
+///
+/// [`RecordingState`] : The new state
+pub struct RecordingStateChangedEvent(RecordingState);
+
+impl RecordingStateChangedEvent {
+    pub fn with_payload(payload: RecordingState) -> Self {
+        debug!("Recording state changed EVENT with following payload: {payload:?}");
+        Self(payload)
+    }
+}
+
+/// Emit a [`RecordingStateChangedEvent`], logging any failure
+pub fn emit_recording_state(app_handle: &AppHandle, state: RecordingState) {
+    let _ = RecordingStateChangedEvent::with_payload(state)
+        .emit(app_handle)
+        .map_err(|err| error!("Error for recording state event: {err}"));
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+/// Event representing a change of the available or selected input devices
+///
+/// ### Payload
+///
+/// - `devices` [`Vec<String>`] : Names of all available input devices
+/// - `current` [`Option<String>`] : Name of the selected input device
+pub struct InputDevicesChangedEvent {
+    pub devices: Vec<String>,
+    pub current: Option<String>,
+}
+
+impl InputDevicesChangedEvent {
+    pub fn with_payload(devices: Vec<String>, current: Option<String>) -> Self {
+        debug!("Input devices changed EVENT: {devices:?} (current={current:?})");
+        Self { devices, current }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+/// Event representing an error of the input stream, e.g. an unsupported configuration
+///
+/// ### Payload
+///
+/// [`AppError`] : Code, description and hint of the error
+pub struct InputStreamErrorEvent(AppError);
+
+impl InputStreamErrorEvent {
+    pub fn with_payload(payload: AppError) -> Self {
+        debug!("Input stream error EVENT with following payload: {payload}");
+        Self(payload)
+    }
+}
+
+/// Emit an [`InputStreamErrorEvent`], logging any failure
+pub fn emit_input_stream_error(app_handle: &AppHandle, error: AppError) {
+    let _ = InputStreamErrorEvent::with_payload(error)
+        .emit(app_handle)
+        .map_err(|err| error!("Error for input stream error event: {err}"));
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+/// Event representing the input level while recording, sent at most every 100 ms
+///
+/// ### Payload
+///
+/// [`InputLevel`] : RMS and peak level, and whether the input clipped
+pub struct InputLevelEvent(InputLevel);
+
+impl InputLevelEvent {
+    pub fn with_payload(payload: InputLevel) -> Self {
+        trace!("Input level EVENT with following payload: {payload:?}");
+        Self(payload)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+/// Event warning that the input has been silent while recording, e.g. because the wrong mic is selected
+///
+/// ### Payload
+///
+/// [f64] : Number of seconds the input has been silent
+pub struct SilenceWarningEvent(f64);
+
+impl SilenceWarningEvent {
+    pub fn with_payload(seconds: f64) -> Self {
+        debug!("Silence warning EVENT after {seconds} seconds");
+        Self(seconds)
+    }
+}
+
+/// Emit the events for a [`MeterUpdate`], logging any failure
+pub fn emit_meter_update(app_handle: &AppHandle, update: MeterUpdate) {
+    let result = match update {
+        MeterUpdate::Level(level) => InputLevelEvent::with_payload(level).emit(app_handle),
+        MeterUpdate::Silence(duration) => {
+            SilenceWarningEvent::with_payload(duration.as_secs_f64()).emit(app_handle)
+        }
+    };
+    let _ = result.map_err(|err| error!("Error for input level event: {err}"));
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+/// Event representing a recording that reached its maximum duration
+///
+/// ### Payload
+///
+/// - `max_duration_seconds` [u32] : The configured limit
+/// - `policy` [`OverflowPolicy`] : Whether recording stopped or keeps dropping the oldest audio
+pub struct RecordingLimitReachedEvent {
+    pub max_duration_seconds: u32,
+    pub policy: OverflowPolicy,
+}
+
+impl RecordingLimitReachedEvent {
+    pub fn with_payload(max_duration_seconds: u32, policy: OverflowPolicy) -> Self {
+        debug!("Recording limit reached EVENT ({max_duration_seconds}s, {policy:?})");
+        Self {
+            max_duration_seconds,
+            policy,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+/// Event representing the detected spoken language of a transcription
+///
+/// ### Payload
+///
+/// [`LanguageDetection`] : Language used, and the most likely languages with probabilities
+pub struct LanguageDetectedEvent(LanguageDetection);
+
+impl LanguageDetectedEvent {
+    pub fn with_payload(detection: LanguageDetection) -> Self {
+        debug!("Language detected EVENT ({})", detection.language);
+        Self(detection)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Event)]
+/// Event representing changed settings, e.g. by the frontend or by applying a benchmark
+///
+/// ### Payload
+///
+/// [`Settings`] : All settings after the change
+pub struct SettingsChangedEvent(Settings);
+
+impl SettingsChangedEvent {
+    pub fn with_payload(settings: Settings) -> Self {
+        debug!("Settings changed EVENT (version {})", settings.version);
+        Self(settings)
+    }
+}
+
 #[must_use]
 pub fn get_collected_events() -> Events {
     collect_events![
         MouseClickEvent,
+        MouseReleaseEvent,
         ModKeyEvent,
         // MouseMoveEvent,
         TranscriptionProgressEvent,
         TranscriptionSegmentEvent,
+        ActiveProfileChangedEvent,
+        HotkeyTriggeredEvent,
+        DictationCompletedEvent,
+        RecordingStateChangedEvent,
+        InputDevicesChangedEvent,
+        InputStreamErrorEvent,
+        InputLevelEvent,
+        SilenceWarningEvent,
+        RecordingLimitReachedEvent,
+        LanguageDetectedEvent,
+        SettingsChangedEvent,
     ]
 }
@@ -0,0 +1,271 @@
+//! Detection of segments whisper made up, e.g. on silence or when looping the same phrase.
+//!
+//! Follows the heuristics of the original whisper: a segment is silence if the no-speech
+//! probability is high while the decoder was unsure about the text, and a loop if its text
+//! compresses too well. On top of that, phrases whisper is known to invent from the subtitles
+//! it was trained on are rejected.
+
+use crate::types::HallucinationFilterOptions;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+const DEFAULT_NO_SPEECH_THRESHOLD: f32 = 0.6;
+const DEFAULT_LOGPROB_THRESHOLD: f32 = -1.0;
+const DEFAULT_COMPRESSION_RATIO_THRESHOLD: f32 = 2.4;
+
+/// Temperatures decoded with one after the other while segments look hallucinated.
+///
+/// Whisper tries six, but every try decodes the whole audio again, so only two re-decodes are
+/// spent on a dictation.
+const FALLBACK_TEMPERATURES: [f32; 3] = [0.0, 0.4, 0.8];
+
+/// Shortest repeat counted by [`compression_ratio`], in bytes
+const MIN_REPEAT: usize = 4;
+
+/// Bytes an LZ77 back reference takes
+const REPEAT_COST: usize = 3;
+
+/// Phrases from subtitled videos that whisper invents on silence or noise, normalized
+const KNOWN_HALLUCINATIONS: &[&str] = &[
+    "thank you for watching",
+    "thanks for watching",
+    "please subscribe",
+    "like and subscribe",
+    "subscribe to my channel",
+    "see you in the next video",
+    "subtitles by the amara org community",
+    "untertitel im auftrag des zdf",
+    "untertitel der amara org community",
+    "sous titrage st 501",
+    "ondertitels ingediend door de amara org gemeenschap",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// Why a segment looks made up by whisper
+pub enum HallucinationReason {
+    /// Whisper heard no speech and was unsure about the text
+    NoSpeech,
+    /// The text is mostly phrases whisper is known to invent
+    KnownPhrase,
+    /// The text loops the same words
+    Repetition,
+    /// Whisper was unsure about the text, which is kept even when dropping bad segments
+    LowConfidence,
+}
+
+#[derive(Clone, Copy, Debug)]
+/// Decoder statistics of a segment
+pub struct SegmentStatistics {
+    /// Probability of the segment having no speech
+    pub no_speech_probability: f32,
+    /// Average log probability of the text tokens
+    pub average_logprob: f32,
+}
+
+/// Hallucination filter with the defaults of [`HallucinationFilterOptions`] applied
+pub struct HallucinationFilter {
+    no_speech_threshold: f32,
+    logprob_threshold: f32,
+    compression_ratio_threshold: f32,
+    phrases: Vec<String>,
+    temperature_fallback: bool,
+    flag_only: bool,
+}
+
+impl HallucinationFilter {
+    pub fn new(options: &HallucinationFilterOptions) -> Self {
+        let blacklist = options
+            .blacklist
+            .iter()
+            .flatten()
+            .map(|phrase| normalize(phrase));
+        Self {
+            no_speech_threshold: options
+                .no_speech_threshold
+                .unwrap_or(DEFAULT_NO_SPEECH_THRESHOLD),
+            logprob_threshold: options
+                .logprob_threshold
+                .unwrap_or(DEFAULT_LOGPROB_THRESHOLD),
+            compression_ratio_threshold: options
+                .compression_ratio_threshold
+                .unwrap_or(DEFAULT_COMPRESSION_RATIO_THRESHOLD),
+            phrases: KNOWN_HALLUCINATIONS
+                .iter()
+                .map(|phrase| (*phrase).to_string())
+                .chain(blacklist)
+                .filter(|phrase| !phrase.is_empty())
+                .collect(),
+            temperature_fallback: options.temperature_fallback.unwrap_or(true),
+            flag_only: options.flag_only.unwrap_or(false),
+        }
+    }
+
+    /// Temperatures to decode with, the next one only used if the previous result looks bad
+    pub fn temperatures(&self) -> &'static [f32] {
+        if self.temperature_fallback {
+            &FALLBACK_TEMPERATURES
+        } else {
+            &FALLBACK_TEMPERATURES[..1]
+        }
+    }
+
+    /// Check a segment, returning why it looks made up or `None` if it looks fine
+    pub fn judge(&self, text: &str, statistics: SegmentStatistics) -> Option<HallucinationReason> {
+        let unsure = statistics.average_logprob < self.logprob_threshold;
+        if unsure && statistics.no_speech_probability > self.no_speech_threshold {
+            return Some(HallucinationReason::NoSpeech);
+        }
+        if self.is_mostly_known_phrases(text) {
+            return Some(HallucinationReason::KnownPhrase);
+        }
+        if compression_ratio(text.trim()) > self.compression_ratio_threshold {
+            return Some(HallucinationReason::Repetition);
+        }
+        unsure.then_some(HallucinationReason::LowConfidence)
+    }
+
+    /// Whether known phrases make up most of the text.
+    ///
+    /// A single segment may hold a whole dictation, which is kept when it merely mentions one.
+    fn is_mostly_known_phrases(&self, text: &str) -> bool {
+        let normalized = normalize(text);
+        let padded = format!(" {normalized} ");
+        let covered = self
+            .phrases
+            .iter()
+            .map(|phrase| padded.matches(&format!(" {phrase} ")).count() * phrase.len())
+            .sum::<usize>();
+        covered > 0 && covered * 2 >= normalized.len()
+    }
+
+    /// Whether decoding again with a higher temperature may give better segments.
+    ///
+    /// Silence and known phrases are not retried, a different temperature rarely changes them.
+    pub fn needs_fallback(verdicts: &[Option<HallucinationReason>]) -> bool {
+        verdicts.iter().any(|verdict| {
+            matches!(
+                verdict,
+                Some(HallucinationReason::Repetition | HallucinationReason::LowConfidence)
+            )
+        })
+    }
+
+    /// Whether a segment with this verdict is left out of the transcript
+    pub fn drops(&self, verdict: Option<HallucinationReason>) -> bool {
+        let dropped = !self.flag_only
+            && verdict.is_some_and(|reason| reason != HallucinationReason::LowConfidence);
+        if dropped {
+            debug!("Dropping hallucinated segment ({verdict:?})");
+        }
+        dropped
+    }
+}
+
+/// Lowercase words of the text without punctuation, separated by single spaces
+fn normalize(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Estimate how well the text compresses, like the gzip ratio used by whisper.
+///
+/// Counts the output of a plain LZ77 coder: every byte not within a repeat of at least
+/// [`MIN_REPEAT`] earlier bytes is a literal, every repeat costs [`REPEAT_COST`] bytes.
+#[allow(clippy::cast_precision_loss, reason = "Segments are short")]
+fn compression_ratio(text: &str) -> f32 {
+    let bytes = text.as_bytes();
+    let mut compressed = 0;
+    let mut position = 0;
+    while position < bytes.len() {
+        let longest = (0..position)
+            .map(|start| {
+                bytes[start..]
+                    .iter()
+                    .zip(&bytes[position..])
+                    .take_while(|(earlier, current)| earlier == current)
+                    .count()
+            })
+            .max()
+            .unwrap_or(0);
+        if longest >= MIN_REPEAT {
+            compressed += REPEAT_COST;
+            position += longest;
+        } else {
+            compressed += 1;
+            position += 1;
+        }
+    }
+    bytes.len() as f32 / compressed.max(1) as f32
+}
+
+#[cfg(test)]
+const CONFIDENT: SegmentStatistics = SegmentStatistics {
+    no_speech_probability: 0.1,
+    average_logprob: -0.3,
+};
+
+#[test]
+fn test_compression_ratio() {
+    assert!(compression_ratio("The quick brown fox jumps over the lazy dog.") < 1.2);
+    assert!(compression_ratio(&"I'm going to go. ".repeat(10)) > 5.0);
+    assert!(compression_ratio("").abs() < f32::EPSILON);
+}
+
+#[test]
+fn test_judge_segments() {
+    let filter = HallucinationFilter::new(&HallucinationFilterOptions {
+        blacklist: Some(vec!["Beep, beep!".into()]),
+        ..Default::default()
+    });
+    let silent = SegmentStatistics {
+        no_speech_probability: 0.9,
+        average_logprob: -1.5,
+    };
+    assert_eq!(filter.judge(" Hello.", CONFIDENT), None);
+    assert_eq!(
+        filter.judge(" Hello.", silent),
+        Some(HallucinationReason::NoSpeech)
+    );
+    assert_eq!(
+        filter.judge(" Thanks for watching!", CONFIDENT),
+        Some(HallucinationReason::KnownPhrase)
+    );
+    assert_eq!(
+        filter.judge(" And then: BEEP beep.", CONFIDENT),
+        Some(HallucinationReason::KnownPhrase)
+    );
+    // A dictation mentioning a phrase is kept
+    assert_eq!(
+        filter.judge(
+            " At the end of the video I say thanks for watching and wave.",
+            CONFIDENT
+        ),
+        None
+    );
+    // Only whole words match
+    assert_eq!(
+        filter.judge(" Please subscribers, read on.", CONFIDENT),
+        None
+    );
+    assert_eq!(
+        filter.judge(&" so so so so".repeat(20), CONFIDENT),
+        Some(HallucinationReason::Repetition)
+    );
+    let unsure = SegmentStatistics {
+        no_speech_probability: 0.1,
+        average_logprob: -1.5,
+    };
+    let verdict = filter.judge(" Mumble.", unsure);
+    assert_eq!(verdict, Some(HallucinationReason::LowConfidence));
+    assert!(!filter.drops(verdict));
+    assert!(filter.drops(Some(HallucinationReason::Repetition)));
+    assert!(HallucinationFilter::needs_fallback(&[None, verdict]));
+    assert!(!HallucinationFilter::needs_fallback(&[
+        None,
+        Some(HallucinationReason::NoSpeech)
+    ]));
+}
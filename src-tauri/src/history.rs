@@ -0,0 +1,390 @@
+//! Recent transcripts kept on the Rust side, used to carry context from one dictation to the next.
+
+use crate::types::PromptContextOptions;
+use log::{debug, trace};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Maximum number of transcripts remembered by the backend
+const MAX_HISTORY_ENTRIES: usize = 64;
+
+/// Default number of previous transcripts used for the prompt
+const DEFAULT_PREVIOUS_TRANSCRIPTS: u8 = 3;
+
+/// Default maximum number of characters carried from previous transcripts.
+///
+/// Whisper only looks at the last ~224 tokens of a prompt, so anything longer is wasted.
+const DEFAULT_MAX_CONTEXT_CHARACTERS: u32 = 500;
+
+#[derive(Debug, Clone)]
+/// A single transcript remembered by the backend
+pub struct HistoryEntry {
+    /// Unique (per app run) identifier of the entry
+    pub id: u32,
+    /// Final text of the transcript
+    pub text: String,
+    /// Application the text was dictated into, if known
+    pub application: Option<String>,
+    /// Spoken language, detected or given, if known
+    pub language: Option<String>,
+    /// When the transcript was created
+    pub created_at: Instant,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// How often a misrecognized text was corrected to another
+pub struct CorrectionCount {
+    /// Words as recognized
+    pub original: String,
+    /// Words they were corrected to
+    pub replacement: String,
+    pub count: u32,
+}
+
+#[derive(Debug, Default)]
+/// History of the most recent transcripts, newest at the back.
+pub struct InnerTranscriptHistory {
+    entries: VecDeque<HistoryEntry>,
+    next_id: u32,
+    corrections: Vec<CorrectionCount>,
+}
+
+impl InnerTranscriptHistory {
+    pub const fn new() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            next_id: 0,
+            corrections: Vec::new(),
+        }
+    }
+
+    /// Add a new transcript to the history, returning its id.
+    ///
+    /// Empty transcripts are not stored, in which case `None` is returned.
+    pub fn push(
+        &mut self,
+        text: &str,
+        application: Option<String>,
+        language: Option<String>,
+    ) -> Option<u32> {
+        self.push_at(text, application, language, Instant::now())
+    }
+
+    fn push_at(
+        &mut self,
+        text: &str,
+        application: Option<String>,
+        language: Option<String>,
+        created_at: Instant,
+    ) -> Option<u32> {
+        let text = text.trim();
+        if text.is_empty() {
+            trace!("Skip adding empty transcript to history");
+            return None;
+        }
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        if self.entries.len() >= MAX_HISTORY_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(HistoryEntry {
+            id,
+            text: text.to_string(),
+            application,
+            language,
+            created_at,
+        });
+        debug!("Added transcript #{id} to history");
+        Some(id)
+    }
+
+    /// Get the most recent transcript
+    pub fn last(&self) -> Option<&HistoryEntry> {
+        self.entries.back()
+    }
+
+    /// Replace `original` with `replacement` in a transcript, the last one if `id` is `None`.
+    ///
+    /// The differing words, if any, are counted in the correction statistics. Returns the
+    /// corrected text, or `None` if there is no such transcript or it does not contain `original`.
+    pub fn correct(
+        &mut self,
+        id: Option<u32>,
+        original: &str,
+        replacement: &str,
+    ) -> Option<String> {
+        let (original, replacement) = (original.trim(), replacement.trim());
+        if original.is_empty() {
+            return None;
+        }
+        let entry = match id {
+            Some(id) => self.entries.iter_mut().find(|entry| entry.id == id),
+            None => self.entries.back_mut(),
+        }?;
+        let start = entry.text.find(original)?;
+        entry
+            .text
+            .replace_range(start..start + original.len(), replacement);
+        debug!("Corrected transcript #{}", entry.id);
+        let (original, replacement) = differing_words(original, replacement);
+        if original.is_empty() && replacement.is_empty() {
+            // Only whitespace changed, so nothing was misrecognized
+            return Some(entry.text.clone());
+        }
+        match self.corrections.iter_mut().find(|correction| {
+            correction.original == original && correction.replacement == replacement
+        }) {
+            Some(correction) => correction.count += 1,
+            None => self.corrections.push(CorrectionCount {
+                original,
+                replacement,
+                count: 1,
+            }),
+        }
+        Some(entry.text.clone())
+    }
+
+    /// Get the corrections made so far, most frequent first
+    pub fn corrections(&self) -> Vec<CorrectionCount> {
+        let mut corrections = self.corrections.clone();
+        corrections.sort_by(|a, b| b.count.cmp(&a.count));
+        corrections
+    }
+
+    /// Remove all remembered transcripts
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Build the context prompt from previous transcripts that match the given options.
+    ///
+    /// Transcripts known to be in another language than `language` are skipped, if given.
+    /// Returns `None` when no previous transcript qualifies or no characters are allowed.
+    pub fn context_prompt(
+        &self,
+        options: &PromptContextOptions,
+        application: Option<&str>,
+        language: Option<&str>,
+    ) -> Option<String> {
+        self.context_prompt_at(options, application, language, Instant::now())
+    }
+
+    fn context_prompt_at(
+        &self,
+        options: &PromptContextOptions,
+        application: Option<&str>,
+        language: Option<&str>,
+        now: Instant,
+    ) -> Option<String> {
+        let count = usize::from(
+            options
+                .previous_transcripts
+                .unwrap_or(DEFAULT_PREVIOUS_TRANSCRIPTS),
+        );
+        let max_age = options
+            .max_age_seconds
+            .map(|secs| Duration::from_secs(u64::from(secs)));
+        let same_application = options.same_application_only.unwrap_or(true);
+        let mut selected = self
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| max_age.is_none_or(|age| now.duration_since(entry.created_at) <= age))
+            .filter(|entry| !same_application || entry.application.as_deref() == application)
+            .filter(|entry| {
+                language
+                    .zip(entry.language.as_deref())
+                    .is_none_or(|(language, entry)| language.eq_ignore_ascii_case(entry))
+            })
+            .take(count)
+            .map(|entry| entry.text.as_str())
+            .collect::<Vec<_>>();
+        if selected.is_empty() {
+            return None;
+        }
+        // Collected newest first, but the prompt must read in order
+        selected.reverse();
+        let context = selected.join(" ");
+        let max_characters = options
+            .max_characters
+            .unwrap_or(DEFAULT_MAX_CONTEXT_CHARACTERS) as usize;
+        trace!(
+            "Context from {} previous transcripts ({} chars)",
+            selected.len(),
+            context.len()
+        );
+        Some(tail_of(&context, max_characters))
+            .filter(|tail| !tail.is_empty())
+            .map(ToString::to_string)
+    }
+}
+
+pub type TranscriptHistoryState = Mutex<InnerTranscriptHistory>;
+
+/// Get the last `max_characters` characters of a text, starting on a word boundary when possible.
+pub fn tail_of(text: &str, max_characters: usize) -> &str {
+    if max_characters == 0 {
+        return "";
+    }
+    let char_count = text.chars().count();
+    if char_count <= max_characters {
+        return text;
+    }
+    let start = text
+        .char_indices()
+        .nth(char_count - max_characters)
+        .map_or(0, |(i, _)| i);
+    let tail = &text[start..];
+    if text[..start].ends_with(char::is_whitespace) {
+        // The cut is already on the start of a word
+        return tail;
+    }
+    // Avoid starting in the middle of a word if there is a later word to start from
+    tail.split_once(char::is_whitespace)
+        .map_or(tail, |(_, rest)| rest.trim_start())
+}
+
+/// Strip the words both texts start and end with, leaving what was actually corrected
+fn differing_words(original: &str, replacement: &str) -> (String, String) {
+    let original = original.split_whitespace().collect::<Vec<_>>();
+    let replacement = replacement.split_whitespace().collect::<Vec<_>>();
+    let prefix = original
+        .iter()
+        .zip(&replacement)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = original[prefix..]
+        .iter()
+        .rev()
+        .zip(replacement[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    (
+        original[prefix..original.len() - suffix].join(" "),
+        replacement[prefix..replacement.len() - suffix].join(" "),
+    )
+}
+
+/// Combine the static initial prompt with the context from previous transcripts.
+///
+/// The context is placed last, since Whisper weighs the end of the prompt the most.
+pub fn combine_prompt(initial_prompt: Option<&str>, context: Option<&str>) -> Option<String> {
+    match (initial_prompt.map(str::trim), context) {
+        (Some(prompt), Some(context)) if !prompt.is_empty() => Some(format!("{prompt} {context}")),
+        (_, Some(context)) => Some(context.to_string()),
+        (Some(prompt), None) => Some(prompt.to_string()),
+        (None, None) => None,
+    }
+}
+
+#[test]
+fn test_context_prompt_takes_last_transcripts_in_order() {
+    let mut history = InnerTranscriptHistory::new();
+    let now = Instant::now();
+    history.push_at("First.", None, None, now);
+    history.push_at("Second.", None, None, now);
+    history.push_at("Third.", None, None, now);
+    let options = PromptContextOptions {
+        previous_transcripts: Some(2),
+        ..Default::default()
+    };
+    let prompt = history.context_prompt_at(&options, None, None, now);
+    assert_eq!(prompt.as_deref(), Some("Second. Third."));
+}
+
+#[test]
+fn test_context_prompt_filters_application_and_age() {
+    let mut history = InnerTranscriptHistory::new();
+    let start = Instant::now();
+    history.push_at("Old editor text.", Some("code".into()), None, start);
+    history.push_at(
+        "Chat text.",
+        Some("chat".into()),
+        Some("en".into()),
+        start + Duration::from_secs(100),
+    );
+    history.push_at(
+        "New editor text.",
+        Some("code".into()),
+        None,
+        start + Duration::from_secs(110),
+    );
+    let options = PromptContextOptions {
+        max_age_seconds: Some(30),
+        ..Default::default()
+    };
+    let now = start + Duration::from_secs(120);
+    let prompt = history.context_prompt_at(&options, Some("code"), None, now);
+    assert_eq!(prompt.as_deref(), Some("New editor text."));
+    assert_eq!(
+        history.context_prompt_at(&options, Some("mail"), None, now),
+        None
+    );
+}
+
+#[test]
+fn test_context_prompt_filters_language() {
+    let mut history = InnerTranscriptHistory::new();
+    let now = Instant::now();
+    history.push_at("Hallo zusammen.", None, Some("de".into()), now);
+    history.push_at("Hello everyone.", None, Some("en".into()), now);
+    history.push_at("Unknown.", None, None, now);
+    let options = PromptContextOptions::default();
+    let prompt = history.context_prompt_at(&options, None, Some("de"), now);
+    assert_eq!(prompt.as_deref(), Some("Hallo zusammen. Unknown."));
+    let prompt = history.context_prompt_at(&options, None, None, now);
+    assert_eq!(
+        prompt.as_deref(),
+        Some("Hallo zusammen. Hello everyone. Unknown.")
+    );
+}
+
+#[test]
+fn test_correct_counts_differing_words() {
+    let mut history = InnerTranscriptHistory::new();
+    let first = history.push("I scream for ice cream. Yes.", None, None);
+    history.push("I scream.", None, None);
+    let corrected = history.correct(
+        first,
+        " I scream for ice cream.",
+        "Ice cream for ice cream.",
+    );
+    assert_eq!(corrected.as_deref(), Some("Ice cream for ice cream. Yes."));
+    history.correct(None, "I scream", "Ice cream");
+    assert_eq!(history.correct(None, "Missing", "Found"), None);
+    // Unchanged words are not counted as a correction
+    assert_eq!(
+        history.correct(None, "Ice cream", " Ice cream ").as_deref(),
+        Some("Ice cream.")
+    );
+    assert_eq!(
+        history.corrections(),
+        [CorrectionCount {
+            original: "I scream".into(),
+            replacement: "Ice cream".into(),
+            count: 2,
+        }]
+    );
+    assert_eq!(
+        history.last().map(|entry| entry.text.as_str()),
+        Some("Ice cream.")
+    );
+}
+
+#[test]
+fn test_tail_of_starts_on_word_boundary() {
+    assert_eq!(tail_of("hello wonderful world", 8), "world");
+    assert_eq!(tail_of("short", 10), "short");
+    assert_eq!(tail_of("hello wonderful world", 0), "");
+}
+
+#[test]
+fn test_tail_of_keeps_word_cut_on_its_start() {
+    assert_eq!(tail_of("hello wonderful world", 15), "wonderful world");
+    assert_eq!(tail_of("hello wonderful world", 16), "wonderful world");
+}
@@ -0,0 +1,455 @@
+//! Global hotkey and mouse-button binding engine.
+//!
+//! Raw input events are matched against the configured [`HotkeyBinding`]s, producing
+//! [`HotkeyTrigger`]s that are executed in Rust, so bindings keep working while the webview is hidden.
+
+use log::{debug, trace};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::{
+    collections::HashSet,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Default time, in milliseconds, in which repeated presses of a chord are ignored
+const DEFAULT_DEBOUNCE_MILLIS: u32 = 50;
+
+/// Default time, in milliseconds, in which a second press counts as a double-click
+const DEFAULT_DOUBLE_CLICK_MILLIS: u32 = 400;
+
+/// Default time, in milliseconds, a hold binding must be held to count as intentional
+const DEFAULT_MIN_HOLD_MILLIS: u32 = 250;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+/// Modifier keys, left and right variants are treated the same
+pub enum Modifier {
+    Control,
+    Shift,
+    Alt,
+    Meta,
+}
+
+impl Modifier {
+    /// Get the modifier for a key name (as given by `device_query`), `None` if not a modifier
+    pub fn from_key_name(key: &str) -> Option<Self> {
+        match key {
+            "LControl" | "RControl" | "Control" => Some(Self::Control),
+            "LShift" | "RShift" | "Shift" => Some(Self::Shift),
+            "LAlt" | "RAlt" | "Alt" | "LOption" | "ROption" | "Option" => Some(Self::Alt),
+            "LMeta" | "RMeta" | "Meta" | "Command" => Some(Self::Meta),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+/// The key or button that activates a chord
+pub enum Trigger {
+    /// Mouse button by its platform number: 1-3 are the main buttons,
+    /// side buttons are usually 4/5 (Windows, macOS) or 8/9 (Linux)
+    MouseButton(u8),
+    /// Non-modifier key by its `device_query` name, e.g. `F9` or `Space`
+    Key(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// A trigger together with the exact set of modifiers that must be held
+pub struct Chord {
+    pub modifiers: Vec<Modifier>,
+    pub trigger: Trigger,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[non_exhaustive]
+/// How pressing a chord activates its action
+pub enum BindingMode {
+    /// Activate once on each press
+    #[default]
+    Toggle,
+    /// Activate on press and again on release
+    Hold,
+    /// Activate on the second press within the double-click time
+    DoubleClick,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[non_exhaustive]
+/// Action done when a binding is activated
+pub enum BindingAction {
+    /// Start recording if stopped, otherwise stop and transcribe
+    ToggleRecording,
+    StartRecording,
+    /// Stop recording, then transcribe and output the text
+    StopRecording,
+    /// Record while held (use with [`BindingMode::Hold`])
+    PushToTalk,
+    /// Stop recording and throw away the audio
+    Cancel,
+    /// Output the last transcript again
+    RePaste,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// A chord bound to an action
+pub struct HotkeyBinding {
+    pub chord: Chord,
+    pub mode: BindingMode,
+    pub action: BindingAction,
+    /// Ignore presses within this many milliseconds of the previous one, defaults to `50`
+    pub debounce_ms: Option<u32>,
+    /// Maximum milliseconds between presses of a double-click, defaults to `400`
+    pub double_click_ms: Option<u32>,
+    /// Minimum milliseconds a [`BindingMode::Hold`] chord must be held, shorter holds are
+    /// reported as [`TriggerPhase::Cancelled`], defaults to `250`
+    pub min_hold_ms: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// Whether the chord was pressed or released
+pub enum TriggerPhase {
+    Pressed,
+    Released,
+    /// Released before the minimum hold time, e.g. an accidental click
+    Cancelled,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// A binding's action that should be executed
+pub struct HotkeyTrigger {
+    pub action: BindingAction,
+    pub phase: TriggerPhase,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Raw input given to the engine
+pub enum InputEvent {
+    KeyDown(String),
+    KeyUp(String),
+    MouseDown(u8),
+    MouseUp(u8),
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+/// Per-binding state used for debouncing, double-clicks and holds
+struct BindingState {
+    last_press: Option<Instant>,
+    /// When the currently held chord was pressed
+    held_since: Option<Instant>,
+}
+
+#[derive(Debug, Default)]
+/// Matches input events against bindings
+pub struct HotkeyEngine {
+    bindings: Vec<(HotkeyBinding, BindingState)>,
+    modifiers: HashSet<Modifier>,
+}
+
+impl HotkeyEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace all bindings, resetting their state
+    pub fn set_bindings(&mut self, bindings: Vec<HotkeyBinding>) {
+        debug!("Replacing {} hotkey bindings", bindings.len());
+        self.bindings = bindings
+            .into_iter()
+            .map(|binding| (binding, BindingState::default()))
+            .collect();
+    }
+
+    /// Get all bindings
+    pub fn bindings(&self) -> Vec<HotkeyBinding> {
+        self.bindings
+            .iter()
+            .map(|(binding, _)| binding.clone())
+            .collect()
+    }
+
+    /// Process an input event that happened at the given time, returning the triggered actions.
+    pub fn handle(&mut self, event: &InputEvent, now: Instant) -> Vec<HotkeyTrigger> {
+        let (trigger, is_down) = match event {
+            InputEvent::KeyDown(key) | InputEvent::KeyUp(key) => {
+                let is_down = matches!(event, InputEvent::KeyDown(_));
+                if let Some(modifier) = Modifier::from_key_name(key) {
+                    if is_down {
+                        self.modifiers.insert(modifier);
+                        return Vec::new();
+                    }
+                    self.modifiers.remove(&modifier);
+                    // Releasing a required modifier ends a hold
+                    return self.release_holds(now, |chord| chord.modifiers.contains(&modifier));
+                }
+                (Trigger::Key(key.clone()), is_down)
+            }
+            InputEvent::MouseDown(button) => (Trigger::MouseButton(*button), true),
+            InputEvent::MouseUp(button) => (Trigger::MouseButton(*button), false),
+        };
+        if is_down {
+            self.press(&trigger, now)
+        } else {
+            self.release_holds(now, |chord| chord.trigger == trigger)
+        }
+    }
+
+    fn press(&mut self, trigger: &Trigger, now: Instant) -> Vec<HotkeyTrigger> {
+        let modifiers = &self.modifiers;
+        let mut triggers = Vec::new();
+        for (binding, state) in &mut self.bindings {
+            let chord = &binding.chord;
+            let exact_modifiers = chord.modifiers.len() == modifiers.len()
+                && chord
+                    .modifiers
+                    .iter()
+                    .all(|modifier| modifiers.contains(modifier));
+            if chord.trigger != *trigger || !exact_modifiers {
+                continue;
+            }
+            let since_last = state.last_press.map(|last| now.duration_since(last));
+            let debounce = millis(binding.debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MILLIS));
+            if since_last.is_some_and(|elapsed| elapsed < debounce) {
+                trace!("Debounced press for {:?}", binding.action);
+                continue;
+            }
+            let phase = match binding.mode {
+                BindingMode::Toggle => Some(TriggerPhase::Pressed),
+                BindingMode::Hold if state.held_since.is_none() => {
+                    state.held_since = Some(now);
+                    Some(TriggerPhase::Pressed)
+                }
+                BindingMode::Hold => None,
+                BindingMode::DoubleClick => {
+                    let window = millis(
+                        binding
+                            .double_click_ms
+                            .unwrap_or(DEFAULT_DOUBLE_CLICK_MILLIS),
+                    );
+                    if since_last.is_some_and(|elapsed| elapsed <= window) {
+                        // Forget the press, so a third press starts a new double-click
+                        state.last_press = None;
+                        triggers.push(HotkeyTrigger {
+                            action: binding.action,
+                            phase: TriggerPhase::Pressed,
+                        });
+                        continue;
+                    }
+                    None
+                }
+            };
+            state.last_press = Some(now);
+            if let Some(phase) = phase {
+                triggers.push(HotkeyTrigger {
+                    action: binding.action,
+                    phase,
+                });
+            }
+        }
+        triggers
+    }
+
+    fn release_holds(
+        &mut self,
+        now: Instant,
+        is_released: impl Fn(&Chord) -> bool,
+    ) -> Vec<HotkeyTrigger> {
+        self.bindings
+            .iter_mut()
+            .filter(|(binding, _)| is_released(&binding.chord))
+            .filter_map(|(binding, state)| {
+                let held_since = state.held_since.take()?;
+                let min_hold = millis(binding.min_hold_ms.unwrap_or(DEFAULT_MIN_HOLD_MILLIS));
+                let phase = if now.duration_since(held_since) < min_hold {
+                    debug!("Hold of {:?} too short, cancelling", binding.action);
+                    TriggerPhase::Cancelled
+                } else {
+                    TriggerPhase::Released
+                };
+                Some(HotkeyTrigger {
+                    action: binding.action,
+                    phase,
+                })
+            })
+            .collect()
+    }
+}
+
+fn millis(ms: u32) -> Duration {
+    Duration::from_millis(u64::from(ms))
+}
+
+pub type HotkeyState = Mutex<HotkeyEngine>;
+
+#[cfg(test)]
+/// Binding of the chord toggling the recording, with default timings
+fn binding(modifiers: &[Modifier], trigger: Trigger, mode: BindingMode) -> HotkeyBinding {
+    HotkeyBinding {
+        chord: Chord {
+            modifiers: modifiers.to_vec(),
+            trigger,
+        },
+        mode,
+        action: BindingAction::ToggleRecording,
+        debounce_ms: None,
+        double_click_ms: None,
+        min_hold_ms: None,
+    }
+}
+
+#[test]
+fn test_toggle_requires_exact_modifiers() {
+    let mut engine = HotkeyEngine::new();
+    engine.set_bindings(vec![binding(
+        &[Modifier::Control],
+        Trigger::MouseButton(3),
+        BindingMode::Toggle,
+    )]);
+    let now = Instant::now();
+    assert!(engine.handle(&InputEvent::MouseDown(3), now).is_empty());
+    engine.handle(&InputEvent::KeyDown("LControl".into()), now);
+    engine.handle(&InputEvent::KeyDown("LShift".into()), now);
+    assert!(engine.handle(&InputEvent::MouseDown(3), now).is_empty());
+    engine.handle(&InputEvent::KeyUp("LShift".into()), now);
+    let triggers = engine.handle(&InputEvent::MouseDown(3), now + millis(100));
+    assert_eq!(triggers.len(), 1);
+    assert_eq!(triggers[0].phase, TriggerPhase::Pressed);
+}
+
+#[test]
+fn test_toggle_debounce() {
+    let mut engine = HotkeyEngine::new();
+    engine.set_bindings(vec![binding(
+        &[],
+        Trigger::MouseButton(4),
+        BindingMode::Toggle,
+    )]);
+    let now = Instant::now();
+    assert_eq!(engine.handle(&InputEvent::MouseDown(4), now).len(), 1);
+    assert!(engine
+        .handle(&InputEvent::MouseDown(4), now + millis(10))
+        .is_empty());
+    assert_eq!(
+        engine
+            .handle(&InputEvent::MouseDown(4), now + millis(200))
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn test_hold_releases_on_button_or_modifier() {
+    let mut engine = HotkeyEngine::new();
+    engine.set_bindings(vec![binding(
+        &[Modifier::Alt],
+        Trigger::MouseButton(2),
+        BindingMode::Hold,
+    )]);
+    let now = Instant::now();
+    engine.handle(&InputEvent::KeyDown("LAlt".into()), now);
+    assert_eq!(
+        engine.handle(&InputEvent::MouseDown(2), now)[0].phase,
+        TriggerPhase::Pressed
+    );
+    let released = engine.handle(&InputEvent::KeyUp("LAlt".into()), now + millis(500));
+    assert_eq!(released[0].phase, TriggerPhase::Released);
+    assert!(engine
+        .handle(&InputEvent::MouseUp(2), now + millis(600))
+        .is_empty());
+}
+
+#[test]
+fn test_double_click() {
+    let mut engine = HotkeyEngine::new();
+    engine.set_bindings(vec![binding(
+        &[],
+        Trigger::MouseButton(5),
+        BindingMode::DoubleClick,
+    )]);
+    let now = Instant::now();
+    assert!(engine.handle(&InputEvent::MouseDown(5), now).is_empty());
+    assert_eq!(
+        engine
+            .handle(&InputEvent::MouseDown(5), now + millis(200))
+            .len(),
+        1
+    );
+    assert!(engine
+        .handle(&InputEvent::MouseDown(5), now + millis(400))
+        .is_empty());
+    assert!(engine
+        .handle(&InputEvent::MouseDown(5), now + millis(1200))
+        .is_empty());
+}
+
+#[test]
+fn test_short_hold_is_cancelled() {
+    let mut engine = HotkeyEngine::new();
+    engine.set_bindings(vec![binding(
+        &[],
+        Trigger::MouseButton(8),
+        BindingMode::Hold,
+    )]);
+    let now = Instant::now();
+    engine.handle(&InputEvent::MouseDown(8), now);
+    let released = engine.handle(&InputEvent::MouseUp(8), now + millis(100));
+    assert_eq!(released[0].phase, TriggerPhase::Cancelled);
+    engine.handle(&InputEvent::MouseDown(8), now + millis(500));
+    let released = engine.handle(&InputEvent::MouseUp(8), now + millis(1000));
+    assert_eq!(released[0].phase, TriggerPhase::Released);
+}
+
+#[test]
+fn test_key_toggle_and_hold() {
+    let mut engine = HotkeyEngine::new();
+    engine.set_bindings(vec![
+        binding(&[], Trigger::Key("F9".into()), BindingMode::Toggle),
+        HotkeyBinding {
+            action: BindingAction::PushToTalk,
+            ..binding(
+                &[Modifier::Control],
+                Trigger::Key("Space".into()),
+                BindingMode::Hold,
+            )
+        },
+    ]);
+    let now = Instant::now();
+    let toggled = engine.handle(&InputEvent::KeyDown("F9".into()), now);
+    assert_eq!(
+        toggled,
+        [HotkeyTrigger {
+            action: BindingAction::ToggleRecording,
+            phase: TriggerPhase::Pressed,
+        }]
+    );
+    assert!(engine
+        .handle(&InputEvent::KeyUp("F9".into()), now + millis(100))
+        .is_empty());
+    assert!(engine
+        .handle(&InputEvent::KeyDown("Space".into()), now + millis(200))
+        .is_empty());
+
+    engine.handle(&InputEvent::KeyDown("LControl".into()), now + millis(300));
+    let pressed = engine.handle(&InputEvent::KeyDown("Space".into()), now + millis(400));
+    assert_eq!(
+        pressed,
+        [HotkeyTrigger {
+            action: BindingAction::PushToTalk,
+            phase: TriggerPhase::Pressed,
+        }]
+    );
+    // Key repeat while held does not press again
+    assert!(engine
+        .handle(&InputEvent::KeyDown("Space".into()), now + millis(500))
+        .is_empty());
+    let released = engine.handle(&InputEvent::KeyUp("Space".into()), now + millis(900));
+    assert_eq!(
+        released,
+        [HotkeyTrigger {
+            action: BindingAction::PushToTalk,
+            phase: TriggerPhase::Released,
+        }]
+    );
+    assert!(engine
+        .handle(&InputEvent::KeyUp("LControl".into()), now + millis(950))
+        .is_empty());
+}
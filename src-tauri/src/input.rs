@@ -0,0 +1,266 @@
+//! Global keyboard and mouse input listener.
+//!
+//! An [`InputSource`] feeds raw [`InputEvent`]s into a channel. A single listener thread blocks
+//! on that channel (so it is parked while idle), tracks the full [`KeyState`] and forwards every
+//! change to all subscribers.
+
+use crate::hotkeys::InputEvent;
+use log::{debug, error, trace, warn};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::{
+    any::Any,
+    collections::BTreeSet,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+/// Keeps an input source alive until dropped
+pub type SourceGuard = Box<dyn Any>;
+
+/// Provider of raw input events
+pub trait InputSource: Send {
+    /// Start sending events to the given sender.
+    ///
+    /// Called from the listener thread, which keeps the returned guard until shutdown.
+    fn start(self: Box<Self>, sender: Sender<InputMessage>) -> Result<SourceGuard, String>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Message handled by the listener thread
+pub enum InputMessage {
+    Event(InputEvent),
+    Shutdown,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// All keys and mouse buttons currently held down
+pub struct KeyState {
+    /// Key names as given by `device_query`, e.g. `LControl` or `F9`
+    pub keys: BTreeSet<String>,
+    /// Mouse buttons by platform number
+    pub mouse_buttons: BTreeSet<u8>,
+}
+
+impl KeyState {
+    /// Update with an event, returning `false` if nothing changed (e.g. key repeat)
+    pub fn apply(&mut self, event: &InputEvent) -> bool {
+        match event {
+            InputEvent::KeyDown(key) => self.keys.insert(key.clone()),
+            InputEvent::KeyUp(key) => self.keys.remove(key),
+            InputEvent::MouseDown(button) => self.mouse_buttons.insert(*button),
+            InputEvent::MouseUp(button) => self.mouse_buttons.remove(button),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A change of input sent to subscribers
+pub struct InputUpdate {
+    /// The event that caused the change
+    pub event: InputEvent,
+    /// Key state after the event
+    pub state: KeyState,
+}
+
+type Subscribers = Arc<Mutex<Vec<Sender<InputUpdate>>>>;
+
+/// Handle to the running listener thread, which stops when shut down or dropped
+pub struct InputListener {
+    sender: Sender<InputMessage>,
+    subscribers: Subscribers,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl InputListener {
+    /// Spawn the listener thread for the given source
+    pub fn start(source: Box<dyn InputSource>) -> Self {
+        let (sender, receiver) = channel();
+        let subscribers = Subscribers::default();
+        let thread_sender = sender.clone();
+        let thread_subscribers = Arc::clone(&subscribers);
+        let thread = std::thread::Builder::new()
+            .name("input-listener".into())
+            .spawn(move || listen(source, &thread_sender, &receiver, &thread_subscribers))
+            .map_err(|err| error!("Could not spawn input listener thread: {err}"))
+            .ok();
+        Self {
+            sender,
+            subscribers,
+            thread,
+        }
+    }
+
+    /// Get a receiver of all input changes from now on
+    pub fn subscribe(&self) -> Receiver<InputUpdate> {
+        let (sender, receiver) = channel();
+        match self.subscribers.lock() {
+            Ok(mut subscribers) => subscribers.push(sender),
+            Err(err) => error!("Could not add input subscriber: {err}"),
+        }
+        receiver
+    }
+
+    /// Stop the source and wait for the listener thread to finish
+    pub fn shutdown(&mut self) {
+        let Some(thread) = self.thread.take() else {
+            return;
+        };
+        debug!("Shutting down input listener");
+        // Fails only if the thread already stopped
+        let _ = self.sender.send(InputMessage::Shutdown);
+        if thread.join().is_err() {
+            error!("Input listener thread panicked");
+        }
+    }
+}
+
+impl Drop for InputListener {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+pub type InputListenerState = Mutex<InputListener>;
+
+fn listen(
+    source: Box<dyn InputSource>,
+    sender: &Sender<InputMessage>,
+    receiver: &Receiver<InputMessage>,
+    subscribers: &Subscribers,
+) {
+    let _guard = match source.start(sender.clone()) {
+        Ok(guard) => guard,
+        Err(err) => {
+            error!("Could not start input source: {err}");
+            return;
+        }
+    };
+    let mut state = KeyState::default();
+    // Blocks (parking the thread) until the next event
+    while let Ok(InputMessage::Event(event)) = receiver.recv() {
+        if !state.apply(&event) {
+            continue;
+        }
+        trace!("Input event {event:?}");
+        let update = InputUpdate {
+            event,
+            state: state.clone(),
+        };
+        match subscribers.lock() {
+            Ok(mut subscribers) => {
+                subscribers.retain(|subscriber| subscriber.send(update.clone()).is_ok());
+            }
+            Err(err) => warn!("Could not get input subscribers: {err}"),
+        }
+    }
+    // Disconnect subscribers, so their receiving threads can finish
+    if let Ok(mut subscribers) = subscribers.lock() {
+        subscribers.clear();
+    }
+    debug!("Input listener stopped");
+}
+
+/// Global input from `device_query`, which polls the devices on its own thread
+pub struct DeviceQuerySource {
+    interval: Duration,
+}
+
+impl DeviceQuerySource {
+    pub const fn new(interval: Duration) -> Self {
+        Self { interval }
+    }
+}
+
+impl InputSource for DeviceQuerySource {
+    fn start(self: Box<Self>, sender: Sender<InputMessage>) -> Result<SourceGuard, String> {
+        use device_query::{DeviceEvents, DeviceEventsHandler};
+
+        let handler = DeviceEventsHandler::new(self.interval)
+            .ok_or_else(|| "Device events handler already running".to_string())?;
+        let send = move |event| {
+            let _ = sender.send(InputMessage::Event(event));
+        };
+        let (key_down, key_up, mouse_down) = (send.clone(), send.clone(), send.clone());
+        let guards: Vec<Box<dyn Any>> = vec![
+            Box::new(
+                handler.on_key_down(move |key| key_down(InputEvent::KeyDown(key.to_string()))),
+            ),
+            Box::new(handler.on_key_up(move |key| key_up(InputEvent::KeyUp(key.to_string())))),
+            Box::new(handler.on_mouse_down(move |button| {
+                if let Ok(button) = u8::try_from(*button) {
+                    mouse_down(InputEvent::MouseDown(button));
+                }
+            })),
+            Box::new(handler.on_mouse_up(move |button| {
+                if let Ok(button) = u8::try_from(*button) {
+                    send(InputEvent::MouseUp(button));
+                }
+            })),
+        ];
+        Ok(Box::new((handler, guards)))
+    }
+}
+
+#[cfg(test)]
+/// Source sending a fixed list of events, for tests
+pub struct SyntheticSource(pub Vec<InputEvent>);
+
+#[cfg(test)]
+impl InputSource for SyntheticSource {
+    fn start(self: Box<Self>, sender: Sender<InputMessage>) -> Result<SourceGuard, String> {
+        for event in self.0 {
+            sender
+                .send(InputMessage::Event(event))
+                .map_err(|err| err.to_string())?;
+        }
+        Ok(Box::new(()))
+    }
+}
+
+#[test]
+fn test_key_state_ignores_repeats() {
+    let mut state = KeyState::default();
+    assert!(state.apply(&InputEvent::KeyDown("A".into())));
+    assert!(!state.apply(&InputEvent::KeyDown("A".into())));
+    assert!(state.apply(&InputEvent::MouseDown(4)));
+    assert!(state.apply(&InputEvent::KeyUp("A".into())));
+    assert!(state.keys.is_empty());
+    assert_eq!(state.mouse_buttons, BTreeSet::from([4]));
+}
+
+#[test]
+fn test_listener_broadcasts_and_shuts_down() {
+    // Gate the source on a subscriber existing, so no event is sent before subscribing
+    struct GatedSource(Receiver<()>, SyntheticSource);
+    impl InputSource for GatedSource {
+        fn start(self: Box<Self>, sender: Sender<InputMessage>) -> Result<SourceGuard, String> {
+            self.0.recv().map_err(|err| err.to_string())?;
+            Box::new(self.1).start(sender)
+        }
+    }
+    let (ready, gate) = channel();
+    let mut listener = InputListener::start(Box::new(GatedSource(
+        gate,
+        SyntheticSource(vec![
+            InputEvent::KeyDown("LShift".into()),
+            InputEvent::KeyDown("F9".into()),
+        ]),
+    )));
+    let first = listener.subscribe();
+    let second = listener.subscribe();
+    ready.send(()).expect("Listener should be waiting");
+    let timeout = Duration::from_secs(1);
+    for receiver in [&first, &second] {
+        receiver.recv_timeout(timeout).expect("First update");
+        let update = receiver.recv_timeout(timeout).expect("Second update");
+        assert_eq!(update.event, InputEvent::KeyDown("F9".into()));
+        assert_eq!(update.state.keys.len(), 2);
+    }
+    listener.shutdown();
+    assert!(first.recv_timeout(timeout).is_err());
+}
@@ -0,0 +1,80 @@
+//! Spoken language detection results, optionally restricted to the languages a user speaks.
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// Number of most likely languages reported with a detection
+pub const DETECTED_CANDIDATES: usize = 5;
+
+/// Value of [`crate::types::TranscribeOptions::language`] asking for detection
+pub const AUTO_LANGUAGE: &str = "auto";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+/// Probability of the audio being in a language
+pub struct LanguageProbability {
+    /// Whisper language code, e.g. `en` or `de`
+    pub language: String,
+    pub probability: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+/// Result of detecting the spoken language
+pub struct LanguageDetection {
+    /// Language the audio was transcribed in
+    pub language: String,
+    /// Most likely languages, most likely first
+    pub candidates: Vec<LanguageProbability>,
+}
+
+/// Whether the language should be detected instead of given to whisper
+pub fn should_detect(language: Option<&str>) -> bool {
+    language.is_none_or(|language| language.eq_ignore_ascii_case(AUTO_LANGUAGE))
+}
+
+/// Rank the language probabilities, keeping only the allowed languages if given.
+///
+/// Returns `None` if no language is left.
+pub fn rank_languages(
+    probabilities: impl IntoIterator<Item = (String, f32)>,
+    allowed: Option<&[String]>,
+    top: usize,
+) -> Option<LanguageDetection> {
+    let mut candidates = probabilities
+        .into_iter()
+        .filter(|(language, _)| {
+            allowed.is_none_or(|allowed| {
+                allowed
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(language))
+            })
+        })
+        .map(|(language, probability)| LanguageProbability {
+            language,
+            probability,
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| b.probability.total_cmp(&a.probability));
+    candidates.truncate(top);
+    Some(LanguageDetection {
+        language: candidates.first()?.language.clone(),
+        candidates,
+    })
+}
+
+#[test]
+fn test_rank_languages() {
+    let probabilities = || {
+        [("en", 0.2), ("de", 0.1), ("nl", 0.6), ("fr", 0.05)]
+            .map(|(language, probability)| (language.to_string(), probability))
+    };
+    let detection = rank_languages(probabilities(), None, 2).expect("Some language");
+    assert_eq!(detection.language, "nl");
+    assert_eq!(detection.candidates.len(), 2);
+    assert_eq!(detection.candidates[1].language, "en");
+    let allowed = ["DE".to_string(), "en".to_string()];
+    let detection = rank_languages(probabilities(), Some(&allowed), 5).expect("Some language");
+    assert_eq!(detection.language, "en");
+    assert_eq!(detection.candidates.len(), 2);
+    assert!(rank_languages(probabilities(), Some(&["ja".to_string()]), 5).is_none());
+    assert!(should_detect(None) && should_detect(Some("Auto")) && !should_detect(Some("en")));
+}
@@ -0,0 +1,170 @@
+//! Input level metering (RMS, peak and clipping) and silence detection for live recordings.
+
+use crate::types::CaptureSource;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::time::{Duration, Instant};
+
+/// Minimum time between two level updates
+const LEVEL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// RMS below which a window counts as silent (about -60 dBFS)
+const SILENCE_RMS: f32 = 0.001;
+
+/// How long the input must be silent before warning
+const SILENCE_WARNING_AFTER: Duration = Duration::from_secs(3);
+
+/// Absolute sample value from which a sample counts as clipped
+const CLIPPING_LEVEL: f32 = 0.999;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Type)]
+/// Levels of the input over one window, all values are linear from `0.0` to `1.0`
+pub struct InputLevel {
+    /// Source the level was measured on
+    pub source: CaptureSource,
+    pub rms: f32,
+    pub peak: f32,
+    /// Whether any sample in the window reached full scale
+    pub clipping: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Output of the [`LevelMeter`]
+pub enum MeterUpdate {
+    Level(InputLevel),
+    /// Input has been silent for the given duration
+    Silence(Duration),
+}
+
+#[derive(Debug, Default)]
+/// Accumulates samples into throttled level windows.
+///
+/// Fed by the thread draining the capture buffer, never by the realtime audio callback, where
+/// emitting events could block and glitch the recording.
+pub struct LevelMeter {
+    source: CaptureSource,
+    window_start: Option<Instant>,
+    sum_squares: f64,
+    count: usize,
+    peak: f32,
+    clipping: bool,
+    silent_since: Option<Instant>,
+    warned: bool,
+}
+
+impl LevelMeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Meter for the given source, only the microphone warns about silence
+    pub fn for_source(source: CaptureSource) -> Self {
+        Self {
+            source,
+            ..Self::default()
+        }
+    }
+
+    /// Add samples received at the given time, returning updates when a window completes.
+    ///
+    /// Does not allocate, as it runs for every drained block.
+    pub fn push(&mut self, samples: &[f32], now: Instant) -> impl Iterator<Item = MeterUpdate> {
+        let window_start = *self.window_start.get_or_insert(now);
+        for &sample in samples {
+            let magnitude = sample.abs();
+            self.sum_squares += f64::from(sample) * f64::from(sample);
+            self.peak = self.peak.max(magnitude);
+            self.clipping |= magnitude >= CLIPPING_LEVEL;
+        }
+        self.count += samples.len();
+        if now.duration_since(window_start) < LEVEL_INTERVAL || self.count == 0 {
+            return [None, None].into_iter().flatten();
+        }
+        #[allow(
+            clippy::cast_precision_loss,
+            clippy::cast_possible_truncation,
+            reason = "Window sample counts are small, and levels only need f32 precision"
+        )]
+        let level = InputLevel {
+            source: self.source,
+            rms: (self.sum_squares / self.count as f64).sqrt() as f32,
+            peak: self.peak,
+            clipping: self.clipping,
+        };
+        *self = Self {
+            source: self.source,
+            window_start: Some(now),
+            silent_since: self.silent_since,
+            warned: self.warned,
+            ..Self::default()
+        };
+        let mut silence = None;
+        if level.rms < SILENCE_RMS {
+            let silent_since = *self.silent_since.get_or_insert(now);
+            let silent_for = now.duration_since(silent_since);
+            let warns = self.source == CaptureSource::Microphone;
+            if warns && silent_for >= SILENCE_WARNING_AFTER && !self.warned {
+                self.warned = true;
+                silence = Some(MeterUpdate::Silence(silent_for));
+            }
+        } else {
+            self.silent_since = None;
+            self.warned = false;
+        }
+        [Some(MeterUpdate::Level(level)), silence]
+            .into_iter()
+            .flatten()
+    }
+}
+
+#[test]
+fn test_levels_are_throttled() {
+    let mut meter = LevelMeter::new();
+    let now = Instant::now();
+    assert_eq!(meter.push(&[0.5, -0.5], now).count(), 0);
+    assert_eq!(
+        meter.push(&[1.0], now + Duration::from_millis(50)).count(),
+        0
+    );
+    let mut updates = meter.push(&[-0.5], now + Duration::from_millis(120));
+    let Some(MeterUpdate::Level(level)) = updates.next() else {
+        panic!("Expected a level update");
+    };
+    assert!((level.rms - 0.661).abs() < 1e-3);
+    assert!((level.peak - 1.0).abs() < f32::EPSILON);
+    assert!(level.clipping);
+}
+
+#[test]
+fn test_silence_warns_once() {
+    let mut meter = LevelMeter::new();
+    let start = Instant::now();
+    let mut warnings = 0;
+    for step in 0..60 {
+        let now = start + LEVEL_INTERVAL * step;
+        warnings += meter
+            .push(&[0.0; 16], now)
+            .filter(|update| matches!(update, MeterUpdate::Silence(_)))
+            .count();
+    }
+    assert_eq!(warnings, 1);
+    meter.push(&[0.3; 16], start + LEVEL_INTERVAL * 61);
+    assert!(meter.silent_since.is_none());
+}
+
+#[test]
+fn test_system_audio_does_not_warn_on_silence() {
+    let mut meter = LevelMeter::for_source(CaptureSource::System);
+    let start = Instant::now();
+    let updates = (0..60)
+        .flat_map(|step| meter.push(&[0.0; 16], start + LEVEL_INTERVAL * step))
+        .collect::<Vec<_>>();
+    assert!(!updates.is_empty());
+    assert!(updates.iter().all(|update| matches!(
+        update,
+        MeterUpdate::Level(InputLevel {
+            source: CaptureSource::System,
+            ..
+        })
+    )));
+}
@@ -9,27 +9,58 @@
 use log::{debug, error, info, trace, warn, LevelFilter};
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
 use std::{collections::HashMap, path::PathBuf};
 use tauri::{path::BaseDirectory, Manager};
-use tauri::{App, AppHandle};
+use tauri::{App, AppHandle, RunEvent};
 use tauri_plugin_sentry::sentry;
 use tauri_plugin_sentry::sentry::ClientInitGuard;
 use tauri_specta::{Builder, Event};
 use types::InnerMicrophoneData;
+use whisper_rs::WhisperContextParameters;
 
 // Internal Modules
+mod alternatives;
+mod benchmark;
+mod capture;
+mod chunking;
 mod command;
+mod confidence;
+mod devices;
+mod diarization;
+mod dictation;
+mod error;
+pub mod evaluation;
 mod events;
+mod hallucination;
+mod history;
+mod hotkeys;
+mod input;
+mod language;
+mod levels;
+mod loopback;
+mod metrics;
 mod mutter;
+mod output;
+mod profiles;
+mod server;
+pub mod settings;
 mod transcript;
 mod types;
 mod utils;
 
 use command::listen_for_mouse_click;
+use devices::{DeviceWatcher, DeviceWatcherState, DEVICE_WATCH_INTERVAL};
+use dictation::DictationQueue;
 use events::ModKeyEvent;
+use history::InnerTranscriptHistory;
+use hotkeys::{HotkeyEngine, InputEvent, Modifier};
+use input::{DeviceQuerySource, InputListener, InputListenerState};
 use mutter::Model;
-use types::{is_modkey, InnerAppState, InnerMicrophoneState, InnerSoundMapState, ModKeyPayload};
-use utils::will_send_to_sentry;
+use profiles::InnerProfileState;
+use server::InnerApiServerState;
+use types::{InnerAppState, InnerMicrophoneState, InnerSoundMapState, ModKeyPayload};
+use utils::{gpu_supported, will_send_to_sentry};
 
 pub use crate::command::get_collected_commands;
 pub use crate::events::get_collected_events;
@@ -84,8 +115,9 @@ pub fn run() {
     app_builder
         .invoke_handler(bindings_builder.invoke_handler())
         .setup(move |app| setup_app(app, &bindings_builder))
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(handle_run_event);
     info!("Finish app building");
 }
 
@@ -193,22 +225,35 @@ fn setup_app(app: &App, bindings_builder: &Builder) -> Result<(), Box<dyn std::e
     trace!("Start resolving resource model path");
     let default_model_path = resolve_model_path(app)?;
     trace!("Converted model path");
-    let model = Model::new(&default_model_path)?;
+    let settings = settings::load(app.handle());
+    let mut model_params = WhisperContextParameters::default();
+    model_params.use_gpu(settings.use_gpu.unwrap_or_else(gpu_supported));
+    let model = Model::new_with_params(&default_model_path, model_params)?;
     trace!("Created new model");
     debug!("Start loading sound paths");
     let sound_map = create_sound_map(app)?;
     debug!("Finished creating sound map");
-    app.manage(Mutex::new(InnerAppState::new(model)));
+    app.manage(Mutex::new(InnerAppState::new(model, default_model_path)));
     app.manage(Mutex::new(InnerSoundMapState::with_map(sound_map)));
     app.manage(Mutex::new(InnerMicrophoneState::new()));
     app.manage(Mutex::new(InnerMicrophoneData::new()));
+    app.manage(Mutex::new(InnerTranscriptHistory::new()));
+    app.manage(Mutex::new(InnerProfileState::new()));
+    app.manage(Mutex::new(InnerApiServerState::new()));
+    app.manage(Mutex::new(HotkeyEngine::new()));
+    app.manage(Mutex::new(settings));
     trace!("Created initial app state");
+    settings::apply_on_start(app.handle());
+    app.manage(Mutex::new(DeviceWatcher::start(
+        app.handle().clone(),
+        DEVICE_WATCH_INTERVAL,
+    )));
+    debug!("Started input device watcher");
     debug!("Setup mouse click listener");
     let _mouse_click_listener_handler = listen_for_mouse_click(app.handle().clone())?;
+    app.manage(DictationQueue::start(app.handle().clone()));
     debug!("Setup modifier key listener");
-    let app_key_listener_handler = app.handle().clone();
-    trace!("Cloned app handle for new listener thread");
-    setup_key_listeners(&app_key_listener_handler);
+    setup_key_listeners(app);
     debug!("Finished setting up key listeners");
     configure_overlay(app)?;
     setup_main_window_close_event(app);
@@ -295,48 +340,63 @@ fn create_sound_map(app: &App) -> Result<HashMap<String, PathBuf>, Box<dyn std::
     Ok(map)
 }
 
-/// Set up key listeners for modifier keys.
+/// Start the global input listener, emitting modifier key events and feeding the hotkey engine.
 ///
-/// # Errors
-///
-/// Returns an error if the listener setup fails.
-fn setup_key_listeners(app_handle: &AppHandle) {
-    let app_handle_up = app_handle.clone();
-    let app_handle_down = app_handle.clone();
-    trace!("Created clones for app handlers");
-    std::mem::drop(tauri::async_runtime::spawn_blocking(move || {
-        use device_query::{DeviceEvents, DeviceEventsHandler};
-        use std::time::Duration;
-
-        let device_state = DeviceEventsHandler::new(Duration::from_millis(KEY_QUERY_MILLIS))
-            .expect("Failed to start event loop");
-        trace!("Created device state");
-
-        let _up_guard = device_state.on_key_up(move |key| {
-            if is_modkey(*key) {
-                trace!("Mod Key UP Event with {key:?}");
-                let _ = ModKeyEvent::with_payload(ModKeyPayload::released(key.to_string()))
-                    .emit(&app_handle_up)
-                    .map_err(|err| error!("Error for mod key event release: {err}"));
-            }
-        });
-        trace!("Start listening for key up events");
-        let _down_guard = device_state.on_key_down(move |key| {
-            if is_modkey(*key) {
-                trace!("Mod Key Event DOWN with {key:?}");
-                let _ = ModKeyEvent::with_payload(ModKeyPayload::pressed(key.to_string()))
-                    .emit(&app_handle_down)
-                    .map_err(|err| error!("Error for mod key event press: {err}"));
+/// The listener is managed as [`InputListenerState`] and shut down when the app exits.
+fn setup_key_listeners(app: &App) {
+    let listener = InputListener::start(Box::new(DeviceQuerySource::new(Duration::from_millis(
+        KEY_QUERY_MILLIS,
+    ))));
+    let updates = listener.subscribe();
+    let app_handle = app.handle().clone();
+    trace!("Created input listener");
+    let spawned = std::thread::Builder::new()
+        .name("input-events".into())
+        .spawn(move || {
+            // Ends once the listener shuts down and drops its subscribers
+            for update in updates {
+                match &update.event {
+                    InputEvent::KeyDown(key) if Modifier::from_key_name(key).is_some() => {
+                        trace!("Mod Key Event DOWN with {key:?}");
+                        let _ = ModKeyEvent::with_payload(ModKeyPayload::pressed(key.clone()))
+                            .emit(&app_handle)
+                            .map_err(|err| error!("Error for mod key event press: {err}"));
+                    }
+                    InputEvent::KeyUp(key) if Modifier::from_key_name(key).is_some() => {
+                        trace!("Mod Key UP Event with {key:?}");
+                        let _ = ModKeyEvent::with_payload(ModKeyPayload::released(key.clone()))
+                            .emit(&app_handle)
+                            .map_err(|err| error!("Error for mod key event release: {err}"));
+                    }
+                    _ => {}
+                }
+                dictation::handle_input_event(&app_handle, &update.event);
             }
+            debug!("Input event thread finished");
         });
-        trace!("Start listening for key down events");
-        // Keep the process alive by running an infinte loop
-        #[allow(
-            clippy::empty_loop,
-            reason = "Require loop to ensure thread remains active"
-        )]
-        loop {}
-    }));
+    if let Err(err) = spawned {
+        error!("Could not spawn input event thread: {err}");
+    }
+    app.manage(Mutex::new(listener));
+}
+
+/// Stop background subsystems before the app exits
+#[allow(
+    clippy::needless_pass_by_value,
+    reason = "Signature is required by Tauri's run callback"
+)]
+fn handle_run_event(app_handle: &AppHandle, event: RunEvent) {
+    if let RunEvent::Exit = event {
+        info!("App exiting, shutting down input listener and device watcher");
+        match app_handle.state::<InputListenerState>().lock() {
+            Ok(mut listener) => listener.shutdown(),
+            Err(err) => error!("Could not get input listener lock: {err}"),
+        }
+        match app_handle.state::<DeviceWatcherState>().lock() {
+            Ok(mut watcher) => watcher.shutdown(),
+            Err(err) => error!("Could not get device watcher lock: {err}"),
+        }
+    }
 }
 
 /// Configures the overlay window settings based on the app configuration.
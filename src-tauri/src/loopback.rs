@@ -0,0 +1,461 @@
+//! System audio (loopback) capture from PulseAudio or PipeWire monitor sources.
+//!
+//! `cpal` only offers input devices, so monitor sources are listed with `pactl` and recorded with
+//! `parec`, which both also work with PipeWire through its PulseAudio server. The system audio goes
+//! through its own ring buffer, and is lined up with the microphone by the recording thread. It is
+//! kept as its own track, so each utterance can be tagged with the source it was spoken in.
+
+use crate::{
+    capture::{capture_ring, CaptureReader, CaptureWriter},
+    levels::{LevelMeter, MeterUpdate},
+    transcript::{Transcript, Utterance},
+    types::CaptureSource,
+};
+use log::{debug, error, warn};
+use rodio::cpal::StreamConfig;
+use std::{
+    io::{ErrorKind, Read},
+    process::{Child, Command, Stdio},
+    thread::JoinHandle,
+    time::Instant,
+};
+
+/// Suffix of the PulseAudio name of monitor sources
+const MONITOR_SUFFIX: &str = ".monitor";
+
+/// Milliseconds one source may run ahead of the other before the other is padded with silence
+const MAX_LAG_MILLIS: u32 = 200;
+
+/// Names of the monitor sources, which record what is played on an output device
+pub fn monitor_sources() -> Result<Vec<String>, String> {
+    let output = Command::new("pactl")
+        .args(["list", "short", "sources"])
+        .output()
+        .map_err(|err| format!("Could not run pactl: {err}"))?;
+    if !output.status.success() {
+        return Err(format!("pactl exited with {}", output.status));
+    }
+    Ok(parse_monitor_sources(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Get the monitor source names from the tab separated output of `pactl list short sources`
+fn parse_monitor_sources(list: &str) -> Vec<String> {
+    list.lines()
+        .filter_map(|line| line.split('\t').nth(1))
+        .filter(|name| name.ends_with(MONITOR_SUFFIX))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Move the complete little endian `f32` samples from `bytes` to `out`, keeping the partial rest
+fn decode_f32le(bytes: &mut Vec<u8>, out: &mut Vec<f32>) {
+    let complete = bytes.len() / 4 * 4;
+    out.extend(
+        bytes[..complete]
+            .chunks_exact(4)
+            .map(|sample| <[u8; 4]>::try_from(sample).map_or(0.0, f32::from_le_bytes)),
+    );
+    bytes.drain(..complete);
+}
+
+/// Running `parec` process, writing the monitor source into a capture ring buffer
+struct LoopbackCapture {
+    child: Child,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl LoopbackCapture {
+    /// Start recording `source` in the channels and sample rate of `config`
+    fn start(
+        source: &str,
+        config: &StreamConfig,
+        mut writer: CaptureWriter,
+    ) -> Result<Self, String> {
+        let mut child = Command::new("parec")
+            .arg(format!("--device={source}"))
+            .arg("--format=float32le")
+            .arg(format!("--rate={}", config.sample_rate.0))
+            .arg(format!("--channels={}", config.channels))
+            .arg("--latency-msec=20")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| format!("Could not start parec: {err}"))?;
+        let Some(mut stdout) = child.stdout.take() else {
+            let _ = child.kill();
+            return Err("Could not read from parec".into());
+        };
+        let thread = std::thread::Builder::new()
+            .name("loopback-capture".into())
+            .spawn(move || {
+                let mut buffer = [0_u8; 4_096];
+                let mut bytes = Vec::with_capacity(buffer.len());
+                let mut samples = Vec::with_capacity(buffer.len() / 4);
+                loop {
+                    match stdout.read(&mut buffer) {
+                        Ok(0) => break,
+                        Ok(read) => {
+                            bytes.extend_from_slice(&buffer[..read]);
+                            samples.clear();
+                            decode_f32le(&mut bytes, &mut samples);
+                            writer.write(&samples);
+                        }
+                        Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                        Err(err) => {
+                            warn!("Could not read system audio: {err}");
+                            break;
+                        }
+                    }
+                }
+                debug!("Loopback capture stopped");
+            })
+            .map_err(|err| error!("Could not spawn loopback capture thread: {err}"))
+            .ok();
+        Ok(Self { child, thread })
+    }
+}
+
+impl Drop for LoopbackCapture {
+    fn drop(&mut self) {
+        // Killing `parec` closes its output, which ends the reading thread
+        if let Err(err) = self.child.kill() {
+            warn!("Could not stop parec: {err}");
+        }
+        let _ = self.child.wait();
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("Loopback capture thread panicked");
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Lines up the microphone with the system audio, which arrive at slightly different times
+pub struct SourceAligner {
+    microphone: Vec<f32>,
+    system: Vec<f32>,
+    channels: usize,
+    max_lag: usize,
+}
+
+impl SourceAligner {
+    pub fn new(config: &StreamConfig) -> Self {
+        let channels = usize::from(config.channels.max(1));
+        let frames = (config.sample_rate.0 * MAX_LAG_MILLIS / 1_000) as usize;
+        Self {
+            microphone: Vec::new(),
+            system: Vec::new(),
+            channels,
+            max_lag: frames * channels,
+        }
+    }
+
+    /// Add newly captured samples of both sources, moving all lined up samples to the outputs.
+    ///
+    /// A source lagging more than [`MAX_LAG_MILLIS`] behind is padded with silence, so a stalled
+    /// source (e.g. a suspended output) does not hold back the other.
+    pub fn align(
+        &mut self,
+        microphone: &[f32],
+        system: &[f32],
+        microphone_out: &mut Vec<f32>,
+        system_out: &mut Vec<f32>,
+    ) {
+        self.microphone.extend_from_slice(microphone);
+        self.system.extend_from_slice(system);
+        if self.microphone.len().abs_diff(self.system.len()) > self.max_lag {
+            self.pad_lagging_source();
+        }
+        self.drain(microphone_out, system_out);
+    }
+
+    /// Move all held samples to the outputs, padding the lagging source with silence
+    pub fn flush(&mut self, microphone_out: &mut Vec<f32>, system_out: &mut Vec<f32>) {
+        self.pad_lagging_source();
+        self.drain(microphone_out, system_out);
+        // Partial frames left by a source can not be lined up
+        self.clear();
+    }
+
+    /// Pad the source which is behind with whole frames of silence, so the channels stay in place
+    fn pad_lagging_source(&mut self) {
+        let lag = self.microphone.len().abs_diff(self.system.len());
+        let padding = lag / self.channels * self.channels;
+        let behind = if self.microphone.len() < self.system.len() {
+            &mut self.microphone
+        } else {
+            &mut self.system
+        };
+        behind.resize(behind.len() + padding, 0.0);
+    }
+
+    fn drain(&mut self, microphone_out: &mut Vec<f32>, system_out: &mut Vec<f32>) {
+        let ready = self.microphone.len().min(self.system.len());
+        microphone_out.extend(self.microphone.drain(..ready));
+        system_out.extend(self.system.drain(..ready));
+    }
+
+    /// Forget samples waiting for the other source
+    pub fn clear(&mut self) {
+        self.microphone.clear();
+        self.system.clear();
+    }
+}
+
+/// System audio captured for one recording
+pub struct SystemAudio {
+    reader: CaptureReader,
+    aligner: SourceAligner,
+    meter: LevelMeter,
+    mix_with_microphone: bool,
+    block: Vec<f32>,
+    _capture: LoopbackCapture,
+}
+
+impl SystemAudio {
+    /// Start recording the monitor `source` in the format of the microphone stream
+    pub fn start(
+        source: &str,
+        config: &StreamConfig,
+        mix_with_microphone: bool,
+    ) -> Result<Self, String> {
+        let (writer, reader) = capture_ring(config);
+        let capture = LoopbackCapture::start(source, config, writer)?;
+        debug!("Capturing system audio from {source}");
+        Ok(Self {
+            reader,
+            aligner: SourceAligner::new(config),
+            meter: LevelMeter::for_source(CaptureSource::System),
+            mix_with_microphone,
+            block: Vec::new(),
+            _capture: capture,
+        })
+    }
+
+    /// Whether the system audio is also mixed into the microphone recording
+    pub const fn mixes_with_microphone(&self) -> bool {
+        self.mix_with_microphone
+    }
+
+    /// Line up the recorded audio with the `microphone` samples, moving the microphone samples
+    /// to `out` (mixed with the system audio if enabled) and the system audio to `system_out`.
+    ///
+    /// Returns the level updates of the system audio.
+    pub fn mix_into(
+        &mut self,
+        microphone: &[f32],
+        out: &mut Vec<f32>,
+        system_out: &mut Vec<f32>,
+    ) -> impl Iterator<Item = MeterUpdate> {
+        self.block.clear();
+        self.reader.drain_into(&mut self.block);
+        let updates = self.meter.push(&self.block, Instant::now());
+        let (start, system_start) = (out.len(), system_out.len());
+        self.aligner.align(microphone, &self.block, out, system_out);
+        self.mix(&mut out[start..], &system_out[system_start..]);
+        updates
+    }
+
+    /// Like [`Self::mix_into`] for the last samples of the recording, also moving the samples
+    /// still waiting for the other source
+    pub fn finish_into(
+        &mut self,
+        microphone: &[f32],
+        out: &mut Vec<f32>,
+        system_out: &mut Vec<f32>,
+    ) {
+        let _ = self.mix_into(microphone, out, system_out);
+        let (start, system_start) = (out.len(), system_out.len());
+        self.aligner.flush(out, system_out);
+        self.mix(&mut out[start..], &system_out[system_start..]);
+    }
+
+    fn mix(&self, microphone: &mut [f32], system: &[f32]) {
+        if self.mix_with_microphone {
+            for (microphone, system) in microphone.iter_mut().zip(system) {
+                *microphone = (*microphone + system).clamp(-1.0, 1.0);
+            }
+        }
+    }
+
+    /// Throw away the audio recorded while paused
+    pub fn discard(&mut self) {
+        self.block.clear();
+        self.reader.drain_into(&mut self.block);
+        self.aligner.clear();
+        self.meter = LevelMeter::for_source(CaptureSource::System);
+    }
+
+    /// Number of system audio samples dropped since the last call
+    pub fn new_overruns(&mut self) -> usize {
+        self.reader.new_overruns()
+    }
+}
+
+/// Energy of the microphone and of the system audio in each centisecond, whisper's timestamp unit
+pub struct SourceEnergies(Vec<(f32, f32)>);
+
+impl SourceEnergies {
+    /// Measure the lined up sources.
+    ///
+    /// `recorded` holds the microphone samples, mixed with `system` if `mixed`. Both have
+    /// `samples_per_second` samples over all channels.
+    pub fn measure(
+        recorded: &[f32],
+        system: &[f32],
+        mixed: bool,
+        samples_per_second: usize,
+    ) -> Self {
+        let samples_per_centisecond = (samples_per_second / 100).max(1);
+        Self(
+            recorded
+                .chunks(samples_per_centisecond)
+                .zip(system.chunks(samples_per_centisecond))
+                .map(|(recorded, system)| {
+                    recorded.iter().zip(system).fold(
+                        (0.0, 0.0),
+                        |(microphone, total), (&recorded, &system)| {
+                            let own = if mixed { recorded - system } else { recorded };
+                            (microphone + own * own, total + system * system)
+                        },
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Tag each utterance with the source that was loudest while it was spoken
+    pub fn tag(&self, utterances: &mut [Utterance]) {
+        let index = |timestamp: i64| usize::try_from(timestamp).unwrap_or(0).min(self.0.len());
+        for utterance in utterances {
+            let stop = index(utterance.stop);
+            let start = index(utterance.start).min(stop);
+            let (microphone, system) = self.0[start..stop]
+                .iter()
+                .fold((0.0_f32, 0.0_f32), |(microphone, system), energies| {
+                    (microphone + energies.0, system + energies.1)
+                });
+            utterance.source = Some(if system > microphone {
+                CaptureSource::System
+            } else {
+                CaptureSource::Microphone
+            });
+        }
+    }
+}
+
+/// Merge the transcript of the microphone with the one of the system audio by time, tagging each
+/// utterance with its source
+pub fn merge_source_transcripts(microphone: Transcript, system: Transcript) -> Transcript {
+    let tagged = |utterances: Vec<Utterance>, source| {
+        utterances.into_iter().map(move |utterance| Utterance {
+            source: Some(source),
+            ..utterance
+        })
+    };
+    let merge = |microphone: Vec<Utterance>, system: Vec<Utterance>| {
+        let mut merged: Vec<Utterance> = tagged(microphone, CaptureSource::Microphone)
+            .chain(tagged(system, CaptureSource::System))
+            .collect();
+        merged.sort_by_key(|utterance| utterance.start);
+        merged
+    };
+    let word_utterances = match (microphone.word_utterances, system.word_utterances) {
+        (None, None) => None,
+        (microphone, system) => Some(merge(
+            microphone.unwrap_or_default(),
+            system.unwrap_or_default(),
+        )),
+    };
+    Transcript {
+        processing_time: microphone.processing_time + system.processing_time,
+        utterances: merge(microphone.utterances, system.utterances),
+        word_utterances,
+        language: microphone.language.or(system.language),
+    }
+}
+
+#[test]
+fn test_parse_monitor_sources() {
+    let list = "52\talsa_output.pci-0000_00_1f.3.analog-stereo.monitor\tPipeWire\ts32le 2ch 48000Hz\tSUSPENDED\n\
+                53\talsa_input.pci-0000_00_1f.3.analog-stereo\tPipeWire\ts32le 2ch 48000Hz\tRUNNING\n";
+    assert_eq!(
+        parse_monitor_sources(list),
+        vec!["alsa_output.pci-0000_00_1f.3.analog-stereo.monitor".to_string()]
+    );
+    let mut bytes = [0.5_f32.to_le_bytes(), (-1.0_f32).to_le_bytes()].concat();
+    bytes.push(7);
+    let mut samples = Vec::new();
+    decode_f32le(&mut bytes, &mut samples);
+    assert_eq!(samples, vec![0.5, -1.0]);
+    assert_eq!(bytes, vec![7]);
+}
+
+#[test]
+fn test_source_aligner() {
+    let config = StreamConfig {
+        channels: 2,
+        sample_rate: rodio::cpal::SampleRate(100),
+        buffer_size: rodio::cpal::BufferSize::Default,
+    };
+    let mut aligner = SourceAligner::new(&config);
+    let (mut microphone, mut system) = (Vec::new(), Vec::new());
+    aligner.align(&[0.25; 6], &[0.5; 4], &mut microphone, &mut system);
+    assert_eq!((microphone.len(), system.len()), (4, 4));
+    aligner.align(&[], &[0.5; 2], &mut microphone, &mut system);
+    assert_eq!((microphone.len(), system.len()), (6, 6));
+    // A stalled microphone is padded with silence, in whole frames
+    aligner.align(&[], &[0.5; 45], &mut microphone, &mut system);
+    assert_eq!((microphone.len(), system.len()), (6 + 44, 6 + 44));
+    assert_eq!(microphone[6..], [0.0; 44]);
+    // Flushing keeps the samples held for the other source
+    aligner.align(&[0.25; 5], &[], &mut microphone, &mut system);
+    aligner.flush(&mut microphone, &mut system);
+    assert_eq!((microphone.len(), system.len()), (50 + 5, 50 + 5));
+    assert_eq!(system[50..], [0.5, 0.0, 0.0, 0.0, 0.0]);
+}
+
+#[test]
+fn test_tag_sources() {
+    let utterance = |start, stop| Utterance {
+        start,
+        stop,
+        text: String::new(),
+        speaker: None,
+        hallucination: None,
+        confidence: None,
+        alternatives: Vec::new(),
+        source: None,
+    };
+    // One second of microphone speech, then one second of a call mixed in
+    let system = [vec![0.0; 100], vec![0.5; 100]].concat();
+    let mixed = [vec![0.25; 100], vec![0.5; 100]].concat();
+    let mut utterances = vec![utterance(0, 100), utterance(100, 200)];
+    SourceEnergies::measure(&mixed, &system, true, 100).tag(&mut utterances);
+    assert_eq!(utterances[0].source, Some(CaptureSource::Microphone));
+    assert_eq!(utterances[1].source, Some(CaptureSource::System));
+
+    let transcript = |utterances| Transcript {
+        processing_time: std::time::Duration::from_secs(1),
+        utterances,
+        word_utterances: None,
+        language: None,
+    };
+    let merged = merge_source_transcripts(
+        transcript(vec![utterance(0, 100), utterance(300, 400)]),
+        transcript(vec![utterance(150, 250)]),
+    );
+    assert_eq!(merged.processing_time.as_secs(), 2);
+    let sources: Vec<_> = merged.utterances.iter().map(|u| u.source).collect();
+    assert_eq!(
+        sources,
+        vec![
+            Some(CaptureSource::Microphone),
+            Some(CaptureSource::System),
+            Some(CaptureSource::Microphone)
+        ]
+    );
+}
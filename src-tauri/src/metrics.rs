@@ -0,0 +1,82 @@
+//! Accuracy metrics comparing a transcript to the reference text that was spoken.
+//!
+//! Both texts are compared case-insensitively and without punctuation, as whisper's casing and
+//! punctuation say little about how well the words were recognized.
+
+/// Lowercase words of the text, without punctuation except within words (e.g. `it's`)
+pub fn normalized_words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|word| {
+            word.trim_matches(|c: char| !c.is_alphanumeric())
+                .chars()
+                .filter(|&c| c.is_alphanumeric() || c == '\'')
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Minimum number of insertions, deletions and substitutions turning `reference` into `hypothesis`
+pub fn edit_distance<T: PartialEq>(reference: &[T], hypothesis: &[T]) -> usize {
+    let mut previous = (0..=hypothesis.len()).collect::<Vec<_>>();
+    let mut current = vec![0; hypothesis.len() + 1];
+    for (i, expected) in reference.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, actual) in hypothesis.iter().enumerate() {
+            let substitution = previous[j] + usize::from(expected != actual);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[hypothesis.len()]
+}
+
+/// Word error rate, the word edits needed per reference word
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "Texts have far less than 2^52 words"
+)]
+pub fn word_error_rate(reference: &str, hypothesis: &str) -> f64 {
+    let reference = normalized_words(reference);
+    let hypothesis = normalized_words(hypothesis);
+    edit_distance(&reference, &hypothesis) as f64 / reference.len().max(1) as f64
+}
+
+/// Character error rate, the character edits needed per reference character.
+///
+/// The characters of the normalized words are compared, with single spaces between words.
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "Texts have far less than 2^52 characters"
+)]
+pub fn character_error_rate(reference: &str, hypothesis: &str) -> f64 {
+    let characters = |text: &str| normalized_words(text).join(" ").chars().collect::<Vec<_>>();
+    let reference = characters(reference);
+    let hypothesis = characters(hypothesis);
+    edit_distance(&reference, &hypothesis) as f64 / reference.len().max(1) as f64
+}
+
+#[test]
+fn test_edit_distance() {
+    assert_eq!(edit_distance(b"kitten", b"sitting"), 3);
+    assert_eq!(edit_distance::<u8>(b"", b"abc"), 3);
+    assert_eq!(edit_distance(b"same", b"same"), 0);
+}
+
+#[test]
+fn test_word_error_rate() {
+    let reference = "The birch canoe slid on the smooth planks.";
+    assert!(word_error_rate(reference, " the Birch canoe slid on the smooth planks").abs() < 1e-9);
+    // One substitution and one deletion out of eight words
+    let rate = word_error_rate(reference, "The birch canoe slid on a planks.");
+    assert!((rate - 0.25).abs() < 1e-9);
+    assert_eq!(normalized_words("It's \"easy\"!"), ["it's", "easy"]);
+}
+
+#[test]
+fn test_character_error_rate() {
+    // One wrong character out of ten, including the space
+    let rate = character_error_rate("Rice bowls", "rice bowl, ");
+    assert!((rate - 0.1).abs() < 1e-9);
+}
@@ -5,8 +5,16 @@
 use std::time::Instant;
 
 use crate::{
+    alternatives::{attach_alternatives, hypothesis_temperature, MAX_HYPOTHESES},
+    chunking::{plan_chunks, transcribe_in_chunks},
+    confidence::{group_words, mean_confidence, Token},
+    diarization::assign_speakers,
+    hallucination::{HallucinationFilter, SegmentStatistics},
+    language::{
+        rank_languages, should_detect, LanguageDetection, LanguageProbability, DETECTED_CANDIDATES,
+    },
     transcript::{Transcript, Utterance},
-    types::AudioProcessingOptions,
+    types::{AudioProcessingOptions, Diarization, TranscribeOptions},
 };
 use log::{debug, error, trace, warn};
 use nnnoiseless::{DenoiseState, RnnModel};
@@ -14,9 +22,22 @@ use rodio::{buffer::SamplesBuffer, source::UniformSourceIterator, Decoder, Sourc
 use std::io::Cursor;
 use whisper_rs::{
     FullParams, SamplingStrategy, SegmentCallbackData, WhisperContext, WhisperContextParameters,
-    WhisperError,
+    WhisperError, WhisperState,
 };
 
+/// Callbacks reporting on a transcription, each one is optional
+#[derive(Default)]
+pub struct TranscribeCallbacks {
+    /// Polled to stop the transcription early when returning `true`
+    pub abort: Option<Box<dyn FnMut() -> bool>>,
+    /// Receives the progress in percent
+    pub progress: Option<Box<dyn FnMut(i32)>>,
+    /// Receives each new segment, with lossily converted text
+    pub new_segment_lossy: Option<Box<dyn FnMut(SegmentCallbackData)>>,
+    /// Receives each new segment
+    pub new_segment: Option<Box<dyn FnMut(SegmentCallbackData)>>,
+}
+
 /// Model struct. Can be constructed with [`Model::new`] or [`Model::download`].
 /// Contains the Whisper model and its context.
 pub struct Model {
@@ -67,34 +88,20 @@ impl Model {
     ///
     /// # Arguments
     /// - `audio`: Audio to transcribe. An array of bytes.
-    /// - `translate`: Whether to translate the text.
-    /// - `word_timestamps`: Whether to output word timestamps.
-    /// - `initial_prompt`: Optinal initial prompt to whisper model.
-    /// - `language`: Optinal language setting for whisper model.
-    /// - `threads`: Number of threads to use. `None` will use the number of cores from
-    ///   the `num_cpus` crate.
+    /// - `options`: Options of the transcription, see [`Model::transcribe_pcm_s16le`].
+    /// - `decode_options`: Processing of the audio, which is only decoded if `denoise_audio` is
+    ///   `Some(false)`.
+    /// - `callbacks`: Callbacks reporting on the transcription.
     /// # Errors
     /// - [`ModelError`]
     /// # Returns
     /// [Transcript]    
-    #[allow(
-        clippy::too_many_arguments,
-        reason = "Temporary allowed to prevent full refactoring into options struct"
-    )]
     pub fn transcribe_audio(
         &self,
         audio: impl AsRef<[u8]>,
-        translate: bool,
-        word_timestamps: bool,
-        initial_prompt: Option<&str>,
-        language: Option<&str>,
-        threads: Option<u16>,
-        patience: Option<f32>,
+        options: &TranscribeOptions,
         decode_options: AudioProcessingOptions,
-        abort_callback: Option<impl FnMut() -> bool + 'static>,
-        progress_callback: Option<impl FnMut(i32) + 'static>,
-        new_segment_lossy_callback: Option<impl FnMut(SegmentCallbackData) + 'static>,
-        new_segment_callback: Option<impl FnMut(SegmentCallbackData) + 'static>,
+        callbacks: TranscribeCallbacks,
     ) -> Result<Transcript, ModelError> {
         trace!("Decoding audio.");
         let samples = if decode_options.denoise_audio.is_none_or(|is_true| is_true) {
@@ -103,20 +110,7 @@ impl Model {
             decode(audio.as_ref().to_vec())?
         };
         trace!("Transcribing audio.");
-        self.transcribe_pcm_s16le(
-            &samples,
-            translate,
-            word_timestamps,
-            initial_prompt,
-            language,
-            threads,
-            patience,
-            abort_callback,
-            progress_callback,
-            new_segment_lossy_callback,
-            new_segment_callback,
-            None,
-        )
+        self.transcribe_pcm_s16le(&samples, options, callbacks)
     }
 
     /// Transcribes audio to text, given the audio is an [f32] float array of codec
@@ -127,11 +121,25 @@ impl Model {
     ///
     /// # Arguments
     /// - `audio`: Audio to transcribe. Must be a [f32] array.
-    /// - `translate`: Whether to translate the text.
-    /// - `word_timestamps`: Whether to output word timestamps.
-    /// - `initial_prompt`: Optinal initial prompt to whisper model.
-    /// - `language`: Optinal language setting for whisper model.
-    /// - `threads`: Number of threads to use. `None` will use the number of cores from
+    /// - `options`: Options of the transcription, of which these are used:
+    ///   - `translate`: Whether to translate the text.
+    ///   - `individual_word_timestamps`: Whether to output word timestamps.
+    ///   - `initial_prompt`: Optinal initial prompt to whisper model.
+    ///   - `language`: Optinal language setting for whisper model, detected if `None` or `auto`.
+    ///   - `threads`: Number of threads to use. `None` or `0` will use the number of cores from
+    ///     the `num_cpus` crate.
+    ///   - `patience`: Optional patience of the beam search.
+    ///   - `beam_size`: Number of beams searched, defaults to `5` and at most the number of
+    ///     cores.
+    ///   - `diarization`: Optional labelling of the segments with their speakers.
+    ///   - `allowed_languages`: Optional languages the detected language is picked from.
+    ///   - `chunking`: Optionally transcribe in chunks, reporting progress and segments per
+    ///     chunk.
+    ///   - `hallucination_filter`: Optionally drop or flag made up segments, falling back to
+    ///     higher temperatures while they look bad.
+    ///   - `alternatives`: Number of extra decodes whose differing texts are offered as
+    ///     alternatives of each segment, none if `None` and at most [`MAX_HYPOTHESES`].
+    /// - `callbacks`: Callbacks reporting on the transcription.
     ///
     /// # Errors
     /// - [`ModelError`]
@@ -139,33 +147,93 @@ impl Model {
     /// This function shouldn't panic, but may due to the underlying -sys c bindings.
     /// # Returns
     /// [Transcript]
-    #[allow(
-        clippy::too_many_arguments,
-        unused_variables,
-        clippy::needless_pass_by_value,
-        reason = "Temporary allowed to prevent full refactoring into options struct"
-    )]
     pub fn transcribe_pcm_s16le(
         &self,
         audio: &[f32],
-        translate: bool,
-        word_timestamps: bool,
-        initial_prompt: Option<&str>,
-        language: Option<&str>,
-        threads: Option<u16>,
-        patience: Option<f32>,
-        abort_callback: Option<impl FnMut() -> bool + 'static>,
-        progress_callback: Option<impl FnMut(i32) + 'static>,
-        new_segment_lossy_callback: Option<impl FnMut(SegmentCallbackData) + 'static>,
-        new_segment_callback: Option<impl FnMut(SegmentCallbackData) + 'static>,
-        beam_size: Option<i32>,
+        options: &TranscribeOptions,
+        callbacks: TranscribeCallbacks,
     ) -> Result<Transcript, ModelError> {
+        let translate = options.translate.unwrap_or(false);
+        let word_timestamps = options.individual_word_timestamps.unwrap_or(false);
+        let initial_prompt = options.initial_prompt.as_deref();
+        let language = options.language.as_deref();
+        // Make sure not to pass 0 for CPU thread, otherwise model crashes
+        let threads = options.threads.filter(|&threads| threads > 0);
+        let patience = options.patience;
+        let beam_size = options.beam_size;
+        let diarization = options.diarization;
+        let allowed_languages = options.allowed_languages.as_deref();
+        let hallucination_filter = options.hallucination_filter.as_ref();
+        let alternatives = options.alternatives;
         debug!("Start transcribing audio");
         trace!(
             "Transcribing audio (len = {}) with translate: {translate} and timestamps: {word_timestamps}",
             audio.len()
         );
 
+        let chunks = options
+            .chunking
+            .map_or_else(Vec::new, |chunking| plan_chunks(audio, chunking));
+        if chunks.len() > 1 {
+            let count = chunks.len();
+            let TranscribeCallbacks {
+                mut progress,
+                mut new_segment,
+                ..
+            } = callbacks;
+            // Keep the language of the first chunk for all others
+            let mut chunk_language = language
+                .filter(|&language| !should_detect(Some(language)))
+                .map(str::to_string);
+            let mut segment = 0;
+            let mut transcript = transcribe_in_chunks(
+                audio,
+                &chunks,
+                initial_prompt,
+                |chunk, prompt| {
+                    let chunk_options = TranscribeOptions {
+                        initial_prompt: prompt.map(str::to_string),
+                        language: chunk_language.clone(),
+                        // Speakers are clustered over the whole audio below
+                        diarization: diarization
+                            .filter(|&diarization| diarization == Diarization::SpeakerTurns),
+                        chunking: None,
+                        ..options.clone()
+                    };
+                    let part = self.transcribe_pcm_s16le(
+                        chunk,
+                        &chunk_options,
+                        TranscribeCallbacks::default(),
+                    )?;
+                    if let Some(detection) = &part.language {
+                        chunk_language.get_or_insert_with(|| detection.language.clone());
+                    }
+                    Ok(part)
+                },
+                |index, utterances| {
+                    debug!("Transcribed chunk {} of {count}", index + 1);
+                    if let Some(callback) = progress.as_mut() {
+                        callback(i32::try_from((index + 1) * 100 / count).unwrap_or(100));
+                    }
+                    if let Some(callback) = new_segment.as_mut() {
+                        for utterance in utterances {
+                            callback(SegmentCallbackData {
+                                segment,
+                                start_timestamp: utterance.start,
+                                end_timestamp: utterance.stop,
+                                text: utterance.text.clone(),
+                            });
+                            segment += 1;
+                        }
+                    }
+                },
+            )?;
+            if let Some(Diarization::Clustering { max_speakers }) = diarization {
+                assign_speakers(&mut transcript.utterances, audio, max_speakers);
+            }
+            return Ok(transcript);
+        }
+
         #[allow(
             clippy::cast_possible_truncation,
             clippy::cast_possible_wrap,
@@ -173,111 +241,221 @@ impl Model {
         )]
         let cpu_count = num_cpus::get() as i32;
 
-        let mut params = FullParams::new(SamplingStrategy::BeamSearch {
-            beam_size: beam_size.map_or(5, |decoder| decoder.min(cpu_count)),
-            patience: patience.map_or_else(|| 1.0, |p| p.min(0.0)),
-        });
-
-        if let Some(prompt) = initial_prompt {
-            params.set_initial_prompt(prompt);
-        }
-
-        params.set_language(language);
-
-        params.set_translate(translate);
-        params.set_print_special(false);
-        params.set_print_progress(false);
-        params.set_print_realtime(false);
-        params.set_print_timestamps(false);
-        params.set_token_timestamps(word_timestamps);
-        params.set_split_on_word(true);
-        params.set_single_segment(true);
-
-        // TODO: Uncomment when I can figure out how to fix crashing bug
-        // trace!("Adding Callbacks");
-        // if let Some(closure) = abort_callback {
-        //     params.set_abort_callback_safe(closure);
-        // }
-        // if let Some(closure) = progress_callback {
-        //     params.set_progress_callback_safe(closure);
-        // }
-        // if let Some(closure) = new_segment_lossy_callback {
-        //     params.set_segment_callback_safe_lossy(closure);
-        // }
-        // if let Some(closure) = new_segment_callback {
-        //     params.set_segment_callback_safe(closure);
-        // }
-
-        trace!("Basic params for Whisper Set");
-
         #[allow(clippy::cast_possible_wrap, clippy::cast_possible_truncation)]
         let threads = threads.map_or_else(|| cpu_count, i32::from);
 
-        trace!("Using {threads} threads");
-
-        params.set_n_threads(threads);
-
         let st = Instant::now();
         let mut state = self.context.create_state().map_err(|e| {
             error!("Failed to create Whisper state");
             ModelError::WhisperError(e)
         })?;
-        trace!("Transcribing audio with WhisperState");
-        state.full(params, audio).map_err(|e| {
-            error!("Failed to transcribe");
-            ModelError::WhisperError(e)
-        })?;
-
-        let num_segments = state.full_n_segments().map_err(|e| {
-            error!("Failed to get segments");
-            ModelError::WhisperError(e)
-        })?;
-        trace!("Number of segments: {num_segments}");
-
-        let mut words = Vec::new();
-        let mut utterances = Vec::new();
-        for segment_idx in 0..num_segments {
-            let text = state
-                .full_get_segment_text(segment_idx)
-                .map_err(ModelError::WhisperError)?;
-            let start = state
-                .full_get_segment_t0(segment_idx)
-                .map_err(ModelError::WhisperError)?;
-            let stop = state
-                .full_get_segment_t1(segment_idx)
-                .map_err(ModelError::WhisperError)?;
-
-            utterances.push(Utterance { start, stop, text });
-
-            if !word_timestamps {
-                trace!("Skipping word timestamps");
-                continue;
+        // Whisper detects any language by itself, only a restricted choice needs its own pass
+        let detection = match allowed_languages.filter(|_| should_detect(language)) {
+            Some(allowed) => Some(Self::detect_language(&mut state, audio, allowed, threads)?),
+            None => None,
+        };
+        let language = detection
+            .as_ref()
+            .map_or(language, |detection| Some(detection.language.as_str()));
+
+        let filter = hallucination_filter.map(HallucinationFilter::new);
+        let temperatures = filter
+            .as_ref()
+            .map_or(&[0.0][..], HallucinationFilter::temperatures);
+        let mut attempt = 0;
+        let (segments, verdicts) = loop {
+            let temperature = temperatures[attempt];
+            let mut params = if temperature > 0.0 {
+                // Sample instead of searching, like the original whisper does when falling back
+                FullParams::new(SamplingStrategy::Greedy { best_of: 5 })
+            } else {
+                FullParams::new(SamplingStrategy::BeamSearch {
+                    beam_size: beam_size.map_or(5, |decoder| decoder.min(cpu_count)),
+                    patience: patience.map_or_else(|| 1.0, |p| p.min(0.0)),
+                })
+            };
+
+            if let Some(prompt) = initial_prompt {
+                params.set_initial_prompt(prompt);
             }
 
-            trace!("Getting word timestamps for segment {segment_idx}");
-
-            let num_tokens = state
-                .full_n_tokens(segment_idx)
-                .map_err(ModelError::WhisperError)?;
+            params.set_language(language);
+
+            params.set_translate(translate);
+            params.set_print_special(false);
+            params.set_print_progress(false);
+            params.set_print_realtime(false);
+            params.set_print_timestamps(false);
+            params.set_token_timestamps(word_timestamps);
+            params.set_split_on_word(true);
+            // Speakers can only be told apart between segments
+            params.set_single_segment(diarization.is_none());
+            params.set_tdrz_enable(diarization == Some(Diarization::SpeakerTurns));
+            if filter.is_some() {
+                // The filter decides about falling back, not whisper.cpp
+                params.set_temperature(temperature);
+                params.set_temperature_inc(0.0);
+            }
 
-            for t in 0..num_tokens {
+            // TODO: Uncomment when I can figure out how to fix crashing bug
+            // trace!("Adding Callbacks");
+            // if let Some(closure) = callbacks.abort {
+            //     params.set_abort_callback_safe(closure);
+            // }
+            // if let Some(closure) = callbacks.progress {
+            //     params.set_progress_callback_safe(closure);
+            // }
+            // if let Some(closure) = callbacks.new_segment_lossy {
+            //     params.set_segment_callback_safe_lossy(closure);
+            // }
+            // if let Some(closure) = callbacks.new_segment {
+            //     params.set_segment_callback_safe(closure);
+            // }
+
+            trace!("Basic params for Whisper Set");
+
+            trace!("Using {threads} threads");
+
+            params.set_n_threads(threads);
+
+            trace!("Transcribing audio with WhisperState at temperature {temperature}");
+            state.full(params, audio).map_err(|e| {
+                error!("Failed to transcribe");
+                ModelError::WhisperError(e)
+            })?;
+
+            let num_segments = state.full_n_segments().map_err(|e| {
+                error!("Failed to get segments");
+                ModelError::WhisperError(e)
+            })?;
+            trace!("Number of segments: {num_segments}");
+
+            let mut segments = Vec::new();
+            let mut verdicts = Vec::new();
+            let mut speaker_turns = 0;
+            for segment_idx in 0..num_segments {
                 let text = state
-                    .full_get_token_text(segment_idx, t)
+                    .full_get_segment_text(segment_idx)
+                    .map_err(ModelError::WhisperError)?;
+                let start = state
+                    .full_get_segment_t0(segment_idx)
+                    .map_err(ModelError::WhisperError)?;
+                let stop = state
+                    .full_get_segment_t1(segment_idx)
                     .map_err(ModelError::WhisperError)?;
-                let token_data = state
-                    .full_get_token_data(segment_idx, t)
+
+                let speaker = (diarization == Some(Diarization::SpeakerTurns))
+                    .then_some(speaker_turns % 2 + 1);
+                if state.full_get_segment_speaker_turn_next(segment_idx) {
+                    speaker_turns += 1;
+                }
+
+                trace!("Getting tokens for segment {segment_idx}");
+
+                let num_tokens = state
+                    .full_n_tokens(segment_idx)
                     .map_err(ModelError::WhisperError)?;
 
-                if text.starts_with("[_") {
-                    continue;
+                let mut tokens = Vec::new();
+                let mut logprobs = Vec::new();
+                for t in 0..num_tokens {
+                    let text = state
+                        .full_get_token_text(segment_idx, t)
+                        .map_err(ModelError::WhisperError)?;
+                    let token_data = state
+                        .full_get_token_data(segment_idx, t)
+                        .map_err(ModelError::WhisperError)?;
+
+                    if text.starts_with("[_") {
+                        continue;
+                    }
+
+                    logprobs.push(token_data.plog);
+                    tokens.push(Token {
+                        text,
+                        start: token_data.t0,
+                        stop: token_data.t1,
+                        probability: token_data.p,
+                    });
                 }
+                let confidence = mean_confidence(tokens.iter().map(|token| token.probability));
+
+                let verdict = match &filter {
+                    Some(filter) => {
+                        #[allow(clippy::cast_precision_loss, reason = "Segments are short")]
+                        let average_logprob =
+                            logprobs.iter().sum::<f32>() / logprobs.len().max(1) as f32;
+                        let no_speech_probability = state
+                            .full_get_segment_no_speech_prob(segment_idx)
+                            .map_err(ModelError::WhisperError)?;
+                        filter.judge(
+                            &text,
+                            SegmentStatistics {
+                                no_speech_probability,
+                                average_logprob,
+                            },
+                        )
+                    }
+                    None => None,
+                };
+                verdicts.push(verdict);
+                segments.push((
+                    Utterance {
+                        start,
+                        stop,
+                        text,
+                        speaker,
+                        hallucination: verdict,
+                        confidence,
+                        alternatives: Vec::new(),
+                        source: None,
+                    },
+                    if word_timestamps {
+                        group_words(tokens)
+                    } else {
+                        Vec::new()
+                    },
+                ));
+            }
+
+            attempt += 1;
+            if attempt == temperatures.len() || !HallucinationFilter::needs_fallback(&verdicts) {
+                break (segments, verdicts);
+            }
+            debug!("Segments look hallucinated at temperature {temperature}, decoding again");
+        };
 
-                words.push(Utterance {
-                    text,
-                    start: token_data.t0,
-                    stop: token_data.t1,
-                });
+        let whisper_detection = if detection.is_none() && should_detect(language) {
+            Some(detected_language(&state)?)
+        } else {
+            None
+        };
+
+        let mut words = Vec::new();
+        let mut utterances = Vec::new();
+        for ((utterance, segment_words), verdict) in segments.into_iter().zip(verdicts) {
+            if filter.as_ref().is_some_and(|filter| filter.drops(verdict)) {
+                continue;
             }
+            utterances.push(utterance);
+            words.extend(segment_words);
+        }
+        for index in 0..alternatives.unwrap_or(0).min(MAX_HYPOTHESES) {
+            let temperature = hypothesis_temperature(index);
+            trace!("Decoding hypothesis {index} at temperature {temperature}");
+            let hypothesis = self.decode_hypothesis(
+                audio,
+                translate,
+                initial_prompt,
+                language,
+                threads,
+                temperature,
+            )?;
+            attach_alternatives(&mut utterances, &hypothesis);
+        }
+        if let Some(Diarization::Clustering { max_speakers }) = diarization {
+            trace!("Clustering segments into at most {max_speakers} speakers");
+            assign_speakers(&mut utterances, audio, max_speakers);
         }
         let processing_time = Instant::now().duration_since(st);
         debug!("Finished transcription");
@@ -285,9 +463,117 @@ impl Model {
         Ok(Transcript {
             utterances,
             processing_time,
+            language: detection.or(whisper_detection),
             word_utterances: if word_timestamps { Some(words) } else { None },
         })
     }
+
+    /// Decode the audio again by sampling at the given temperature, for alternative texts.
+    /// # Errors
+    /// - [`ModelError`]
+    fn decode_hypothesis(
+        &self,
+        audio: &[f32],
+        translate: bool,
+        initial_prompt: Option<&str>,
+        language: Option<&str>,
+        threads: i32,
+        temperature: f32,
+    ) -> Result<Vec<Utterance>, ModelError> {
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        if let Some(prompt) = initial_prompt {
+            params.set_initial_prompt(prompt);
+        }
+        params.set_language(language);
+        params.set_translate(translate);
+        params.set_print_special(false);
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_timestamps(false);
+        params.set_temperature(temperature);
+        params.set_temperature_inc(0.0);
+        params.set_n_threads(threads);
+
+        let mut state = self
+            .context
+            .create_state()
+            .map_err(ModelError::WhisperError)?;
+        state
+            .full(params, audio)
+            .map_err(ModelError::WhisperError)?;
+        let num_segments = state.full_n_segments().map_err(ModelError::WhisperError)?;
+        (0..num_segments)
+            .map(|segment_idx| {
+                Ok(Utterance {
+                    start: state
+                        .full_get_segment_t0(segment_idx)
+                        .map_err(ModelError::WhisperError)?,
+                    stop: state
+                        .full_get_segment_t1(segment_idx)
+                        .map_err(ModelError::WhisperError)?,
+                    text: state
+                        .full_get_segment_text(segment_idx)
+                        .map_err(ModelError::WhisperError)?,
+                    speaker: None,
+                    hallucination: None,
+                    confidence: None,
+                    alternatives: Vec::new(),
+                    source: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Detect the spoken language from the first 30 seconds of 16 kHz mono audio, picking one
+    /// of the `allowed` languages.
+    ///
+    /// The mel spectrogram is computed in `state`, which can transcribe the audio afterwards.
+    /// # Errors
+    /// - [`ModelError::UnknownLanguages`] if none of the allowed languages is known to whisper
+    /// - [`ModelError::WhisperError`]
+    pub fn detect_language(
+        state: &mut WhisperState,
+        audio: &[f32],
+        allowed: &[String],
+        threads: i32,
+    ) -> Result<LanguageDetection, ModelError> {
+        let threads = usize::try_from(threads).unwrap_or(1);
+        state
+            .pcm_to_mel(audio, threads)
+            .map_err(ModelError::WhisperError)?;
+        let probabilities = state
+            .lang_detect(0, threads)
+            .map_err(ModelError::WhisperError)?;
+        let detection = rank_languages(
+            (0..).zip(probabilities).filter_map(|(id, probability)| {
+                whisper_rs::get_lang_str(id).map(|language| (language.to_string(), probability))
+            }),
+            Some(allowed),
+            DETECTED_CANDIDATES,
+        )
+        .ok_or_else(|| ModelError::UnknownLanguages(allowed.to_vec()))?;
+        debug!("Detected languages {:?}", detection.candidates);
+        Ok(detection)
+    }
+}
+
+/// Language whisper detected by itself while transcribing with `state`.
+///
+/// Whisper does not report the probabilities of this detection, so the only candidate is the
+/// detected language with an unknown probability of `0`.
+fn detected_language(state: &WhisperState) -> Result<LanguageDetection, ModelError> {
+    let id = state
+        .full_lang_id_from_state()
+        .map_err(ModelError::WhisperError)?;
+    let language = whisper_rs::get_lang_str(id).unwrap_or_default().to_string();
+    debug!("Whisper detected language {language}");
+    Ok(LanguageDetection {
+        candidates: vec![LanguageProbability {
+            language: language.clone(),
+            probability: 0.0,
+        }],
+        language,
+    })
 }
 /// Crate error that contains an enum of all possible errors related to the model.
 #[derive(Debug)]
@@ -301,6 +587,8 @@ pub enum ModelError {
     // IoError(std::io::Error),
     /// [`rodio::decoder::DecoderError`]. Error decoding audio.
     DecodingError(rodio::decoder::DecoderError),
+    /// None of the languages allowed for detection is known to whisper
+    UnknownLanguages(Vec<String>),
 }
 
 /// Decode a byte array of audio into a float array
@@ -0,0 +1,489 @@
+//! Sending the final text to the focused application, by typing or through the clipboard.
+
+use crate::types::{FileEntryFormat, OutputMethod, OutputOptions, OutputTarget};
+use enigo::{Direction, Enigo, Key, Keyboard, Settings};
+use log::{debug, error, trace, warn};
+use std::{
+    fmt::Display,
+    fs::OpenOptions,
+    io::Write,
+    process::{Command, Stdio},
+    time::Duration,
+};
+use tauri::AppHandle;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+/// Default time to wait after pasting before the previous clipboard is restored.
+///
+/// The target application reads the clipboard asynchronously, restoring too early pastes the old text.
+const DEFAULT_RESTORE_DELAY_MILLIS: u32 = 200;
+
+#[derive(Debug)]
+/// Error from sending text to an application
+pub enum OutputError {
+    /// No input backend is available (e.g. no display server or missing permission)
+    NoBackend(String),
+    /// Sending keys to the system failed
+    Input(String),
+    /// Reading or writing the clipboard failed
+    Clipboard(String),
+    /// The action is not supported on this system
+    Unsupported(String),
+    /// Writing to a file, pipe, socket or stdout failed
+    Io(String),
+    /// The output command failed to run or exited unsuccessfully
+    Command(String),
+}
+
+impl Display for OutputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoBackend(msg) => write!(f, "No input backend available: {msg}"),
+            Self::Input(msg) => write!(f, "Could not send input: {msg}"),
+            Self::Clipboard(msg) => write!(f, "Clipboard error: {msg}"),
+            Self::Unsupported(msg) => write!(f, "Unsupported action: {msg}"),
+            Self::Io(msg) => write!(f, "Could not write output: {msg}"),
+            Self::Command(msg) => write!(f, "Output command failed: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for OutputError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Keyboard shortcut used to paste
+pub enum PasteShortcut {
+    /// Ctrl+V (Cmd+V on macOS)
+    Standard,
+    /// Ctrl+Shift+V, used by most terminal emulators (Cmd+V on macOS)
+    Terminal,
+}
+
+/// Low-level actions needed to send text to the focused application.
+pub trait OutputSink {
+    /// Type the text as key presses
+    fn type_text(&mut self, text: &str) -> Result<(), OutputError>;
+    /// Press the paste shortcut
+    fn paste(&mut self, shortcut: PasteShortcut) -> Result<(), OutputError>;
+    /// Read the current clipboard text, `None` if it holds no text
+    fn read_clipboard(&mut self) -> Result<Option<String>, OutputError>;
+    /// Replace the clipboard with the given text
+    fn write_clipboard(&mut self, text: &str) -> Result<(), OutputError>;
+    /// Wait between actions
+    fn wait(&mut self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Send the text using the sink, following the given options.
+///
+/// # Errors
+///
+/// Returns the first [`OutputError`] from the sink.
+pub fn send_text(
+    sink: &mut impl OutputSink,
+    text: &str,
+    options: &OutputOptions,
+) -> Result<(), OutputError> {
+    let method = options.method.unwrap_or_default();
+    debug!("Sending {} bytes with {method:?}", text.len());
+    match method {
+        OutputMethod::Type => type_in_chunks(sink, text, options),
+        OutputMethod::Paste => {
+            sink.write_clipboard(text)?;
+            sink.paste(PasteShortcut::Standard)
+        }
+        OutputMethod::TerminalPaste => {
+            sink.write_clipboard(text)?;
+            sink.paste(PasteShortcut::Terminal)
+        }
+        OutputMethod::PasteAndRestore => {
+            let previous = sink.read_clipboard().unwrap_or_else(|err| {
+                warn!("Could not read clipboard, it will not be restored: {err}");
+                None
+            });
+            sink.write_clipboard(text)?;
+            let pasted = sink.paste(PasteShortcut::Standard);
+            if let Some(previous) = previous {
+                sink.wait(Duration::from_millis(u64::from(
+                    options
+                        .restore_delay_ms
+                        .unwrap_or(DEFAULT_RESTORE_DELAY_MILLIS),
+                )));
+                trace!("Restoring previous clipboard");
+                sink.write_clipboard(&previous)?;
+            }
+            pasted
+        }
+    }
+}
+
+/// Type the text in chunks, waiting the key delay between each chunk.
+///
+/// Without a chunk size, the text is typed in one go, or one character at a time if a key delay is given.
+fn type_in_chunks(
+    sink: &mut impl OutputSink,
+    text: &str,
+    options: &OutputOptions,
+) -> Result<(), OutputError> {
+    let delay = options
+        .key_delay_ms
+        .filter(|&ms| ms > 0)
+        .map(|ms| Duration::from_millis(u64::from(ms)));
+    let chunk_size = match (options.chunk_size.filter(|&size| size > 0), delay) {
+        (Some(size), _) => size as usize,
+        (None, Some(_)) => 1,
+        (None, None) => return sink.type_text(text),
+    };
+    let chars = text.chars().collect::<Vec<_>>();
+    trace!("Typing in chunks of {chunk_size} characters");
+    for (i, chunk) in chars.chunks(chunk_size).enumerate() {
+        if i > 0 {
+            if let Some(delay) = delay {
+                sink.wait(delay);
+            }
+        }
+        sink.type_text(&chunk.iter().collect::<String>())?;
+    }
+    Ok(())
+}
+
+/// Send the text to a target other than the focused application.
+///
+/// Runs blocking IO, so it should not be called on the async runtime.
+///
+/// # Errors
+///
+/// [`OutputError::Io`] or [`OutputError::Command`] if writing the text fails.
+/// [`OutputError::Unsupported`] for [`OutputTarget::FocusedApplication`], which goes through an [`OutputSink`].
+pub fn send_to_target(target: &OutputTarget, text: &str) -> Result<(), OutputError> {
+    let io_error = |err: std::io::Error| {
+        error!("Output IO error: {err}");
+        OutputError::Io(err.to_string())
+    };
+    match target {
+        OutputTarget::FocusedApplication => Err(OutputError::Unsupported(
+            "Focused application is not a direct output target".into(),
+        )),
+        OutputTarget::File { path, format } => {
+            debug!("Appending text to file {path}");
+            let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| {
+                    file.write_all(format_file_entry(text, *format, &timestamp).as_bytes())
+                })
+                .map_err(io_error)
+        }
+        OutputTarget::Stdout => {
+            let mut stdout = std::io::stdout().lock();
+            writeln!(stdout, "{text}")
+                .and_then(|()| stdout.flush())
+                .map_err(io_error)
+        }
+        OutputTarget::NamedPipe { path } => {
+            debug!("Writing text to pipe {path}");
+            let mut options = OpenOptions::new();
+            options.write(true);
+            // Opening a FIFO blocks until a reader is connected, unless opened non-blocking
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::custom_flags(&mut options, libc::O_NONBLOCK);
+            let mut pipe = options.open(path).map_err(|err| {
+                #[cfg(unix)]
+                if err.raw_os_error() == Some(libc::ENXIO) {
+                    return OutputError::Io(format!("Nothing is reading from the pipe {path}"));
+                }
+                io_error(err)
+            })?;
+            writeln!(pipe, "{text}").map_err(io_error)
+        }
+        #[cfg(unix)]
+        OutputTarget::UnixSocket { path } => {
+            debug!("Writing text to socket {path}");
+            std::os::unix::net::UnixStream::connect(path)
+                .and_then(|mut socket| writeln!(socket, "{text}"))
+                .map_err(io_error)
+        }
+        #[cfg(not(unix))]
+        OutputTarget::UnixSocket { .. } => Err(OutputError::Unsupported(
+            "Unix sockets on this system".into(),
+        )),
+        OutputTarget::Command { program, args } => run_output_command(program, args, text),
+    }
+}
+
+/// Run the command with the text given on its standard input, waiting for it to finish.
+fn run_output_command(program: &str, args: &[String], text: &str) -> Result<(), OutputError> {
+    debug!(
+        "Running output command {program} with {} arguments",
+        args.len()
+    );
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|err| OutputError::Command(format!("Could not start {program}: {err}")))?;
+    // Dropping stdin closes it, so the command sees the end of input
+    let written = child
+        .stdin
+        .take()
+        .map_or(Ok(()), |mut stdin| stdin.write_all(text.as_bytes()));
+    // Always wait, so the command does not stay behind as a zombie process
+    let status = child
+        .wait()
+        .map_err(|err| OutputError::Command(format!("Could not wait for {program}: {err}")))?;
+    written.map_err(|err| OutputError::Command(format!("Could not write to {program}: {err}")))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(OutputError::Command(format!(
+            "{program} exited with {status}"
+        )))
+    }
+}
+
+/// Format the text as an entry appended to a file, with the timestamp as header.
+fn format_file_entry(text: &str, format: FileEntryFormat, timestamp: &str) -> String {
+    match format {
+        FileEntryFormat::Text => format!("[{timestamp}]\n{}\n\n", text.trim()),
+        FileEntryFormat::Markdown => format!("## {timestamp}\n\n{}\n\n", text.trim()),
+    }
+}
+
+/// Output sink using the system keyboard (through Enigo) and the app's clipboard.
+pub struct SystemOutputSink<'a> {
+    enigo: Enigo,
+    app_handle: &'a AppHandle,
+}
+
+impl<'a> SystemOutputSink<'a> {
+    /// Connect to the system input backend.
+    ///
+    /// # Errors
+    ///
+    /// [`OutputError::NoBackend`] if the input backend cannot be created.
+    pub fn new(app_handle: &'a AppHandle) -> Result<Self, OutputError> {
+        let enigo = Enigo::new(&Settings::default()).map_err(|err| {
+            error!("Could not create input backend: {err}");
+            OutputError::NoBackend(err.to_string())
+        })?;
+        trace!("Enigo setup: {enigo:?}");
+        Ok(Self { enigo, app_handle })
+    }
+
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), OutputError> {
+        self.enigo.key(key, direction).map_err(|err| {
+            error!("Input error: {err}");
+            OutputError::Input(err.to_string())
+        })
+    }
+}
+
+/// Modifier keys used for the paste shortcut on the current system
+fn paste_modifiers(shortcut: PasteShortcut) -> Result<&'static [Key], OutputError> {
+    match (std::env::consts::OS, shortcut) {
+        ("macos", _) => Ok(&[Key::Meta]),
+        ("windows" | "linux", PasteShortcut::Standard) => Ok(&[Key::Control]),
+        ("windows" | "linux", PasteShortcut::Terminal) => Ok(&[Key::Control, Key::Shift]),
+        (os, _) => {
+            error!("Pasting from an unsupported/unknown target");
+            Err(OutputError::Unsupported(format!("Pasting on {os}")))
+        }
+    }
+}
+
+impl OutputSink for SystemOutputSink<'_> {
+    fn type_text(&mut self, text: &str) -> Result<(), OutputError> {
+        self.enigo
+            .text(text)
+            .map_err(|err| OutputError::Input(err.to_string()))?;
+        // Use len rather then actual text to prevent leaking info in logs
+        trace!("Enigo Wrote {} bytes", text.len());
+        Ok(())
+    }
+
+    fn paste(&mut self, shortcut: PasteShortcut) -> Result<(), OutputError> {
+        let modifiers = paste_modifiers(shortcut)?;
+        let mut pressed = 0;
+        let mut result = Ok(());
+        for modifier in modifiers {
+            result = self.key(*modifier, Direction::Press);
+            if result.is_err() {
+                break;
+            }
+            pressed += 1;
+        }
+        if result.is_ok() {
+            result = self.key(Key::Unicode('v'), Direction::Click);
+        }
+        // Always release what was pressed, otherwise modifiers stay stuck
+        for modifier in modifiers[..pressed].iter().rev() {
+            if let Err(err) = self.key(*modifier, Direction::Release) {
+                result = result.and(Err(err));
+            }
+        }
+        trace!("Enigo Pasted text");
+        result
+    }
+
+    fn read_clipboard(&mut self) -> Result<Option<String>, OutputError> {
+        // Plugin returns an error for an empty or non-text clipboard, which cannot be restored anyway
+        Ok(self
+            .app_handle
+            .clipboard()
+            .read_text()
+            .map_err(|err| debug!("No text in clipboard: {err}"))
+            .ok())
+    }
+
+    fn write_clipboard(&mut self, text: &str) -> Result<(), OutputError> {
+        self.app_handle
+            .clipboard()
+            .write_text(text)
+            .map_err(|err| OutputError::Clipboard(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Action recorded by [`MockOutputSink`]
+pub enum MockAction {
+    Type(String),
+    Paste(PasteShortcut),
+    Wait(Duration),
+}
+
+#[cfg(test)]
+#[derive(Debug, Default)]
+/// Output sink that records every action instead of sending it, for testing.
+pub struct MockOutputSink {
+    pub actions: Vec<MockAction>,
+    pub clipboard: Option<String>,
+    /// Clipboard content at the time of each paste
+    pub pasted: Vec<Option<String>>,
+}
+
+#[cfg(test)]
+impl OutputSink for MockOutputSink {
+    fn type_text(&mut self, text: &str) -> Result<(), OutputError> {
+        self.actions.push(MockAction::Type(text.to_string()));
+        Ok(())
+    }
+
+    fn paste(&mut self, shortcut: PasteShortcut) -> Result<(), OutputError> {
+        self.actions.push(MockAction::Paste(shortcut));
+        self.pasted.push(self.clipboard.clone());
+        Ok(())
+    }
+
+    fn read_clipboard(&mut self) -> Result<Option<String>, OutputError> {
+        Ok(self.clipboard.clone())
+    }
+
+    fn write_clipboard(&mut self, text: &str) -> Result<(), OutputError> {
+        self.clipboard = Some(text.to_string());
+        Ok(())
+    }
+
+    fn wait(&mut self, duration: Duration) {
+        self.actions.push(MockAction::Wait(duration));
+    }
+}
+
+#[test]
+fn test_paste_and_restore_keeps_previous_clipboard() {
+    let mut sink = MockOutputSink {
+        clipboard: Some("previous".into()),
+        ..Default::default()
+    };
+    let options = OutputOptions {
+        method: Some(OutputMethod::PasteAndRestore),
+        ..Default::default()
+    };
+    send_text(&mut sink, "dictated", &options).unwrap();
+    assert_eq!(sink.pasted, vec![Some("dictated".to_string())]);
+    assert_eq!(sink.clipboard.as_deref(), Some("previous"));
+}
+
+#[test]
+fn test_terminal_paste_uses_terminal_shortcut() {
+    let mut sink = MockOutputSink::default();
+    let options = OutputOptions {
+        method: Some(OutputMethod::TerminalPaste),
+        ..Default::default()
+    };
+    send_text(&mut sink, "ls", &options).unwrap();
+    assert_eq!(
+        sink.actions,
+        vec![MockAction::Paste(PasteShortcut::Terminal)]
+    );
+}
+
+#[test]
+fn test_type_with_chunks_and_delay() {
+    let mut sink = MockOutputSink::default();
+    let options = OutputOptions {
+        method: Some(OutputMethod::Type),
+        key_delay_ms: Some(5),
+        chunk_size: Some(3),
+        ..Default::default()
+    };
+    send_text(&mut sink, "abcdefg", &options).unwrap();
+    let delay = MockAction::Wait(Duration::from_millis(5));
+    assert_eq!(
+        sink.actions,
+        vec![
+            MockAction::Type("abc".into()),
+            delay.clone(),
+            MockAction::Type("def".into()),
+            delay,
+            MockAction::Type("g".into()),
+        ]
+    );
+}
+
+#[test]
+fn test_format_file_entry() {
+    assert_eq!(
+        format_file_entry(" Hello. ", FileEntryFormat::Text, "2025-01-02 03:04:05"),
+        "[2025-01-02 03:04:05]\nHello.\n\n"
+    );
+    assert_eq!(
+        format_file_entry("Hello.", FileEntryFormat::Markdown, "2025-01-02 03:04:05"),
+        "## 2025-01-02 03:04:05\n\nHello.\n\n"
+    );
+}
+
+#[test]
+fn test_file_target_appends_entries() {
+    let path =
+        std::env::temp_dir().join(format!("super-mouse-ai-output-{}.md", std::process::id()));
+    let target = OutputTarget::File {
+        path: path.to_string_lossy().into_owned(),
+        format: FileEntryFormat::Markdown,
+    };
+    send_to_target(&target, "First").unwrap();
+    send_to_target(&target, "Second").unwrap();
+    let content = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(content.matches("## ").count(), 2);
+    assert!(content.find("First") < content.find("Second"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pipe_target_without_reader_fails() {
+    let path =
+        std::env::temp_dir().join(format!("super-mouse-ai-output-{}.fifo", std::process::id()));
+    let status = Command::new("mkfifo").arg(&path).status().unwrap();
+    assert!(status.success());
+    let target = OutputTarget::NamedPipe {
+        path: path.to_string_lossy().into_owned(),
+    };
+    let result = send_to_target(&target, "Nobody listens");
+    let _ = std::fs::remove_file(&path);
+    assert!(matches!(result, Err(OutputError::Io(message)) if message.contains("Nothing")));
+}
@@ -0,0 +1,418 @@
+//! Per-application profiles, selected based on the focused window when dictation starts.
+
+use crate::types::{
+    OutputMethod, OutputTarget, TextPostProcessing, TextProcessOptions, TranscribeOptions,
+};
+use log::{debug, trace};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize, Type)]
+/// Information about the currently focused window
+pub struct WindowInfo {
+    /// Name of the process that owns the window (e.g. `code`, `firefox`)
+    pub process_name: Option<String>,
+    /// Title of the window
+    pub title: Option<String>,
+}
+
+/// Something that can tell which window currently has focus.
+pub trait WindowDetector: Send + Sync {
+    /// Get the currently focused window, `None` if it cannot be detected.
+    fn active_window(&self) -> Option<WindowInfo>;
+}
+
+#[cfg(target_os = "linux")]
+/// Window detection for X11 using the EWMH `_NET_ACTIVE_WINDOW` property.
+///
+/// The connection is kept open between calls, and opened again after it failed.
+/// Under Wayland this only sees XWayland windows.
+#[derive(Default)]
+pub struct X11WindowDetector {
+    /// Connection and root window of its default screen
+    connection: Mutex<Option<(x11rb::rust_connection::RustConnection, u32)>>,
+}
+
+#[cfg(target_os = "linux")]
+impl X11WindowDetector {
+    fn read_property(
+        conn: &x11rb::rust_connection::RustConnection,
+        window: u32,
+        name: &[u8],
+        kind: impl Into<u32>,
+    ) -> Option<x11rb::protocol::xproto::GetPropertyReply> {
+        use x11rb::protocol::xproto::ConnectionExt;
+        let atom = conn.intern_atom(false, name).ok()?.reply().ok()?.atom;
+        conn.get_property(false, window, atom, kind, 0, u32::MAX)
+            .ok()?
+            .reply()
+            .ok()
+    }
+
+    fn connect() -> Option<(x11rb::rust_connection::RustConnection, u32)> {
+        use x11rb::connection::Connection;
+        let (conn, screen_num) = x11rb::connect(None)
+            .map_err(|err| debug!("Could not connect to X11 server: {err}"))
+            .ok()?;
+        let root = conn.setup().roots.get(screen_num)?.root;
+        Some((conn, root))
+    }
+
+    fn query(conn: &x11rb::rust_connection::RustConnection, root: u32) -> Option<WindowInfo> {
+        use x11rb::protocol::xproto::{AtomEnum, ConnectionExt};
+        let window = Self::read_property(conn, root, b"_NET_ACTIVE_WINDOW", AtomEnum::WINDOW)?
+            .value32()?
+            .next()?;
+        trace!("Active X11 window: {window:#x}");
+        let utf8_string = conn
+            .intern_atom(false, b"UTF8_STRING")
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .map(|reply| reply.atom);
+        let title = utf8_string
+            .and_then(|utf8| Self::read_property(conn, window, b"_NET_WM_NAME", utf8))
+            .filter(|reply| !reply.value.is_empty())
+            .or_else(|| Self::read_property(conn, window, b"WM_NAME", AtomEnum::STRING))
+            .map(|reply| String::from_utf8_lossy(&reply.value).into_owned());
+        let process_name = Self::read_property(conn, window, b"_NET_WM_PID", AtomEnum::CARDINAL)
+            .and_then(|reply| reply.value32().and_then(|mut pid| pid.next()))
+            .and_then(|pid| std::fs::read_to_string(format!("/proc/{pid}/comm")).ok())
+            .map(|name| name.trim().to_string());
+        Some(WindowInfo {
+            process_name,
+            title,
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl WindowDetector for X11WindowDetector {
+    fn active_window(&self) -> Option<WindowInfo> {
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|err| debug!("Could not get X11 connection lock: {err}"))
+            .ok()?;
+        if connection.is_none() {
+            *connection = Self::connect();
+        }
+        let (conn, root) = connection.as_ref()?;
+        let window = Self::query(conn, *root);
+        if window.is_none() {
+            // Could be a closed connection, so open a new one next time
+            *connection = None;
+        }
+        window
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+/// Window detector for platforms where detection is not supported yet.
+pub struct UnsupportedWindowDetector;
+
+#[cfg(not(target_os = "linux"))]
+impl WindowDetector for UnsupportedWindowDetector {
+    fn active_window(&self) -> Option<WindowInfo> {
+        None
+    }
+}
+
+#[cfg(test)]
+/// Window detector that always returns the same window, for testing.
+pub struct MockWindowDetector(pub Option<WindowInfo>);
+
+#[cfg(test)]
+impl WindowDetector for MockWindowDetector {
+    fn active_window(&self) -> Option<WindowInfo> {
+        self.0.clone()
+    }
+}
+
+/// Create the window detector for the current platform
+pub fn default_window_detector() -> Arc<dyn WindowDetector> {
+    #[cfg(target_os = "linux")]
+    {
+        Arc::new(X11WindowDetector::default())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        Arc::new(UnsupportedWindowDetector)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize, Type)]
+/// Rule deciding whether a window belongs to a profile.
+///
+/// Every given item must match, comparison ignores case.
+pub struct ProfileMatcher {
+    /// Process name must be exactly this value
+    pub process_name: Option<String>,
+    /// Window title must contain this value
+    pub title_contains: Option<String>,
+}
+
+impl ProfileMatcher {
+    /// Check if the given window matches. A matcher without rules never matches.
+    pub fn matches(&self, window: &WindowInfo) -> bool {
+        if self.process_name.is_none() && self.title_contains.is_none() {
+            return false;
+        }
+        let process_matches = self.process_name.as_ref().is_none_or(|expected| {
+            window
+                .process_name
+                .as_ref()
+                .is_some_and(|name| name.eq_ignore_ascii_case(expected))
+        });
+        let title_matches = self.title_contains.as_ref().is_none_or(|expected| {
+            window
+                .title
+                .as_ref()
+                .is_some_and(|title| title.to_lowercase().contains(&expected.to_lowercase()))
+        });
+        process_matches && title_matches
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize, Type)]
+/// Settings that replace the global ones when dictating into a matching window.
+pub struct ApplicationProfile {
+    /// Display name of the profile
+    pub name: String,
+    /// Which windows use this profile
+    pub matcher: ProfileMatcher,
+    /// Transcription options, each given item overrides the global value
+    pub transcribe_options: Option<TranscribeOptions>,
+    /// Text post-processing options, replaces the global options if given
+    pub processing_options: Option<TextProcessOptions>,
+    /// How the final text is sent to the application
+    pub output_method: Option<OutputMethod>,
+    /// Where the final text is sent, the focused application if `None`
+    #[serde(default)]
+    pub output_target: Option<OutputTarget>,
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize, Type)]
+/// The focused window and matching profile at the start of a dictation
+pub struct ActiveProfile {
+    pub window: Option<WindowInfo>,
+    pub profile: Option<ApplicationProfile>,
+}
+
+impl ActiveProfile {
+    /// Apply the transcription overrides of the profile to the given options.
+    ///
+    /// Also fills in the target application from the window when not given.
+    pub fn transcribe_options(&self, options: TranscribeOptions) -> TranscribeOptions {
+        let mut options = match self
+            .profile
+            .as_ref()
+            .and_then(|profile| profile.transcribe_options.as_ref())
+        {
+            Some(overrides) => {
+                debug!("Using transcription options from active profile");
+                options.overridden_by(overrides)
+            }
+            None => options,
+        };
+        if options.target_application.is_none() {
+            options.target_application = self
+                .window
+                .as_ref()
+                .and_then(|window| window.process_name.clone());
+        }
+        options
+    }
+
+    /// The text post-processing of the profile, or the given one if it has none
+    pub fn processing_options(&self, processing: TextPostProcessing) -> TextPostProcessing {
+        self.profile
+            .as_ref()
+            .and_then(|profile| profile.processing_options.clone())
+            .map_or(processing, TextPostProcessing::Custom)
+    }
+}
+
+/// State of the application profiles
+pub struct InnerProfileState {
+    profiles: Vec<ApplicationProfile>,
+    detector: Arc<dyn WindowDetector>,
+    active: ActiveProfile,
+}
+
+impl InnerProfileState {
+    pub fn new() -> Self {
+        Self::with_detector(default_window_detector())
+    }
+
+    pub fn with_detector(detector: Arc<dyn WindowDetector>) -> Self {
+        Self {
+            profiles: Vec::new(),
+            detector,
+            active: ActiveProfile::default(),
+        }
+    }
+
+    pub fn profiles(&self) -> &[ApplicationProfile] {
+        &self.profiles
+    }
+
+    pub fn set_profiles(&mut self, profiles: Vec<ApplicationProfile>) {
+        debug!("Replacing {} application profiles", profiles.len());
+        self.profiles = profiles;
+    }
+
+    /// Find the first profile matching the given window
+    pub fn find_profile(&self, window: &WindowInfo) -> Option<&ApplicationProfile> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.matcher.matches(window))
+    }
+
+    /// Get the window detector, to detect the focused window without holding the state lock
+    pub fn detector(&self) -> Arc<dyn WindowDetector> {
+        Arc::clone(&self.detector)
+    }
+
+    /// Remember the profile of the focused window as active, returning it.
+    pub fn activate(&mut self, window: Option<WindowInfo>) -> &ActiveProfile {
+        trace!("Focused window: {window:?}");
+        let profile = window
+            .as_ref()
+            .and_then(|window| self.find_profile(window))
+            .cloned();
+        debug!(
+            "Active profile: {:?}",
+            profile.as_ref().map(|profile| &profile.name)
+        );
+        self.active = ActiveProfile { window, profile };
+        &self.active
+    }
+
+    /// The profile selected when the current dictation started
+    pub const fn active(&self) -> &ActiveProfile {
+        &self.active
+    }
+}
+
+pub type ProfileState = Mutex<InnerProfileState>;
+
+#[test]
+fn test_profile_matches_process_name_or_title() {
+    let mut state = InnerProfileState::with_detector(Arc::new(MockWindowDetector(None)));
+    state.set_profiles(vec![
+        ApplicationProfile {
+            name: "Editor".into(),
+            matcher: ProfileMatcher {
+                process_name: Some("code".into()),
+                title_contains: None,
+            },
+            output_method: Some(OutputMethod::Type),
+            ..Default::default()
+        },
+        ApplicationProfile {
+            name: "Mail".into(),
+            matcher: ProfileMatcher {
+                process_name: None,
+                title_contains: Some("inbox".into()),
+            },
+            ..Default::default()
+        },
+    ]);
+    let editor = state.activate(Some(WindowInfo {
+        process_name: Some("Code".into()),
+        title: Some("main.rs".into()),
+    }));
+    assert_eq!(
+        editor.profile.as_ref().map(|profile| profile.name.as_str()),
+        Some("Editor")
+    );
+    let mail = state.activate(Some(WindowInfo {
+        process_name: Some("firefox".into()),
+        title: Some("Inbox (3) - Mail".into()),
+    }));
+    assert_eq!(
+        mail.profile.as_ref().map(|profile| profile.name.as_str()),
+        Some("Mail")
+    );
+    let terminal = state.activate(Some(WindowInfo {
+        process_name: Some("terminal".into()),
+        title: None,
+    }));
+    assert!(terminal.profile.is_none());
+    assert!(state.activate(None).profile.is_none());
+    assert!(!ProfileMatcher::default().matches(&WindowInfo::default()));
+}
+
+#[test]
+fn test_detected_window_applies_profile_options() {
+    let editor = WindowInfo {
+        process_name: Some("code".into()),
+        title: Some("main.rs - project".into()),
+    };
+    let mut state = InnerProfileState::with_detector(Arc::new(MockWindowDetector(Some(editor))));
+    state.set_profiles(vec![ApplicationProfile {
+        name: "Editor".into(),
+        matcher: ProfileMatcher {
+            process_name: Some("code".into()),
+            title_contains: None,
+        },
+        transcribe_options: Some(TranscribeOptions {
+            initial_prompt: Some("fn, struct, impl".into()),
+            beam_size: Some(5),
+            ..Default::default()
+        }),
+        processing_options: Some(TextProcessOptions {
+            replace_inter_sentence_newlines: Some(true),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }]);
+    let defaults = TranscribeOptions {
+        initial_prompt: Some("Hello.".into()),
+        threads: Some(4),
+        ..Default::default()
+    };
+
+    let window = state.detector().active_window();
+    let active = state.activate(window).clone();
+    let options = active.transcribe_options(defaults.clone());
+    assert_eq!(options.initial_prompt.as_deref(), Some("fn, struct, impl"));
+    assert_eq!(options.beam_size, Some(5));
+    assert_eq!(options.threads, Some(4));
+    assert_eq!(options.target_application.as_deref(), Some("code"));
+    assert_eq!(
+        active.processing_options(TextPostProcessing::Default),
+        TextPostProcessing::Custom(TextProcessOptions {
+            replace_inter_sentence_newlines: Some(true),
+            ..Default::default()
+        })
+    );
+
+    let browser = WindowInfo {
+        process_name: Some("firefox".into()),
+        title: Some("Search".into()),
+    };
+    let mut state = InnerProfileState::with_detector(Arc::new(MockWindowDetector(Some(browser))));
+    state.set_profiles(vec![ApplicationProfile {
+        matcher: ProfileMatcher {
+            process_name: Some("code".into()),
+            title_contains: None,
+        },
+        ..Default::default()
+    }]);
+    let window = state.detector().active_window();
+    let active = state.activate(window).clone();
+    assert!(active.profile.is_none());
+    assert_eq!(
+        active.transcribe_options(defaults.clone()),
+        TranscribeOptions {
+            target_application: Some("firefox".into()),
+            ..defaults
+        }
+    );
+    assert_eq!(
+        active.processing_options(TextPostProcessing::Default),
+        TextPostProcessing::Default
+    );
+}
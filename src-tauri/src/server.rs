@@ -0,0 +1,764 @@
+//! Opt-in local HTTP/WebSocket API, letting other tools use the already loaded Whisper model.
+//!
+//! The server only binds to `127.0.0.1` and every request must carry the token,
+//! either as `Authorization: Bearer <token>` or as a `token` query parameter (for WebSockets).
+//! The `/v1/audio/transcriptions` endpoint follows the OpenAI request and response shape.
+
+use crate::{
+    hallucination::HallucinationReason,
+    language::LanguageDetection,
+    mutter::{directly_denoise, TranscribeCallbacks},
+    settings,
+    transcript::Transcript,
+    types::{AppState, AudioProcessingOptions, TranscribeOptions, TranscriptionFormat},
+};
+use axum::{
+    body::Bytes,
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket},
+        DefaultBodyLimit, Multipart, Query, Request, State, WebSocketUpgrade,
+    },
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use log::{debug, error, info, trace, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use std::{net::Ipv4Addr, sync::Mutex};
+use tauri::{AppHandle, Manager};
+use tokio::sync::oneshot;
+
+/// Default port of the local API
+pub const DEFAULT_API_PORT: u16 = 7723;
+
+/// Largest accepted upload, about an hour of 16-bit 48kHz stereo WAV
+const MAX_UPLOAD_BYTES: usize = 700 * 1024 * 1024;
+
+/// Sample rate assumed for streamed audio when not given
+const DEFAULT_STREAM_SAMPLE_RATE: u32 = 16_000;
+
+/// Seconds of new streamed audio before a partial transcript is sent
+const STREAM_PARTIAL_SECONDS: u32 = 5;
+
+/// Seconds of the most recent streamed audio transcribed for a partial transcript
+const STREAM_WINDOW_SECONDS: u32 = 30;
+
+/// Highest accepted sample rate of streamed audio
+const MAX_STREAM_SAMPLE_RATE: u32 = 192_000;
+
+/// Most samples accepted from one stream, the same amount of data as the largest upload
+const MAX_STREAM_SAMPLES: usize = MAX_UPLOAD_BYTES / size_of::<f32>();
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// Information about the running API server
+pub struct ApiServerInfo {
+    /// Port the server listens on (always on `127.0.0.1`)
+    pub port: u16,
+    /// Token clients must send with each request
+    pub token: String,
+}
+
+/// State of the local API server
+pub struct InnerApiServerState {
+    shutdown: Option<oneshot::Sender<()>>,
+    info: Option<ApiServerInfo>,
+}
+
+impl InnerApiServerState {
+    pub const fn new() -> Self {
+        Self {
+            shutdown: None,
+            info: None,
+        }
+    }
+
+    /// Information about the server, `None` if it is not running
+    pub const fn info(&self) -> Option<&ApiServerInfo> {
+        self.info.as_ref()
+    }
+
+    /// Stop the server if it is running, returns whether it was running
+    pub fn stop(&mut self) -> bool {
+        self.info = None;
+        self.shutdown.take().is_some_and(|shutdown| {
+            info!("Stopping local API server");
+            shutdown.send(()).is_ok()
+        })
+    }
+}
+
+pub type ApiServerState = Mutex<InnerApiServerState>;
+
+/// Start the local API server on a background task, replacing any running one.
+///
+/// A random token is generated when none is given.
+///
+/// # Errors
+///
+/// Returns an error if the port cannot be bound.
+pub async fn start(
+    app_handle: AppHandle,
+    port: Option<u16>,
+    token: Option<String>,
+) -> Result<ApiServerInfo, String> {
+    let port = port.unwrap_or(DEFAULT_API_PORT);
+    let token = token
+        .filter(|token| !token.is_empty())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+    // Stop old server first, so the port is free when reusing it
+    let _ = app_handle
+        .state::<ApiServerState>()
+        .lock()
+        .map(|mut server| server.stop())
+        .map_err(|err| error!("Could not get API server state lock: {err}"));
+    let listener = tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .await
+        .map_err(|err| format!("Could not bind local API to port {port}: {err}"))?;
+    let port = listener.local_addr().map_or(port, |addr| addr.port());
+    let info = ApiServerInfo { port, token };
+    let router = router(ApiContext {
+        app_handle: app_handle.clone(),
+        token: info.token.clone(),
+    });
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    tauri::async_runtime::spawn(async move {
+        info!("Local API listening on 127.0.0.1:{port}");
+        if let Err(err) = axum::serve(listener, router)
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            })
+            .await
+        {
+            error!("Local API server error: {err}");
+        }
+        info!("Local API server stopped");
+    });
+    let mut server = app_handle
+        .state::<ApiServerState>()
+        .lock()
+        .map_err(|err| err.to_string())?;
+    server.shutdown = Some(shutdown_tx);
+    server.info = Some(info.clone());
+    drop(server);
+    Ok(info)
+}
+
+#[derive(Clone)]
+/// Shared data for every request handler
+struct ApiContext {
+    app_handle: AppHandle,
+    token: String,
+}
+
+fn router(context: ApiContext) -> Router {
+    let token = context.token.clone();
+    with_token(
+        Router::new()
+            .route("/v1/status", get(status))
+            .route("/v1/models", get(models))
+            .route("/v1/audio/transcriptions", post(openai_transcription))
+            .route("/v1/transcribe/bytes", post(transcribe_bytes))
+            .route("/v1/transcribe/file", post(transcribe_file))
+            .route("/v1/transcribe/stream", get(transcribe_stream))
+            .layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES))
+            .with_state(context),
+        token,
+    )
+}
+
+/// Only let requests with the given token through to the routes
+fn with_token(routes: Router, token: String) -> Router {
+    routes.layer(middleware::from_fn_with_state(token, require_token))
+}
+
+/// Compare without exiting early, so the token cannot be guessed from response times
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Decode a percent-encoded query value, `None` if it is not valid UTF-8 or has a bad escape
+fn percent_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'%' => {
+                let hex = std::str::from_utf8(rest.get(..2)?).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                rest = &rest[2..];
+            }
+            b'+' => bytes.push(b' '),
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+async fn require_token(State(expected): State<String>, request: Request, next: Next) -> Response {
+    let header_token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let query_token = request.uri().query().and_then(|query| {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("token="))
+            .and_then(percent_decode)
+    });
+    if header_token
+        .or(query_token.as_deref())
+        .is_some_and(|token| token_matches(token, &expected))
+    {
+        next.run(request).await
+    } else {
+        warn!("Rejected local API request without valid token");
+        ApiError::Unauthorized.into_response()
+    }
+}
+
+#[derive(Debug)]
+/// Error returned to API clients, in the OpenAI error shape
+enum ApiError {
+    Unauthorized,
+    BadRequest(String),
+    Transcription(String),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, kind, message) = match self {
+            Self::Unauthorized => (
+                StatusCode::UNAUTHORIZED,
+                "authentication_error",
+                "Missing or invalid token".to_string(),
+            ),
+            Self::BadRequest(message) => {
+                (StatusCode::BAD_REQUEST, "invalid_request_error", message)
+            }
+            Self::Transcription(message) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "server_error", message)
+            }
+        };
+        (
+            status,
+            Json(json!({ "error": { "message": message, "type": kind } })),
+        )
+            .into_response()
+    }
+}
+
+async fn status(State(context): State<ApiContext>) -> Response {
+    let state = context.app_handle.state::<AppState>();
+    // Transcriptions hold the lock, so a failed `try_lock` means the model is busy
+    let (model, busy) = state.try_lock().map_or_else(
+        |_| (None, true),
+        |app_state| (Some(app_state.get_model_info()), false),
+    );
+    Json(json!({
+        "version": context.app_handle.package_info().version.to_string(),
+        "model": model,
+        "busy": busy,
+    }))
+    .into_response()
+}
+
+async fn models(State(context): State<ApiContext>) -> Response {
+    let owned_by = context.app_handle.package_info().name.clone();
+    Json(json!({
+        "object": "list",
+        "data": [{ "id": "whisper-1", "object": "model", "owned_by": owned_by }],
+    }))
+    .into_response()
+}
+
+#[derive(Debug, Serialize)]
+/// Response of the non-OpenAI transcription endpoints
+struct TranscriptionResponse {
+    text: String,
+    processing_time: f64,
+    /// Detected language, if it was not given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<LanguageDetection>,
+    segments: Vec<SegmentResponse>,
+}
+
+#[derive(Debug, Serialize)]
+/// A transcribed segment, times are in seconds
+struct SegmentResponse {
+    id: usize,
+    start: f64,
+    end: f64,
+    text: String,
+    /// Speaker number starting at 1, if diarized
+    #[serde(skip_serializing_if = "Option::is_none")]
+    speaker: Option<u32>,
+    /// Why the segment looks made up, if flagged by the hallucination filter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hallucination: Option<HallucinationReason>,
+    /// Probability between 0 and 1 that the text is right
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confidence: Option<f32>,
+    /// Other texts considered for the segment, if alternatives were requested
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    alternatives: Vec<String>,
+}
+
+impl TranscriptionResponse {
+    fn new(transcript: &Transcript, format: TranscriptionFormat) -> Self {
+        Self {
+            text: format.convert_transcript(transcript).trim().to_string(),
+            processing_time: transcript.processing_time.as_secs_f64(),
+            language: transcript.language.clone(),
+            segments: segments_of(transcript),
+        }
+    }
+}
+
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "Unlikely that user gives a audio file greater than 2^51 ms"
+)]
+fn segments_of(transcript: &Transcript) -> Vec<SegmentResponse> {
+    transcript
+        .utterances
+        .iter()
+        .enumerate()
+        .map(|(id, utterance)| SegmentResponse {
+            id,
+            // Timestamps are given in hundredths of a second
+            start: utterance.start as f64 / 100.0,
+            end: utterance.stop as f64 / 100.0,
+            text: utterance.text.trim().to_string(),
+            speaker: utterance.speaker,
+            hallucination: utterance.hallucination,
+            confidence: utterance.confidence,
+            alternatives: utterance.alternatives.clone(),
+        })
+        .collect()
+}
+
+/// Transcribe WAV bytes with the app's current model, on a blocking thread.
+///
+/// Options not given in the request are taken from the settings.
+async fn transcribe_wav(
+    app_handle: AppHandle,
+    audio: Vec<u8>,
+    options: TranscribeOptions,
+    decode_options: Option<AudioProcessingOptions>,
+) -> Result<Transcript, ApiError> {
+    let settings = settings::current(&app_handle);
+    let options = settings.transcribe_options(Some(options));
+    let decode_options = settings.decode_options(decode_options);
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        let app_state = state
+            .lock()
+            .map_err(|err| ApiError::Transcription(err.to_string()))?;
+        debug!("Local API transcribing with {}", app_state.get_model_info());
+        app_state
+            .get_model()
+            .transcribe_audio(
+                &audio,
+                &options,
+                decode_options,
+                TranscribeCallbacks::default(),
+            )
+            .map_err(|err| ApiError::Transcription(format!("{err:?}")))
+    })
+    .await
+    .map_err(|err| ApiError::Transcription(err.to_string()))?
+}
+
+/// Transcribe raw samples with the app's current model, on a blocking thread.
+///
+/// Options not given in the request are taken from the settings.
+async fn transcribe_samples(
+    app_handle: AppHandle,
+    samples: Vec<f32>,
+    sample_rate: u32,
+    options: TranscribeOptions,
+    decode_options: Option<AudioProcessingOptions>,
+) -> Result<Transcript, ApiError> {
+    let settings = settings::current(&app_handle);
+    let options = settings.transcribe_options(Some(options));
+    let decode_options = settings.decode_options(decode_options);
+    tauri::async_runtime::spawn_blocking(move || {
+        let processed = directly_denoise(samples, 1, sample_rate, decode_options)
+            .map_err(|err| ApiError::Transcription(format!("{err:?}")))?;
+        let state = app_handle.state::<AppState>();
+        let app_state = state
+            .lock()
+            .map_err(|err| ApiError::Transcription(err.to_string()))?;
+        app_state
+            .get_model()
+            .transcribe_pcm_s16le(&processed, &options, TranscribeCallbacks::default())
+            .map_err(|err| ApiError::Transcription(format!("{err:?}")))
+    })
+    .await
+    .map_err(|err| ApiError::Transcription(err.to_string()))?
+}
+
+/// `POST /v1/audio/transcriptions`, multipart form following the OpenAI API.
+///
+/// Supports the `file`, `language`, `prompt` and `response_format` fields, `model` is ignored.
+async fn openai_transcription(
+    State(context): State<ApiContext>,
+    mut multipart: Multipart,
+) -> Result<Response, ApiError> {
+    let mut audio = None;
+    let mut options = TranscribeOptions::default();
+    let mut response_format = String::from("json");
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| ApiError::BadRequest(err.to_string()))?
+    {
+        let name = field.name().unwrap_or_default().to_string();
+        trace!("Multipart field: {name}");
+        match name.as_str() {
+            "file" => {
+                audio = Some(
+                    field
+                        .bytes()
+                        .await
+                        .map_err(|err| ApiError::BadRequest(err.to_string()))?
+                        .to_vec(),
+                );
+            }
+            "language" | "prompt" | "response_format" => {
+                let value = field
+                    .text()
+                    .await
+                    .map_err(|err| ApiError::BadRequest(err.to_string()))?;
+                match name.as_str() {
+                    "language" => options.language = Some(value),
+                    "prompt" => options.initial_prompt = Some(value),
+                    _ => response_format = value,
+                }
+            }
+            _ => debug!("Ignoring multipart field {name}"),
+        }
+    }
+    let audio = audio.ok_or_else(|| ApiError::BadRequest("Missing `file` field".into()))?;
+    let language = options.language.clone();
+    let transcript = transcribe_wav(context.app_handle, audio, options, None).await?;
+    let language = transcript
+        .language
+        .as_ref()
+        .map(|detection| detection.language.clone())
+        .or(language);
+    Ok(match response_format.as_str() {
+        "text" => transcript.as_text().trim().to_string().into_response(),
+        "srt" => transcript.as_srt().into_response(),
+        "vtt" => transcript.as_vtt().into_response(),
+        "verbose_json" => Json(json!({
+            "task": "transcribe",
+            "language": language,
+            "text": transcript.as_text().trim(),
+            "segments": segments_of(&transcript),
+        }))
+        .into_response(),
+        _ => Json(json!({ "text": transcript.as_text().trim() })).into_response(),
+    })
+}
+
+#[derive(Debug, Default, Deserialize)]
+/// Query parameters for the raw transcription endpoints
+struct TranscribeQuery {
+    language: Option<String>,
+    prompt: Option<String>,
+    translate: Option<bool>,
+    format: Option<TranscriptionFormat>,
+    sample_rate: Option<u32>,
+}
+
+impl TranscribeQuery {
+    fn options(&self) -> TranscribeOptions {
+        TranscribeOptions {
+            language: self.language.clone(),
+            initial_prompt: self.prompt.clone(),
+            translate: self.translate,
+            format: self.format,
+            ..Default::default()
+        }
+    }
+}
+
+/// `POST /v1/transcribe/bytes`, body is a WAV file
+async fn transcribe_bytes(
+    State(context): State<ApiContext>,
+    Query(query): Query<TranscribeQuery>,
+    body: Bytes,
+) -> Result<Json<TranscriptionResponse>, ApiError> {
+    let options = query.options();
+    let format = options.format.unwrap_or_default();
+    let transcript = transcribe_wav(context.app_handle, body.to_vec(), options, None).await?;
+    Ok(Json(TranscriptionResponse::new(&transcript, format)))
+}
+
+#[derive(Debug, Deserialize)]
+/// Body of the file transcription endpoint
+struct TranscribeFileRequest {
+    /// Path to a WAV file readable by the app
+    path: String,
+    options: Option<TranscribeOptions>,
+    decode_options: Option<AudioProcessingOptions>,
+}
+
+/// `POST /v1/transcribe/file`, transcribe a WAV file on this machine
+async fn transcribe_file(
+    State(context): State<ApiContext>,
+    Json(request): Json<TranscribeFileRequest>,
+) -> Result<Json<TranscriptionResponse>, ApiError> {
+    let audio = tokio::fs::read(&request.path)
+        .await
+        .map_err(|err| ApiError::BadRequest(format!("Could not read {}: {err}", request.path)))?;
+    let options = request.options.unwrap_or_default();
+    let format = options.format.unwrap_or_default();
+    let transcript =
+        transcribe_wav(context.app_handle, audio, options, request.decode_options).await?;
+    Ok(Json(TranscriptionResponse::new(&transcript, format)))
+}
+
+/// `GET /v1/transcribe/stream`, WebSocket streaming transcription.
+///
+/// The client sends binary messages of mono little-endian `f32` samples (at `sample_rate`, default 16kHz),
+/// then the text message `end`. Partial transcripts of the last 30 seconds are sent as audio
+/// arrives, followed by the final transcript of all audio. Streams longer than the upload limit
+/// are closed with an error.
+async fn transcribe_stream(
+    State(context): State<ApiContext>,
+    Query(query): Query<TranscribeQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    let sample_rate = query.sample_rate.unwrap_or(DEFAULT_STREAM_SAMPLE_RATE);
+    if !(1..=MAX_STREAM_SAMPLE_RATE).contains(&sample_rate) {
+        return Err(ApiError::BadRequest(format!(
+            "`sample_rate` must be between 1 and {MAX_STREAM_SAMPLE_RATE}"
+        )));
+    }
+    Ok(upgrade.on_upgrade(move |socket| handle_stream(socket, context, query, sample_rate)))
+}
+
+async fn handle_stream(
+    mut socket: WebSocket,
+    context: ApiContext,
+    query: TranscribeQuery,
+    sample_rate: u32,
+) {
+    let samples_for = |seconds: u32| {
+        sample_rate
+            .checked_mul(seconds)
+            .and_then(|count| usize::try_from(count).ok())
+            .unwrap_or(usize::MAX)
+    };
+    let partial_interval = samples_for(STREAM_PARTIAL_SECONDS);
+    let window = samples_for(STREAM_WINDOW_SECONDS);
+    let mut samples: Vec<f32> = Vec::new();
+    // Bytes of a sample split over two messages
+    let mut carry = Vec::with_capacity(size_of::<f32>());
+    let mut transcribed_len = 0;
+    debug!("Local API stream opened with sample rate {sample_rate}");
+    while let Some(message) = socket.recv().await {
+        let is_final = match message {
+            Ok(Message::Binary(bytes)) => {
+                if samples.len() + (carry.len() + bytes.len()) / size_of::<f32>()
+                    > MAX_STREAM_SAMPLES
+                {
+                    warn!("Local API stream exceeded {MAX_STREAM_SAMPLES} samples");
+                    close_stream(socket, close_code::SIZE, "Streamed audio is too long").await;
+                    return;
+                }
+                push_le_samples(&mut samples, &mut carry, &bytes);
+                false
+            }
+            Ok(Message::Text(text)) if text.as_str().trim() == "end" => {
+                if !carry.is_empty() {
+                    warn!("Local API stream ended inside a sample");
+                    close_stream(
+                        socket,
+                        close_code::INVALID,
+                        "Stream ended inside a sample, send whole 4 byte samples",
+                    )
+                    .await;
+                    return;
+                }
+                true
+            }
+            Ok(Message::Close(_)) => break,
+            Ok(_) => continue,
+            Err(err) => {
+                warn!("Local API stream error: {err}");
+                break;
+            }
+        };
+        if !is_final && samples.len() - transcribed_len < partial_interval {
+            continue;
+        }
+        transcribed_len = samples.len();
+        let start = if is_final {
+            0
+        } else {
+            samples.len().saturating_sub(window)
+        };
+        let reply = match transcribe_samples(
+            context.app_handle.clone(),
+            samples[start..].to_vec(),
+            sample_rate,
+            query.options(),
+            None,
+        )
+        .await
+        {
+            Ok(transcript) => json!({
+                "type": if is_final { "final" } else { "partial" },
+                "text": transcript.as_text().trim(),
+                "segments": segments_of(&transcript),
+            }),
+            Err(err) => json!({ "type": "error", "message": format!("{err:?}") }),
+        };
+        if socket
+            .send(Message::Text(reply.to_string().into()))
+            .await
+            .is_err()
+            || is_final
+        {
+            break;
+        }
+    }
+    debug!("Local API stream closed");
+}
+
+/// Close the stream with the given close code and reason
+async fn close_stream(mut socket: WebSocket, code: u16, reason: &str) {
+    let frame = CloseFrame {
+        code,
+        reason: reason.into(),
+    };
+    if let Err(err) = socket.send(Message::Close(Some(frame))).await {
+        debug!("Could not close local API stream: {err}");
+    }
+}
+
+/// Append the little-endian `f32` samples in `bytes` to `samples`.
+///
+/// A sample split between messages is kept in `carry` until its remaining bytes arrive.
+fn push_le_samples(samples: &mut Vec<f32>, carry: &mut Vec<u8>, mut bytes: &[u8]) {
+    if !carry.is_empty() {
+        let needed = (size_of::<f32>() - carry.len()).min(bytes.len());
+        carry.extend_from_slice(&bytes[..needed]);
+        bytes = &bytes[needed..];
+        if carry.len() < size_of::<f32>() {
+            return;
+        }
+        samples.push(f32::from_le_bytes([carry[0], carry[1], carry[2], carry[3]]));
+        carry.clear();
+    }
+    let chunks = bytes.chunks_exact(size_of::<f32>());
+    carry.extend_from_slice(chunks.remainder());
+    samples
+        .extend(chunks.map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])));
+}
+
+#[test]
+fn test_percent_decode() {
+    assert_eq!(percent_decode("abc%2B1+2").as_deref(), Some("abc+1 2"));
+    assert_eq!(percent_decode("abc123").as_deref(), Some("abc123"));
+    assert_eq!(percent_decode("bad%2"), None);
+}
+
+#[test]
+fn test_token_matches() {
+    assert!(token_matches("abc123", "abc123"));
+    assert!(!token_matches("abc124", "abc123"));
+    assert!(!token_matches("abc", "abc123"));
+}
+
+#[test]
+fn test_push_le_samples_carries_split_samples() {
+    let bytes: Vec<u8> = [0.5_f32, -1.0, 0.25]
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect();
+    let mut samples = Vec::new();
+    let mut carry = Vec::new();
+    push_le_samples(&mut samples, &mut carry, &bytes[..6]);
+    assert_eq!(samples, [0.5]);
+    assert_eq!(carry.len(), 2);
+    push_le_samples(&mut samples, &mut carry, &bytes[6..7]);
+    assert_eq!(samples, [0.5]);
+    push_le_samples(&mut samples, &mut carry, &bytes[7..]);
+    assert_eq!(samples, [0.5, -1.0, 0.25]);
+    assert!(carry.is_empty());
+}
+
+#[cfg(test)]
+/// Send a GET request to the local server, returning the status code
+async fn get_status(port: u16, path: &str, authorization: Option<&str>) -> u16 {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    let mut stream = tokio::net::TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+        .await
+        .expect("Server should accept connections");
+    let authorization = authorization
+        .map(|token| format!("Authorization: Bearer {token}\r\n"))
+        .unwrap_or_default();
+    let request = format!(
+        "GET {path} HTTP/1.1\r\nHost: localhost\r\n{authorization}Connection: close\r\n\r\n"
+    );
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .expect("Request should be sent");
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .await
+        .expect("Response should be read");
+    response
+        .split(' ')
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .expect("Response should start with a status line")
+}
+
+#[test]
+fn test_router_requires_token() {
+    let runtime = tokio::runtime::Runtime::new().expect("Runtime should start");
+    runtime.block_on(async {
+        let routes = with_token(
+            Router::new().route("/v1/status", get(|| async { "ok" })),
+            "secret token".into(),
+        );
+        let listener = tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .await
+            .expect("Port should be free");
+        let port = listener
+            .local_addr()
+            .expect("Listener has an address")
+            .port();
+        tokio::spawn(async move { axum::serve(listener, routes).await });
+
+        assert_eq!(get_status(port, "/v1/status", None).await, 401);
+        assert_eq!(get_status(port, "/v1/status", Some("wrong")).await, 401);
+        assert_eq!(
+            get_status(port, "/v1/status", Some("secret token")).await,
+            200
+        );
+        assert_eq!(
+            get_status(port, "/v1/status?token=secret+token", None).await,
+            200
+        );
+        assert_eq!(get_status(port, "/v1/status?token=secret", None).await, 401);
+    });
+}
@@ -0,0 +1,686 @@
+//! Settings shared by the commands, the local HTTP API, hotkey dictation and the CLI.
+//!
+//! Settings are stored as versioned JSON in [`SETTINGS_FILE`] in the app's config directory.
+//! Older files are migrated step by step when loaded. Version 0 is the key-value store the
+//! frontend kept its configuration in, so its values are taken over on the first start. From then
+//! on the frontend saves them through [`crate::command::update_settings`]. Version 1 still kept
+//! the preferred input device in that store.
+
+use crate::{
+    devices::use_input_device,
+    error::{AppError, ErrorCode},
+    events::SettingsChangedEvent,
+    hotkeys::{HotkeyBinding, HotkeyState},
+    profiles::{ApplicationProfile, ProfileState},
+    server::{ApiServerInfo, ApiServerState, DEFAULT_API_PORT},
+    types::{
+        AppState, AudioProcessingOptions, CaptureLimits, DictationOptions, MicrophoneState,
+        OutputOptions, SystemAudioOptions, TextPostProcessing, TextProcessOptions,
+        TranscribeOptions,
+    },
+};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use specta::Type;
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+use tauri_specta::Event;
+
+/// Version of the settings schema written by this app
+pub const SETTINGS_VERSION: u32 = 2;
+
+/// Name of the settings file in the app's config directory
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// Identifier of the app, naming its config directory (see `tauri.conf.json`)
+const APP_IDENTIFIER: &str = "com.super-mouse-ai.app";
+
+/// Store the frontend kept its configuration in, the settings of version 0
+const FRONTEND_STORE_FILE: &str = "super-mouse-ai.json";
+
+/// Key of the preferred input device in the frontend's store, used up to version 1
+const STORE_INPUT_DEVICE_KEY: &str = "input_device";
+
+/// Largest beam size whisper.cpp decodes with
+const MAX_BEAM_SIZE: i32 = 8;
+
+/// Migrations of the settings document, the one at index `i` going from version `i` to `i + 1`.
+///
+/// Each also gets the entries of the frontend's store, see [`FRONTEND_STORE_FILE`].
+const MIGRATIONS: &[Migration] = &[from_frontend_store, with_recording_settings];
+
+type Migration = fn(Map<String, Value>, &Map<String, Value>) -> Map<String, Value>;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Type)]
+#[serde(default)]
+/// Settings of the app, every item missing from the file takes its default
+pub struct Settings {
+    /// Schema version, see [`SETTINGS_VERSION`]
+    pub version: u32,
+    /// Run the model on the GPU, defaults to `true` on builds with GPU support
+    pub use_gpu: Option<bool>,
+    /// Defaults for the options of commands, the local API and hotkey dictation.
+    ///
+    /// Options given with a call take precedence.
+    pub dictation: DictationOptions,
+    /// Global hotkey and mouse-button bindings
+    pub hotkey_bindings: Vec<HotkeyBinding>,
+    pub api_server: ApiServerSettings,
+    /// Application profiles, the first one matching the focused window is used
+    pub profiles: Vec<ApplicationProfile>,
+    pub recording: RecordingSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            use_gpu: None,
+            dictation: DictationOptions::default(),
+            hotkey_bindings: Vec::new(),
+            api_server: ApiServerSettings::default(),
+            profiles: Vec::new(),
+            recording: RecordingSettings::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(default)]
+/// Settings of the local HTTP/WebSocket API
+pub struct ApiServerSettings {
+    /// Start the API with the app
+    pub enabled: bool,
+    /// Port on `127.0.0.1`, defaults to [`crate::server::DEFAULT_API_PORT`]
+    pub port: Option<u16>,
+    /// Token clients must send, a random one is generated on every start if `None`
+    pub token: Option<String>,
+}
+
+impl ApiServerSettings {
+    /// Whether the running API was started with these settings
+    #[must_use]
+    pub fn is_running_as(&self, info: &ApiServerInfo) -> bool {
+        self.port.unwrap_or(DEFAULT_API_PORT) == info.port
+            && self.token.as_ref().is_none_or(|token| *token == info.token)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(default)]
+/// Settings of the microphone recording
+pub struct RecordingSettings {
+    /// Input device selected whenever it is available, the default device if `None`
+    pub preferred_device: Option<String>,
+    pub capture_limits: CaptureLimits,
+    /// Directory the raw audio of every recording is archived to, not archived if `None`
+    pub archive_directory: Option<String>,
+    /// System audio recorded along with the microphone, if any
+    pub system_audio: Option<SystemAudioOptions>,
+}
+
+pub type SettingsState = Mutex<Settings>;
+
+impl Settings {
+    /// Check that all values are in range, listing every problem in the error
+    ///
+    /// # Errors
+    ///
+    /// Returns the problems found, separated by `; `.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+        if self.version != SETTINGS_VERSION {
+            problems.push(format!(
+                "Version {} is not the supported version {SETTINGS_VERSION}",
+                self.version
+            ));
+        }
+        if let Some(options) = &self.dictation.transcribe_options {
+            if options
+                .beam_size
+                .is_some_and(|size| !(1..=MAX_BEAM_SIZE).contains(&size))
+            {
+                problems.push(format!("Beam size must be between 1 and {MAX_BEAM_SIZE}"));
+            }
+            if options
+                .patience
+                .is_some_and(|patience| !patience.is_finite() || patience < 0.0)
+            {
+                problems.push("Patience must be a positive number".into());
+            }
+        }
+        if self
+            .dictation
+            .min_confidence
+            .is_some_and(|confidence| !(0.0..=1.0).contains(&confidence))
+        {
+            problems.push("Minimum confidence must be between 0 and 1".into());
+        }
+        if self.recording.capture_limits.max_duration_seconds == Some(0) {
+            problems.push("Maximum recording duration must be at least one second".into());
+        }
+        if self
+            .recording
+            .archive_directory
+            .as_ref()
+            .is_some_and(|directory| directory.trim().is_empty())
+        {
+            problems.push("Archive directory must not be empty".into());
+        }
+        if self.api_server.port == Some(0) {
+            problems.push("API port must not be 0".into());
+        }
+        if self
+            .api_server
+            .token
+            .as_ref()
+            .is_some_and(|token| token.trim().is_empty())
+        {
+            problems.push("API token must not be empty".into());
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("; "))
+        }
+    }
+
+    /// Options given with a call on top of the configured transcription options
+    #[must_use]
+    pub fn transcribe_options(&self, given: Option<TranscribeOptions>) -> TranscribeOptions {
+        let defaults = self
+            .dictation
+            .transcribe_options
+            .clone()
+            .unwrap_or_default();
+        match given {
+            Some(given) => defaults.overridden_by(&given),
+            None => defaults,
+        }
+    }
+
+    /// Audio processing options given with a call, or the configured ones
+    #[must_use]
+    pub fn decode_options(&self, given: Option<AudioProcessingOptions>) -> AudioProcessingOptions {
+        given.or(self.dictation.decode_options).unwrap_or_default()
+    }
+
+    /// Text processing options given with a call, or the configured custom ones
+    #[must_use]
+    pub fn text_process_options(&self, given: Option<TextProcessOptions>) -> TextProcessOptions {
+        given
+            .or_else(|| match &self.dictation.processing_options {
+                Some(TextPostProcessing::Custom(options)) => Some(options.clone()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// Every output option not given with a call is taken from the configured ones
+    #[must_use]
+    pub fn output_options(&self, given: OutputOptions) -> OutputOptions {
+        let defaults = self.dictation.output_options.clone().unwrap_or_default();
+        OutputOptions {
+            target: given.target.or(defaults.target),
+            method: given.method.or(defaults.method),
+            key_delay_ms: given.key_delay_ms.or(defaults.key_delay_ms),
+            chunk_size: given.chunk_size.or(defaults.chunk_size),
+            restore_delay_ms: given.restore_delay_ms.or(defaults.restore_delay_ms),
+        }
+    }
+
+    /// Read and migrate a settings file, e.g. for the CLI
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can not be read, or holds invalid settings.
+    pub fn read(path: &Path) -> Result<Self, String> {
+        migrate(read_document(path)?, &Map::new())
+    }
+
+    /// Write the settings as JSON, creating the directory if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can not be written.
+    pub fn write(&self, path: &Path) -> Result<(), AppError> {
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|err| AppError::new(ErrorCode::Internal, err))?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+/// JSON object of a settings file, of any version
+fn read_document(path: &Path) -> Result<Map<String, Value>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("Could not read {}: {err}", path.display()))?;
+    match serde_json::from_str(&text).map_err(|err| err.to_string())? {
+        Value::Object(document) => Ok(document),
+        _ => Err(format!("{} does not hold a JSON object", path.display())),
+    }
+}
+
+/// Schema version of a settings document, `0` for the frontend's store
+fn document_version(document: &Map<String, Value>) -> u64 {
+    document
+        .get("version")
+        .and_then(Value::as_u64)
+        .unwrap_or_default()
+}
+
+/// Bring a settings document of any known version to the current one, then validate it
+fn migrate(
+    mut document: Map<String, Value>,
+    store: &Map<String, Value>,
+) -> Result<Settings, String> {
+    let version = document_version(&document);
+    let start = usize::try_from(version)
+        .ok()
+        .filter(|&start| start <= MIGRATIONS.len())
+        .ok_or_else(|| {
+            format!("Settings version {version} is newer than this app's {SETTINGS_VERSION}")
+        })?;
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(start) {
+        debug!("Migrating settings from version {from}");
+        document = migration(document, store);
+        document.insert("version".into(), json!(from + 1));
+    }
+    let settings: Settings =
+        serde_json::from_value(Value::Object(document)).map_err(|err| err.to_string())?;
+    settings.validate()?;
+    Ok(settings)
+}
+
+/// Version 0 to 1, take over the options of the frontend's store
+fn from_frontend_store(store: Map<String, Value>, _: &Map<String, Value>) -> Map<String, Value> {
+    let get = |key: &str| store.get(key).filter(|value| !value.is_null()).cloned();
+    let mut transcribe = Map::new();
+    if let Some(threads) = get("threads") {
+        transcribe.insert("threads".into(), threads);
+    }
+    if let Some(prompt) = get("prompt").filter(|prompt| prompt.as_str() != Some("")) {
+        transcribe.insert("initial_prompt".into(), prompt);
+    }
+    if let Some(patience) = get("beam_search_patience") {
+        transcribe.insert("patience".into(), patience);
+    }
+    let mut decode = Map::new();
+    if let Some(denoise) = get("audio_denoise") {
+        decode.insert("denoise_audio".into(), denoise);
+    }
+    if let Some(normalize) = get("audio_normalize") {
+        decode.insert("normalize_result".into(), normalize);
+    }
+    let mut processing = Map::new();
+    if let Some(ignored) = get("ignored") {
+        let words = ignored
+            .as_str()
+            .unwrap_or_default()
+            .lines()
+            .map(str::trim)
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>();
+        processing.insert("removed_words".into(), json!(words));
+    }
+    if let Some(replace) = get("remove_newline_inside_sentence") {
+        processing.insert("replace_inter_sentence_newlines".into(), replace);
+    }
+    let mut dictation = Map::new();
+    if !transcribe.is_empty() {
+        dictation.insert("transcribe_options".into(), Value::Object(transcribe));
+    }
+    if !decode.is_empty() {
+        dictation.insert("decode_options".into(), Value::Object(decode));
+    }
+    if !processing.is_empty() {
+        dictation.insert(
+            "processing_options".into(),
+            json!({ "Custom": Value::Object(processing) }),
+        );
+    }
+    let mut settings = Map::new();
+    if let Some(use_gpu) = get("use_gpu") {
+        settings.insert("use_gpu".into(), use_gpu);
+    }
+    settings.insert("dictation".into(), Value::Object(dictation));
+    settings
+}
+
+/// Version 1 to 2, take over the preferred input device from the frontend's store
+fn with_recording_settings(
+    mut settings: Map<String, Value>,
+    store: &Map<String, Value>,
+) -> Map<String, Value> {
+    if let Some(device) = store
+        .get(STORE_INPUT_DEVICE_KEY)
+        .filter(|value| value.is_string())
+    {
+        settings.insert("recording".into(), json!({ "preferred_device": device }));
+    }
+    settings
+}
+
+/// Current settings of the app, or the defaults if they can not be read
+pub fn current(app_handle: &AppHandle) -> Settings {
+    app_handle
+        .state::<SettingsState>()
+        .lock()
+        .map(|settings| settings.clone())
+        .unwrap_or_else(|err| {
+            error!("Could not get settings lock, using defaults: {err}");
+            Settings::default()
+        })
+}
+
+/// Path of the settings file of the app
+///
+/// # Errors
+///
+/// Returns an error if the config directory can not be resolved.
+pub fn settings_path(app_handle: &AppHandle) -> Result<PathBuf, AppError> {
+    Ok(app_handle.path().app_config_dir()?.join(SETTINGS_FILE))
+}
+
+/// Path of the settings file without a running app, following Tauri's config directory
+pub fn default_settings_path() -> Option<PathBuf> {
+    let home = || std::env::var_os("HOME").map(PathBuf::from);
+    let config_directory = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library").join("Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home().map(|home| home.join(".config")))
+    }?;
+    Some(config_directory.join(APP_IDENTIFIER).join(SETTINGS_FILE))
+}
+
+/// Load the settings of the app, migrating the frontend's store on the first start.
+///
+/// Migrated settings are saved. Invalid settings are logged and replaced by the defaults,
+/// without overwriting the file.
+pub fn load(app_handle: &AppHandle) -> Settings {
+    let path = match settings_path(app_handle) {
+        Ok(path) => path,
+        Err(err) => {
+            error!("Could not find settings file, using defaults: {err}");
+            return Settings::default();
+        }
+    };
+    let document = if path.exists() {
+        match read_document(&path) {
+            Ok(document) => Some(document),
+            Err(err) => {
+                warn!("Invalid settings, using defaults: {err}");
+                return Settings::default();
+            }
+        }
+    } else {
+        info!("No settings file, taking over the frontend's options");
+        None
+    };
+    let is_current = document
+        .as_ref()
+        .is_some_and(|document| document_version(document) == u64::from(SETTINGS_VERSION));
+    let store = if is_current {
+        Map::new()
+    } else {
+        app_handle
+            .store(FRONTEND_STORE_FILE)
+            .map(|store| store.entries().into_iter().collect::<Map<_, _>>())
+            .unwrap_or_else(|err| {
+                warn!("Could not open frontend store: {err}");
+                Map::new()
+            })
+    };
+    let document = document.unwrap_or_else(|| {
+        let mut document = store.clone();
+        // The frontend versions its store on its own
+        document.insert("version".into(), json!(0));
+        document
+    });
+    let settings = match migrate(document, &store) {
+        Ok(settings) => settings,
+        Err(err) => {
+            warn!("Invalid settings, using defaults: {err}");
+            return Settings::default();
+        }
+    };
+    if !is_current {
+        if let Err(err) = settings.write(&path) {
+            warn!("Could not save migrated settings: {err}");
+        }
+    }
+    settings
+}
+
+/// Start the subsystems configured by the settings, called once the app state is managed
+pub fn apply_on_start(app_handle: &AppHandle) {
+    let settings = match app_handle.state::<SettingsState>().lock() {
+        Ok(settings) => settings.clone(),
+        Err(err) => {
+            error!("Could not get settings lock: {err}");
+            return;
+        }
+    };
+    // The model was already loaded with the GPU setting
+    let initial = Settings {
+        use_gpu: settings.use_gpu,
+        ..Settings::default()
+    };
+    apply(app_handle, &initial, &settings);
+}
+
+/// Validate, save and apply a change to the settings, then notify the frontend.
+///
+/// # Errors
+///
+/// Returns [`ErrorCode::InvalidSettings`] if the changed settings are invalid, or an error
+/// if they can not be saved. The settings are unchanged in both cases.
+pub fn update(
+    app_handle: &AppHandle,
+    change: impl FnOnce(&mut Settings),
+) -> Result<Settings, AppError> {
+    let state = app_handle.state::<SettingsState>();
+    let mut settings = state.lock()?;
+    let previous = settings.clone();
+    let mut next = previous.clone();
+    change(&mut next);
+    next.validate()
+        .map_err(|err| AppError::new(ErrorCode::InvalidSettings, err))?;
+    next.write(&settings_path(app_handle)?)?;
+    settings.clone_from(&next);
+    drop(settings);
+    apply(app_handle, &previous, &next);
+    let _ = SettingsChangedEvent::with_payload(next.clone())
+        .emit(app_handle)
+        .map_err(|err| error!("Error for settings changed event: {err}"));
+    Ok(next)
+}
+
+/// Bring the hotkey engine, model and local API in line with the changed settings
+fn apply(app_handle: &AppHandle, previous: &Settings, next: &Settings) {
+    if previous.hotkey_bindings != next.hotkey_bindings {
+        match app_handle.state::<HotkeyState>().lock() {
+            Ok(mut engine) => engine.set_bindings(next.hotkey_bindings.clone()),
+            Err(err) => error!("Could not get hotkey engine lock: {err}"),
+        }
+    }
+    if let Some(use_gpu) = next.use_gpu.filter(|_| previous.use_gpu != next.use_gpu) {
+        let reloaded = app_handle
+            .state::<AppState>()
+            .lock()
+            .map_err(|err| err.to_string())
+            .and_then(|mut app_state| {
+                app_state
+                    .reload_model(use_gpu)
+                    .map_err(|err| err.to_string())
+            });
+        if let Err(err) = reloaded {
+            error!("Could not reload model with use_gpu={use_gpu}: {err}");
+        }
+    }
+    if previous.api_server != next.api_server {
+        let api_server = next.api_server.clone();
+        let running = app_handle
+            .state::<ApiServerState>()
+            .lock()
+            .ok()
+            .and_then(|server| server.info().cloned());
+        if running.is_some_and(|info| api_server.enabled && api_server.is_running_as(&info)) {
+            debug!("Local API is already running with the settings");
+        } else if api_server.enabled {
+            let handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                let started = crate::server::start(handle, api_server.port, api_server.token).await;
+                if let Err(err) = started {
+                    error!("Could not start local API: {err}");
+                }
+            });
+        } else {
+            match app_handle.state::<ApiServerState>().lock() {
+                Ok(mut server) => {
+                    server.stop();
+                }
+                Err(err) => error!("Could not get API server state lock: {err}"),
+            }
+        }
+    }
+    if previous.profiles != next.profiles {
+        match app_handle.state::<ProfileState>().lock() {
+            Ok(mut profiles) => profiles.set_profiles(next.profiles.clone()),
+            Err(err) => error!("Could not get profile state lock: {err}"),
+        }
+    }
+    if previous.recording != next.recording {
+        apply_recording(app_handle, &next.recording);
+    }
+}
+
+/// Hand the recording settings to the microphone state, selecting the preferred device if it
+/// is not in use yet. Recordings in progress keep their settings.
+fn apply_recording(app_handle: &AppHandle, recording: &RecordingSettings) {
+    let preferred_changed = match app_handle.state::<MicrophoneState>().lock() {
+        Ok(mut mic_state) => {
+            mic_state.capture_limits = recording.capture_limits;
+            mic_state.archive_directory = recording.archive_directory.as_ref().map(PathBuf::from);
+            mic_state.system_audio.clone_from(&recording.system_audio);
+            mic_state.preferred_device != recording.preferred_device
+        }
+        Err(err) => {
+            error!("Could not get mic state lock: {err}");
+            return;
+        }
+    };
+    if preferred_changed {
+        info!(
+            "Selecting preferred input device {:?}",
+            recording.preferred_device
+        );
+        if let Err(err) = use_input_device(app_handle, recording.preferred_device.clone()) {
+            warn!("Could not select preferred input device: {err}");
+        }
+    }
+}
+
+#[test]
+fn test_migrate_frontend_store() {
+    let store = json!({
+        "version": 0,
+        "threads": 4,
+        "prompt": "",
+        "ignored": "[BLANK_AUDIO]\n[SILENCE]\n",
+        "remove_newline_inside_sentence": false,
+        "audio_denoise": false,
+        "use_gpu": null,
+        "theme": "dark",
+        "input_device": "USB Microphone",
+    });
+    let Value::Object(document) = store else {
+        unreachable!("Store is an object");
+    };
+    let settings = migrate(document.clone(), &document).expect("Frontend store should migrate");
+    assert_eq!(settings.version, SETTINGS_VERSION);
+    assert_eq!(settings.use_gpu, None);
+    let transcribe = settings.transcribe_options(None);
+    assert_eq!(transcribe.threads, Some(4));
+    assert_eq!(transcribe.initial_prompt, None);
+    assert_eq!(settings.decode_options(None).denoise_audio, Some(false));
+    let processing = settings.text_process_options(None);
+    assert_eq!(
+        processing.removed_words,
+        Some(vec!["[BLANK_AUDIO]".to_string(), "[SILENCE]".to_string()])
+    );
+    assert_eq!(processing.replace_inter_sentence_newlines, Some(false));
+    assert_eq!(
+        settings.recording.preferred_device.as_deref(),
+        Some("USB Microphone")
+    );
+    assert_eq!(u32::try_from(MIGRATIONS.len()), Ok(SETTINGS_VERSION));
+}
+
+#[test]
+fn test_migrate_input_device_from_store() {
+    let Value::Object(document) = json!({ "version": 1, "use_gpu": false }) else {
+        unreachable!("Document is an object");
+    };
+    let Value::Object(store) = json!({ "input_device": "Headset", "threads": 2 }) else {
+        unreachable!("Store is an object");
+    };
+    let settings = migrate(document.clone(), &store).expect("Settings should migrate");
+    assert_eq!(settings.version, SETTINGS_VERSION);
+    assert_eq!(settings.use_gpu, Some(false));
+    assert_eq!(
+        settings.recording.preferred_device.as_deref(),
+        Some("Headset")
+    );
+    // Only the device is taken from the store once the settings exist
+    assert_eq!(settings.transcribe_options(None).threads, None);
+    let settings = migrate(document, &Map::new()).expect("Settings should migrate");
+    assert_eq!(settings.recording, RecordingSettings::default());
+}
+
+#[test]
+fn test_validate_and_defaults() {
+    let mut settings = Settings::default();
+    assert_eq!(settings.validate(), Ok(()));
+    settings.dictation.min_confidence = Some(1.5);
+    settings.api_server.token = Some(" ".into());
+    settings.recording.capture_limits.max_duration_seconds = Some(0);
+    let problems = settings.validate().expect_err("Settings should be invalid");
+    assert_eq!(problems.split("; ").count(), 3);
+    settings.recording.capture_limits.max_duration_seconds = None;
+    let Value::Object(newer) = json!({ "version": SETTINGS_VERSION + 1 }) else {
+        unreachable!("Document is an object");
+    };
+    assert!(migrate(newer, &Map::new()).is_err());
+    settings.dictation.transcribe_options = Some(TranscribeOptions {
+        threads: Some(2),
+        language: Some("de".into()),
+        ..Default::default()
+    });
+    let given = TranscribeOptions {
+        language: Some("en".into()),
+        ..Default::default()
+    };
+    let options = settings.transcribe_options(Some(given));
+    assert_eq!(options.threads, Some(2));
+    assert_eq!(options.language.as_deref(), Some("en"));
+    let info = ApiServerInfo {
+        port: DEFAULT_API_PORT,
+        token: "generated".into(),
+    };
+    assert!(ApiServerSettings::default().is_running_as(&info));
+    settings.api_server.token = Some("configured".into());
+    assert!(!settings.api_server.is_running_as(&info));
+}
@@ -1,3 +1,6 @@
+use crate::{
+    hallucination::HallucinationReason, language::LanguageDetection, types::CaptureSource,
+};
 use num::integer::div_floor;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -16,6 +19,9 @@ pub struct Transcript {
     /// List of words in the transcript - split by each word.
     /// Only present if `word_timestamps` is `true` in [`Model::transcribe_audio`].
     pub word_utterances: Option<Vec<Utterance>>,
+    /// Detected spoken language, `None` if the language was given.
+    #[serde(default)]
+    pub language: Option<LanguageDetection>,
 }
 
 /// A single utterance in the transcript.
@@ -29,6 +35,32 @@ pub struct Utterance {
     pub stop: i64,
     /// Text of the utterance.
     pub text: String,
+    /// Number of the speaker starting at 1, if the transcript was diarized.
+    #[serde(default)]
+    pub speaker: Option<u32>,
+    /// Why the utterance looks made up by whisper, if flagged by the hallucination filter.
+    #[serde(default)]
+    pub hallucination: Option<HallucinationReason>,
+    /// Probability between 0 and 1 that the text is right, if known.
+    #[serde(default)]
+    pub confidence: Option<f32>,
+    /// Other texts whisper considered for the utterance, most likely first.
+    #[serde(default)]
+    pub alternatives: Vec<String>,
+    /// Where the utterance was recorded, if system audio was recorded along with the microphone.
+    #[serde(default)]
+    pub source: Option<CaptureSource>,
+}
+
+impl Utterance {
+    /// Returns the trimmed text, prefixed with `Speaker N: ` if the speaker is known.
+    #[must_use]
+    pub fn labelled_text(&self) -> String {
+        self.speaker.map_or_else(
+            || self.text.trim().to_string(),
+            |speaker| format!("Speaker {speaker}: {}", self.text.trim()),
+        )
+    }
 }
 
 impl Transcript {
@@ -38,7 +70,7 @@ impl Transcript {
         self.utterances
             .iter()
             .fold(String::new(), |transcript, fragment| {
-                transcript + format!("{}\n", fragment.text.trim()).as_str()
+                transcript + format!("{}\n", fragment.labelled_text()).as_str()
             })
     }
 
@@ -51,9 +83,12 @@ impl Transcript {
             .fold(String::new(), |transcript, fragment| {
                 transcript
                     + format!(
-                        "{} --> {}\n{}\n\n",
+                        "{} --> {}\n{}{}\n\n",
                         format_timestamp(fragment.start, true, "."),
                         format_timestamp(fragment.stop, true, "."),
+                        fragment
+                            .speaker
+                            .map_or_else(String::new, |speaker| format!("<v Speaker {speaker}>")),
                         fragment.text.trim().replace("-->", "->")
                     )
                     .as_str()
@@ -74,13 +109,20 @@ impl Transcript {
                             "{i}\n{} --> {}\n{}\n",
                             format_timestamp(fragment.start, true, ","),
                             format_timestamp(fragment.stop, true, ","),
-                            fragment.text.trim().replace("-->", "->")
+                            fragment.labelled_text().replace("-->", "->")
                         )
                         .as_str(),
                 )
             })
             .1
     }
+
+    /// Returns the transcript as JSON, with timestamps, speakers and confidences of every
+    /// utterance and word, e.g. to highlight doubtful words.
+    #[must_use]
+    pub fn as_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
 }
 
 /// Timestamp is oddly given in number of seconds * 100, or number of milliseconds / 10.
@@ -128,3 +170,44 @@ fn test_format_timestamp_seconds() {
     let result = format_timestamp(100, false, ".");
     assert_eq!(result, "00:01.000");
 }
+
+#[test]
+fn test_speaker_labels() {
+    let transcript = Transcript {
+        processing_time: Duration::ZERO,
+        utterances: vec![
+            Utterance {
+                start: 0,
+                stop: 150,
+                text: " Hello there.".into(),
+                speaker: Some(1),
+                hallucination: None,
+                confidence: None,
+                alternatives: Vec::new(),
+                source: None,
+            },
+            Utterance {
+                start: 150,
+                stop: 300,
+                text: " Hi!".into(),
+                speaker: Some(2),
+                hallucination: None,
+                confidence: None,
+                alternatives: Vec::new(),
+                source: None,
+            },
+        ],
+        word_utterances: None,
+        language: None,
+    };
+    assert_eq!(
+        transcript.as_text(),
+        "Speaker 1: Hello there.\nSpeaker 2: Hi!\n"
+    );
+    assert!(transcript
+        .as_vtt()
+        .contains("00:00:01.500 --> 00:00:03.000\n<v Speaker 2>Hi!\n"));
+    assert!(transcript
+        .as_srt()
+        .starts_with("1\n00:00:00,000 --> 00:00:01,500\nSpeaker 1: Hello there.\n"));
+}
@@ -1,21 +1,27 @@
 //! Data types and associated functions for those types.
 
-use crate::mutter::Model;
+use crate::{capture::AudioArchive, mutter::Model};
 use log::{debug, warn};
 use mouce::common::MouseButton;
 use rodio::{
     cpal::{default_host, traits::HostTrait, Host, StreamConfig},
-    Device,
+    Device, DeviceTrait,
 };
 use serde::{Deserialize, Serialize};
 use specta::Type;
-use std::{collections::HashMap, path::PathBuf, sync::Mutex};
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    sync::Mutex,
+};
 use tauri::async_runtime::Sender;
+use tokio::sync::oneshot;
 use whisper_rs::{WhisperContextParameters, WhisperError};
 
 /// A struct to hold both the default and custom model together, enabling for easy switching
 pub struct ModelHolder {
     default: Model,
+    default_path: String,
     custom: Option<(Model, String)>,
 }
 
@@ -28,13 +34,14 @@ pub struct InnerAppState {
 }
 
 impl InnerAppState {
-    pub const fn new(model: Model) -> Self {
+    pub const fn new(model: Model, path: String) -> Self {
         // Load model into memory by evaluating short silence
         // FIXME: Need to do this in another thread, otherwise UI freezes
         // let _ = model.transcribe_pcm_s16le(&[0.0; 20_000], false, false, None, None, None);
         Self {
             model: ModelHolder {
                 default: model,
+                default_path: path,
                 custom: None,
             },
         }
@@ -60,6 +67,25 @@ impl InnerAppState {
         let _ = self.model.custom.take();
     }
 
+    /// Load the model being used again, e.g. to switch GPU use
+    pub fn reload_model(&mut self, use_gpu: bool) -> Result<(), WhisperError> {
+        if let Some((_, path)) = &self.model.custom {
+            return self.replace_custom_model(path.clone(), use_gpu);
+        }
+        let mut params = WhisperContextParameters::new();
+        params.use_gpu(use_gpu);
+        self.model.default = Model::new_with_params(&self.model.default_path, params)?;
+        Ok(())
+    }
+
+    /// Path of the model being used
+    pub fn get_model_path(&self) -> &str {
+        self.model
+            .custom
+            .as_ref()
+            .map_or(&self.model.default_path, |holder| &holder.1)
+    }
+
     pub fn get_model(&self) -> &Model {
         self.model
             .custom
@@ -109,13 +135,60 @@ pub type SoundMapState = Mutex<InnerSoundMapState>;
 pub enum RecordingState {
     Stopped,
     Recording,
+    /// Recording is on hold, resuming appends to the same audio
+    Paused,
+    /// Recording has stopped, audio is being transcribed and processed
+    Processing,
+}
+
+impl RecordingState {
+    /// Check whether moving from this state to `next` is allowed (staying in a state always is)
+    pub const fn can_transition_to(self, next: Self) -> bool {
+        matches!(
+            (self, next),
+            (
+                Self::Stopped,
+                Self::Stopped | Self::Recording | Self::Processing
+            ) | (
+                Self::Recording | Self::Paused,
+                Self::Recording | Self::Paused | Self::Stopped | Self::Processing
+            ) | (
+                Self::Processing,
+                Self::Processing | Self::Stopped | Self::Recording
+            )
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Message to the thread running the input stream
+pub enum StreamControl {
+    Stop,
+    Pause,
+    Resume,
+    /// Stop, handing the open archive over to the recording continued on another device
+    Switch,
 }
 
 /// State of the microphone
 pub struct InnerMicrophoneState {
     pub host: Host,
     pub device: Option<Device>,
-    pub stream_sender: Option<Sender<()>>,
+    pub stream_sender: Option<Sender<StreamControl>>,
+    pub recording_state: RecordingState,
+    /// Name of the device chosen by the user, used again once it is plugged back in
+    pub preferred_device: Option<String>,
+    /// Stream configuration for the selected device, the device's default when `None`
+    pub stream_config: Option<InputStreamConfig>,
+    pub capture_limits: CaptureLimits,
+    /// Directory the raw audio of every recording is archived to, not archived if `None`
+    pub archive_directory: Option<PathBuf>,
+    /// Resolved by the recording thread once all audio is stored, with the archive file if any
+    pub recording_finished: Option<oneshot::Receiver<Option<PathBuf>>>,
+    /// System audio recorded along with the microphone, if any
+    pub system_audio: Option<SystemAudioOptions>,
+    /// Archive of a recording that moved to another device, continued by the next stream
+    pub continued_archive: Option<AudioArchive>,
 }
 
 impl InnerMicrophoneState {
@@ -129,33 +202,161 @@ impl InnerMicrophoneState {
             host,
             device,
             stream_sender: None,
+            recording_state: RecordingState::Stopped,
+            preferred_device: None,
+            stream_config: None,
+            capture_limits: CaptureLimits::default(),
+            archive_directory: None,
+            recording_finished: None,
+            system_audio: None,
+            continued_archive: None,
         }
     }
 
-    /// Check if currently being recorded
+    /// Check if currently being recorded (including paused)
     pub const fn is_recording(&self) -> bool {
         self.stream_sender.is_some()
     }
 
-    // TODO: Add switching devices
+    /// Move to the next recording state, returning whether it changed
+    pub fn transition(&mut self, next: RecordingState) -> Result<bool, String> {
+        let current = self.recording_state;
+        if !current.can_transition_to(next) {
+            return Err(format!(
+                "Cannot change recording state from {current:?} to {next:?}"
+            ));
+        }
+        self.recording_state = next;
+        Ok(current != next)
+    }
+
+    /// Name of the currently selected device
+    pub fn device_name(&self) -> Option<String> {
+        self.device.as_ref().and_then(|device| device.name().ok())
+    }
 
     // TODO: Encapsulate data to allow independent access
 }
 
 pub type MicrophoneState = Mutex<InnerMicrophoneState>;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// Sample formats that can be recorded, all are converted to `f32`
+pub enum InputSampleFormat {
+    F32,
+    I16,
+    U16,
+    I32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// Stream configuration chosen by the user for recording
+pub struct InputStreamConfig {
+    pub sample_format: InputSampleFormat,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Frames per buffer, uses the device's default when `None`
+    pub buffer_size: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// A range of stream configurations supported by an input device
+pub struct SupportedInputConfig {
+    pub sample_format: InputSampleFormat,
+    pub channels: u16,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    /// Smallest buffer size in frames, `None` if unknown
+    pub min_buffer_size: Option<u32>,
+    /// Largest buffer size in frames, `None` if unknown
+    pub max_buffer_size: Option<u32>,
+}
+
+impl SupportedInputConfig {
+    /// Check whether the given configuration is within this range
+    pub fn supports(&self, config: &InputStreamConfig) -> bool {
+        let buffer_ok = config.buffer_size.is_none_or(|size| {
+            self.min_buffer_size.is_none_or(|min| size >= min)
+                && self.max_buffer_size.is_none_or(|max| size <= max)
+        });
+        self.sample_format == config.sample_format
+            && self.channels == config.channels
+            && (self.min_sample_rate..=self.max_sample_rate).contains(&config.sample_rate)
+            && buffer_ok
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// What to do when a recording reaches its maximum duration
+pub enum OverflowPolicy {
+    /// Stop recording, keeping the audio so far
+    #[default]
+    Stop,
+    /// Keep recording, dropping the oldest audio
+    DropOldest,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// Limits of a single recording.
+///
+/// All items are optional.
+pub struct CaptureLimits {
+    /// Maximum seconds of audio kept, unbounded if not set
+    pub max_duration_seconds: Option<u32>,
+    /// Defaults to [`OverflowPolicy::Stop`]
+    pub overflow: Option<OverflowPolicy>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// Where captured audio comes from
+pub enum CaptureSource {
+    #[default]
+    Microphone,
+    /// Audio played on the machine, e.g. calls and videos
+    System,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// Options for recording the audio played on the machine
+pub struct SystemAudioOptions {
+    /// Monitor source to record, e.g. `alsa_output.pci-0000_00_1f.3.analog-stereo.monitor`
+    pub source: String,
+    /// Mix into the microphone recording, otherwise both are transcribed on their own and merged.
+    ///
+    /// Utterances are tagged with their source either way.
+    pub mix_with_microphone: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+/// System audio recorded along with the microphone, kept apart so the sources can be told apart
+pub struct SystemTrack {
+    /// Samples lined up with the microphone data, in the same format
+    pub samples: VecDeque<f32>,
+    /// Whether the samples are also mixed into the microphone data
+    pub mixed: bool,
+}
+
 #[derive(Debug, Clone, Default)]
 /// Data recorded from user's microphone
-pub struct InnerMicrophoneData(pub Vec<f32>, pub u16, pub u32);
+///
+/// The samples are a ring, so the oldest audio can be dropped cheaply at the recording limit.
+/// The system audio, if recorded, is kept in its own track.
+pub struct InnerMicrophoneData(pub VecDeque<f32>, pub u16, pub u32, pub Option<SystemTrack>);
 
 impl InnerMicrophoneData {
     pub const fn new() -> Self {
-        Self(Vec::new(), 1, 48_000)
+        Self(VecDeque::new(), 1, 48_000, None)
+    }
+
+    /// Throw away the recorded audio, including the system audio track
+    pub fn clear(&mut self) {
+        self.0.clear();
+        self.3 = None;
     }
 
     /// Replace microphone data with one that follows a specific stream configuration
     pub fn replace_with_config(&mut self, audio_config: &StreamConfig) {
-        self.0.clear();
+        self.clear();
         self.1 = audio_config.channels;
         self.2 = audio_config.sample_rate.0;
     }
@@ -213,27 +414,6 @@ impl ModKeyPayload {
     }
 }
 
-/// Given a key, check if it matches one of the (specific) modifier keys.
-///
-/// The main modifiers are: Alt, Control, Meta, Option, and Shift (both left and right).
-pub const fn is_modkey(key: device_query::Keycode) -> bool {
-    use device_query::Keycode as K;
-    matches!(
-        key,
-        K::Command
-            | K::LAlt
-            | K::LControl
-            | K::LMeta
-            | K::LOption
-            | K::LShift
-            | K::RAlt
-            | K::RControl
-            | K::RMeta
-            | K::ROption
-            | K::RShift
-    )
-}
-
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
 #[non_exhaustive]
 /// Format type for a transcription
@@ -244,6 +424,8 @@ pub enum TranscriptionFormat {
     SRT,
     #[allow(clippy::upper_case_acronyms, reason = "Proper name of format type")]
     VTT,
+    /// The structured [`crate::transcript::Transcript`], including confidences
+    Json,
 }
 
 impl TranscriptionFormat {
@@ -253,8 +435,69 @@ impl TranscriptionFormat {
             Self::Text => transcript.as_text(),
             Self::SRT => transcript.as_srt(),
             Self::VTT => transcript.as_vtt(),
+            Self::Json => transcript.as_json(),
         }
     }
+
+    /// Whether the format is structured data, which text processing would break and which is
+    /// not pasted into applications
+    pub const fn is_structured(self) -> bool {
+        matches!(self, Self::Json)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Type)]
+/// Transcript of the recorded audio, processed if asked for
+pub struct TranscriptionResult {
+    /// Transcript in `format`
+    pub text: String,
+    /// Format of the text
+    pub format: TranscriptionFormat,
+    /// Seconds spent transcribing and processing the text
+    pub processing_time: f64,
+    /// Path of the archived recording, if it was archived
+    pub archive_path: Option<String>,
+    /// Confidence of the transcript between 0 and 1, if known
+    pub confidence: Option<f32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// How to tell apart the speakers of a transcript
+pub enum Diarization {
+    /// Speaker turns marked by a tinydiarize model (`*-tdrz`), alternating between two speakers
+    SpeakerTurns,
+    /// Cluster the segments by voice on the CPU, into at most `max_speakers` speakers
+    Clustering { max_speakers: u8 },
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// Options for transcribing long audio in chunks.
+///
+/// All items are optional.
+pub struct ChunkingOptions {
+    /// Maximum seconds of audio per chunk, defaults to `30`
+    pub window_seconds: Option<u32>,
+    /// Seconds repeated in the next chunk when no pause is found, defaults to `2`
+    pub overlap_seconds: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize, Type)]
+/// Options for filtering segments whisper made up, e.g. on silence or when looping a phrase.
+///
+/// All items are optional.
+pub struct HallucinationFilterOptions {
+    /// No-speech probability above which an unsure segment counts as silence, defaults to `0.6`
+    pub no_speech_threshold: Option<f32>,
+    /// Average token log probability below which a segment is unsure, defaults to `-1.0`
+    pub logprob_threshold: Option<f32>,
+    /// Compression ratio above which a segment counts as repetition, defaults to `2.4`
+    pub compression_ratio_threshold: Option<f32>,
+    /// Phrases rejected in addition to the ones whisper is known to invent
+    pub blacklist: Option<Vec<String>>,
+    /// Decode again with increasing temperature while segments look bad, defaults to `true`
+    pub temperature_fallback: Option<bool>,
+    /// Keep bad segments and only flag them instead of dropping them, defaults to `false`
+    pub flag_only: Option<bool>,
 }
 
 #[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize, Type)]
@@ -269,7 +512,85 @@ pub struct TranscribeOptions {
     pub language: Option<String>,
     pub format: Option<TranscriptionFormat>,
     pub patience: Option<f32>,
+    /// Number of beams searched, defaults to `5`
+    #[serde(default)]
+    pub beam_size: Option<i32>,
     pub include_callback: Option<bool>,
+    /// Carry the tail of previous transcripts into the prompt, skipped if `None`
+    #[serde(default)]
+    pub previous_context: Option<PromptContextOptions>,
+    /// Name of the application the text is dictated into, used to group context
+    #[serde(default)]
+    pub target_application: Option<String>,
+    /// Label the segments with their speakers, skipped if `None`
+    #[serde(default)]
+    pub diarization: Option<Diarization>,
+    /// Languages to pick from when detecting the language, any language if `None`
+    #[serde(default)]
+    pub allowed_languages: Option<Vec<String>>,
+    /// Transcribe long audio in chunks with progress for each, in one pass if `None`
+    #[serde(default)]
+    pub chunking: Option<ChunkingOptions>,
+    /// Drop or flag hallucinated segments, keep every segment if `None`
+    #[serde(default)]
+    pub hallucination_filter: Option<HallucinationFilterOptions>,
+    /// Number of alternative texts to look for per segment, e.g. for corrections, none if `None`.
+    ///
+    /// Each one decodes the audio again, so at most `5` are looked for. Only kept in JSON
+    /// transcripts of the recorded audio.
+    #[serde(default)]
+    pub alternatives: Option<u8>,
+}
+
+impl TranscribeOptions {
+    /// Create new options where every item given in `overrides` replaces the current one.
+    #[must_use]
+    pub fn overridden_by(self, overrides: &Self) -> Self {
+        Self {
+            translate: overrides.translate.or(self.translate),
+            individual_word_timestamps: overrides
+                .individual_word_timestamps
+                .or(self.individual_word_timestamps),
+            threads: overrides.threads.or(self.threads),
+            initial_prompt: overrides.initial_prompt.clone().or(self.initial_prompt),
+            language: overrides.language.clone().or(self.language),
+            format: overrides.format.or(self.format),
+            patience: overrides.patience.or(self.patience),
+            beam_size: overrides.beam_size.or(self.beam_size),
+            include_callback: overrides.include_callback.or(self.include_callback),
+            previous_context: overrides.previous_context.clone().or(self.previous_context),
+            target_application: overrides
+                .target_application
+                .clone()
+                .or(self.target_application),
+            diarization: overrides.diarization.or(self.diarization),
+            allowed_languages: overrides
+                .allowed_languages
+                .clone()
+                .or(self.allowed_languages),
+            chunking: overrides.chunking.or(self.chunking),
+            hallucination_filter: overrides
+                .hallucination_filter
+                .clone()
+                .or(self.hallucination_filter),
+            alternatives: overrides.alternatives.or(self.alternatives),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize, Type)]
+/// Options for carrying previous transcripts into the next prompt.
+///
+/// All items are optional.
+pub struct PromptContextOptions {
+    /// Number of previous transcripts to use, defaults to `3`
+    pub previous_transcripts: Option<u8>,
+    /// Only use transcripts created within this many seconds, no limit if `None`
+    pub max_age_seconds: Option<u32>,
+    /// Only use transcripts dictated into the same application, defaults to `true`
+    pub same_application_only: Option<bool>,
+    /// Maximum number of characters taken from previous transcripts, defaults to `500`
+    pub max_characters: Option<u32>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Type)]
@@ -296,6 +617,106 @@ pub struct TextProcessOptions {
     pub replace_inter_sentence_newlines: Option<bool>,
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[non_exhaustive]
+/// How the final text is sent to the focused application
+pub enum OutputMethod {
+    /// Copy text to clipboard and paste it
+    #[default]
+    Paste,
+    /// Type each character of the text
+    Type,
+    /// Paste the text, then put the previous clipboard text back
+    PasteAndRestore,
+    /// Paste with the terminal shortcut (Ctrl+Shift+V, Cmd+V on macOS)
+    TerminalPaste,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[non_exhaustive]
+/// Layout of each entry appended to an output file
+pub enum FileEntryFormat {
+    /// Timestamp in brackets, followed by the text
+    #[default]
+    Text,
+    /// Timestamp as a level 2 heading, followed by the text
+    Markdown,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[non_exhaustive]
+/// Where the final text is sent
+pub enum OutputTarget {
+    /// The focused application, using an [`OutputMethod`]
+    #[default]
+    FocusedApplication,
+    /// Append to a text or Markdown file, with a timestamp header
+    File {
+        path: String,
+        format: FileEntryFormat,
+    },
+    /// Print to the standard output of the app process
+    Stdout,
+    /// Write a line to a named pipe (FIFO)
+    NamedPipe { path: String },
+    /// Write a line to a Unix domain socket
+    UnixSocket { path: String },
+    /// Run a command with the text on its standard input
+    Command { program: String, args: Vec<String> },
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+/// Options for sending text to the focused application or another target.
+///
+/// All items are optional.
+pub struct OutputOptions {
+    /// Where to send the text, defaults to [`OutputTarget::FocusedApplication`]
+    pub target: Option<OutputTarget>,
+    /// How to send the text, defaults to [`OutputMethod::Paste`]
+    pub method: Option<OutputMethod>,
+    /// Milliseconds to wait between typed chunks, only used by [`OutputMethod::Type`]
+    pub key_delay_ms: Option<u32>,
+    /// Number of characters typed at once, defaults to `1` with a key delay, otherwise the whole text
+    pub chunk_size: Option<u32>,
+    /// Milliseconds to wait before restoring the clipboard, defaults to `200`
+    pub restore_delay_ms: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize, Type)]
+/// Options for benchmarking transcription settings on this machine.
+///
+/// All items are optional.
+pub struct BenchmarkOptions {
+    /// WAV file to transcribe, defaults to a synthetic speech-like clip that only measures speed
+    pub audio_path: Option<String>,
+    /// Text spoken in `audio_path`, required with it
+    pub reference_text: Option<String>,
+    /// Language of the audio, defaults to `en` for the synthetic clip and detection otherwise
+    pub language: Option<String>,
+    /// Thread counts to try, defaults to half and all cores
+    pub threads: Option<Vec<u16>>,
+    /// Beam sizes to try, defaults to `1` and `5`
+    pub beam_sizes: Option<Vec<i32>>,
+    /// Also try with GPU if built with GPU support, defaults to `true`
+    pub try_gpu: Option<bool>,
+    /// Use the recommended settings for hotkey dictation and reload the model, defaults to `false`
+    pub apply: Option<bool>,
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize, Type)]
+/// Options used when a dictation is started and finished by a hotkey binding.
+///
+/// All items are optional.
+pub struct DictationOptions {
+    pub transcribe_options: Option<TranscribeOptions>,
+    /// Text post-processing, defaults to [`TextPostProcessing::Default`]
+    pub processing_options: Option<TextPostProcessing>,
+    pub decode_options: Option<AudioProcessingOptions>,
+    pub output_options: Option<OutputOptions>,
+    /// Show a notification instead of outputting transcripts less confident than this (0 to 1)
+    pub min_confidence: Option<f32>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Type)]
 /// Basic information about the current system
 pub struct SystemInfo {
@@ -346,3 +767,13 @@ impl TextPostProcessing {
         }
     }
 }
+
+#[test]
+fn test_recording_state_transitions() {
+    use RecordingState as S;
+    assert!(S::Recording.can_transition_to(S::Paused));
+    assert!(S::Paused.can_transition_to(S::Recording));
+    assert!(S::Paused.can_transition_to(S::Processing));
+    assert!(!S::Stopped.can_transition_to(S::Paused));
+    assert!(!S::Processing.can_transition_to(S::Paused));
+}
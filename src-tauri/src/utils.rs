@@ -18,3 +18,13 @@ pub fn will_send_to_sentry() -> bool {
 pub fn change_send_to_sentry(val: bool) {
     SEND_TO_SENTRY.store(val, ORDERING);
 }
+
+/// Whether whisper is built with a GPU backend: Metal on macOS, Vulkan on Windows and with the
+/// `vulkan` feature elsewhere
+pub const fn gpu_supported() -> bool {
+    cfg!(any(
+        feature = "vulkan",
+        target_os = "macos",
+        target_os = "windows"
+    ))
+}
@@ -1,4 +1,3 @@
-
 // This file was generated by [tauri-specta](https://github.com/oscartbeaumont/tauri-specta). Do not edit this file manually.
 
 /** user-defined commands **/
@@ -10,7 +9,7 @@ export const commands = {
  * 
  * Check [crate::mutter::Model::transcribe_audio] for details on arguments
  */
-async transcribe(audioData: number[], whisperOptions: TranscribeOptions | null, decodeOptions: AudioProcessingOptions | null) : Promise<Result<[string, number], string>> {
+async transcribe(audioData: number[], whisperOptions: TranscribeOptions | null, decodeOptions: AudioProcessingOptions | null) : Promise<Result<[string, number], AppError>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("transcribe", { audioData, whisperOptions, decodeOptions }) };
 } catch (e) {
@@ -21,7 +20,7 @@ async transcribe(audioData: number[], whisperOptions: TranscribeOptions | null,
 /**
  * Play the provided sound given its name that is stored in the app_state
  */
-async playSound(soundName: string) : Promise<Result<null, string>> {
+async playSound(soundName: string) : Promise<Result<null, AppError>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("play_sound", { soundName }) };
 } catch (e) {
@@ -32,7 +31,7 @@ async playSound(soundName: string) : Promise<Result<null, string>> {
 /**
  * Paste text from clipboard
  */
-async pasteText() : Promise<Result<null, string>> {
+async pasteText() : Promise<Result<null, AppError>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("paste_text") };
 } catch (e) {
@@ -43,7 +42,7 @@ async pasteText() : Promise<Result<null, string>> {
 /**
  * Process the text
  */
-async processText(text: string, options: TextProcessOptions | null) : Promise<Result<[string, number], string>> {
+async processText(text: string, options: TextProcessOptions | null) : Promise<Result<[string, number], AppError>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("process_text", { text, options }) };
 } catch (e) {
@@ -54,7 +53,7 @@ async processText(text: string, options: TextProcessOptions | null) : Promise<Re
 /**
  * Run [transcribe] function then pass to [process_text] for post processing.
  */
-async transcribeWithPostProcess(audioData: number[], transcribeOptions: TranscribeOptions | null, processingOptions: TextProcessOptions | null, decodeOptions: AudioProcessingOptions | null) : Promise<Result<[string, number], string>> {
+async transcribeWithPostProcess(audioData: number[], transcribeOptions: TranscribeOptions | null, processingOptions: TextProcessOptions | null, decodeOptions: AudioProcessingOptions | null) : Promise<Result<[string, number], AppError>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("transcribe_with_post_process", { audioData, transcribeOptions, processingOptions, decodeOptions }) };
 } catch (e) {
@@ -65,7 +64,7 @@ async transcribeWithPostProcess(audioData: number[], transcribeOptions: Transcri
 /**
  * Put window on top, can be overriden by optional parameter
  */
-async setWindowTop(overrideValue: boolean | null) : Promise<Result<null, string>> {
+async setWindowTop(overrideValue: boolean | null) : Promise<Result<null, AppError>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("set_window_top", { overrideValue }) };
 } catch (e) {
@@ -74,9 +73,9 @@ async setWindowTop(overrideValue: boolean | null) : Promise<Result<null, string>
 }
 },
 /**
- * Paste text from clipboard
+ * Type the text into the focused application
  */
-async writeText(text: string) : Promise<Result<null, string>> {
+async writeText(text: string) : Promise<Result<null, AppError>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("write_text", { text }) };
 } catch (e) {
@@ -87,7 +86,7 @@ async writeText(text: string) : Promise<Result<null, string>> {
 /**
  * Update the custom model information
  */
-async updateModel(path: string | null, useGpu: boolean | null) : Promise<Result<null, string>> {
+async updateModel(path: string | null, useGpu: boolean | null) : Promise<Result<null, AppError>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("update_model", { path, useGpu }) };
 } catch (e) {
@@ -101,10 +100,25 @@ async updateModel(path: string | null, useGpu: boolean | null) : Promise<Result<
 async getSystemInfo() : Promise<SystemInfo> {
     return await TAURI_INVOKE("get_system_info");
 },
+/**
+ * Transcribe a reference clip with several thread counts, beam sizes and GPU settings,
+ * measuring the real-time factor and word error rate of each.
+ * 
+ * Recommends the fastest settings about as accurate as the most accurate ones, and applies
+ * them to hotkey dictation and the model if asked to.
+ */
+async runBenchmark(options: BenchmarkOptions | null) : Promise<Result<BenchmarkReport, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("run_benchmark", { options }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
  * Initialize/De-initialize the sentry plugin depending on the toggled value
  */
-async sentryCrashReporterUpdate(enable: boolean) : Promise<Result<null, string>> {
+async sentryCrashReporterUpdate(enable: boolean) : Promise<Result<null, AppError>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("sentry_crash_reporter_update", { enable }) };
 } catch (e) {
@@ -112,7 +126,10 @@ async sentryCrashReporterUpdate(enable: boolean) : Promise<Result<null, string>>
     else return { status: "error", error: e  as any };
 }
 },
-async startMicrophoneRecording() : Promise<Result<boolean, string>> {
+/**
+ * Start recording microphone from backend, creating a new thread to listen for audio data.
+ */
+async startMicrophoneRecording() : Promise<Result<boolean, AppError>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("start_microphone_recording") };
 } catch (e) {
@@ -120,7 +137,13 @@ async startMicrophoneRecording() : Promise<Result<boolean, string>> {
     else return { status: "error", error: e  as any };
 }
 },
-async stopMicrophoneRecording(delay: number | null) : Promise<Result<null, string>> {
+/**
+ * Send a stop signal, after an optional delay, to the audio thread to finish recording.
+ * 
+ * Waits until all audio is stored, returning the path of the archived audio, if any, to pass
+ * on to [`transcribe_current_data`].
+ */
+async stopMicrophoneRecording(delay: number | null) : Promise<Result<string | null, AppError>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("stop_microphone_recording", { delay }) };
 } catch (e) {
@@ -128,23 +151,38 @@ async stopMicrophoneRecording(delay: number | null) : Promise<Result<null, strin
     else return { status: "error", error: e  as any };
 }
 },
-async transcribeCurrentData(transcribeOptions: TranscribeOptions | null, decodeOptions: AudioProcessingOptions | null) : Promise<Result<[string, number], string>> {
+/**
+ * Transcribe the current data that is in [`MicrophoneDataState`], fully consuming it on read.
+ * 
+ * `archive_path` is the path returned by [`stop_microphone_recording`], returned along with
+ * the transcript.
+ */
+async transcribeCurrentData(transcribeOptions: TranscribeOptions | null, decodeOptions: AudioProcessingOptions | null, archivePath: string | null) : Promise<Result<TranscriptionResult, AppError>> {
     try {
-    return { status: "ok", data: await TAURI_INVOKE("transcribe_current_data", { transcribeOptions, decodeOptions }) };
+    return { status: "ok", data: await TAURI_INVOKE("transcribe_current_data", { transcribeOptions, decodeOptions, archivePath }) };
 } catch (e) {
     if(e instanceof Error) throw e;
     else return { status: "error", error: e  as any };
 }
 },
-async transcribeCurrentThenProcess(transcribeOptions: TranscribeOptions | null, processingOptions: TextPostProcessing, decodeOptions: AudioProcessingOptions | null) : Promise<Result<[string, number], string>> {
+/**
+ * Transcribe the data from [`MicrophoneDataState`], then process the resulting text.
+ * 
+ * `archive_path` is the path returned by [`stop_microphone_recording`]. Structured formats
+ * like JSON are returned without processing.
+ */
+async transcribeCurrentThenProcess(transcribeOptions: TranscribeOptions | null, processingOptions: TextPostProcessing, decodeOptions: AudioProcessingOptions | null, archivePath: string | null) : Promise<Result<TranscriptionResult, AppError>> {
     try {
-    return { status: "ok", data: await TAURI_INVOKE("transcribe_current_then_process", { transcribeOptions, processingOptions, decodeOptions }) };
+    return { status: "ok", data: await TAURI_INVOKE("transcribe_current_then_process", { transcribeOptions, processingOptions, decodeOptions, archivePath }) };
 } catch (e) {
     if(e instanceof Error) throw e;
     else return { status: "error", error: e  as any };
 }
 },
-async stopTranscribeAndProcessData(stopMicTime: number | null, transcribeOptions: TranscribeOptions | null, processingOptions: TextPostProcessing, decodeOptions: AudioProcessingOptions | null) : Promise<Result<[string, number], string>> {
+/**
+ * Stop the microphone, then transcribe the audio, and finally post-processing the text.
+ */
+async stopTranscribeAndProcessData(stopMicTime: number | null, transcribeOptions: TranscribeOptions | null, processingOptions: TextPostProcessing, decodeOptions: AudioProcessingOptions | null) : Promise<Result<TranscriptionResult, AppError>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("stop_transcribe_and_process_data", { stopMicTime, transcribeOptions, processingOptions, decodeOptions }) };
 } catch (e) {
@@ -152,7 +190,12 @@ async stopTranscribeAndProcessData(stopMicTime: number | null, transcribeOptions
     else return { status: "error", error: e  as any };
 }
 },
-async setInputDevice(index: number) : Promise<Result<boolean, string>> {
+/**
+ * Set the current input device on user's system from Host by its position in [`get_input_devices`].
+ * 
+ * Prefer [`set_input_device_by_name`], since positions change when devices are plugged in or out.
+ */
+async setInputDevice(index: number) : Promise<Result<boolean, AppError>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("set_input_device", { index }) };
 } catch (e) {
@@ -160,7 +203,23 @@ async setInputDevice(index: number) : Promise<Result<boolean, string>> {
     else return { status: "error", error: e  as any };
 }
 },
-async getInputDevices() : Promise<Result<string[], string>> {
+/**
+ * Set the current input device by name, using the default device when `None` or not found.
+ * 
+ * The device is remembered and selected again whenever it is available.
+ */
+async setInputDeviceByName(name: string | null) : Promise<Result<boolean, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_input_device_by_name", { name }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Get all possible input devices on user's system.
+ */
+async getInputDevices() : Promise<Result<string[], AppError>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("get_input_devices") };
 } catch (e) {
@@ -168,146 +227,1091 @@ async getInputDevices() : Promise<Result<string[], string>> {
     else return { status: "error", error: e  as any };
 }
 },
-async getCurrentInputDevice() : Promise<Result<string | null, string>> {
+/**
+ * Get the currently active input device (the one that will record audio data).
+ */
+async getCurrentInputDevice() : Promise<Result<string | null, AppError>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("get_current_input_device") };
 } catch (e) {
     if(e instanceof Error) throw e;
     else return { status: "error", error: e  as any };
 }
+},
+/**
+ * Get the stream configurations supported by the named input device, or the current one if `None`
+ */
+async getInputStreamConfigs(name: string | null) : Promise<Result<SupportedInputConfig[], AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_input_stream_configs", { name }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
 }
+},
+/**
+ * Set the stream configuration of the current input device, `None` for the device's default.
+ * 
+ * Used from the next recording on.
+ */
+async setInputStreamConfig(config: InputStreamConfig | null) : Promise<Result<null, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_input_stream_config", { config }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
 }
-
-/** user-defined events **/
-
-
-export const events = __makeEvents__<{
-modKeyEvent: ModKeyEvent,
-mouseClickEvent: MouseClickEvent,
-transcriptionProgressEvent: TranscriptionProgressEvent,
-transcriptionSegmentEvent: TranscriptionSegmentEvent
-}>({
-modKeyEvent: "mod-key-event",
-mouseClickEvent: "mouse-click-event",
-transcriptionProgressEvent: "transcription-progress-event",
-transcriptionSegmentEvent: "transcription-segment-event"
-})
-
-/** user-defined constants **/
-
-
-
-/** user-defined types **/
-
+},
 /**
- * Options for the processing audio for [`crate::mutter::decode_and_denoise`] function.
+ * Set the maximum duration of a recording and what happens when it is reached, saving them in
+ * the settings.
  * 
- * All items are optional.
+ * Used from the next recording on.
  */
-export type AudioProcessingOptions = { 
+async setCaptureLimits(limits: CaptureLimits) : Promise<Result<null, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_capture_limits", { limits }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Wheter to normalize audio, defaults to `false`
+ * Archive the raw audio of every recording as a WAV file in `directory`, or stop if `None`.
+ * 
+ * The path of the file is returned with the transcript of the recording. Saved in the settings
+ * and used from the next recording on.
  */
-normalize_result: boolean | null; 
+async setRecordingArchive(directory: string | null) : Promise<Result<null, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_recording_archive", { directory }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Wheter to denoise audio, defaults to `true`
+ * Get the monitor sources of PulseAudio or PipeWire, which record the audio played on an output
  */
-denoise_audio: boolean | null; 
+async getSystemAudioSources() : Promise<Result<string[], AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_system_audio_sources") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Value for low pass filter, this represents maximum frequency allowed, default is `3000`
+ * Record system audio from a monitor source along with the microphone, or stop if `None`.
+ * 
+ * Saved in the settings and used from the next recording on.
  */
-low_pass_value: number | null; 
+async setSystemAudioCapture(options: SystemAudioOptions | null) : Promise<Result<null, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_system_audio_capture", { options }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Value for high pass filter, this represents minimum frequency allowed, default is `200`
+ * Pause the current recording, keeping the audio recorded so far
  */
-high_pass_value: number | null }
+async pauseMicrophoneRecording() : Promise<Result<null, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("pause_microphone_recording") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Tauri event representing a modifier key press globally
- * 
- * ### Payload
- * 
- * [`ModKeyPayload`] : The modifer key that is pressed/released
+ * Resume a paused recording, appending to the same audio
  */
-export type ModKeyEvent = ModKeyPayload
+async resumeMicrophoneRecording() : Promise<Result<null, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("resume_microphone_recording") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Information about modifier key event (pressed or released)
+ * Forget all previous transcripts used as context for the next prompt.
  */
-export type ModKeyPayload = { key: string; is_pressed: boolean }
+async clearTranscriptHistory() : Promise<Result<null, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("clear_transcript_history") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Enum representing mouse button type
+ * Replace `original` with `replacement`, e.g. an alternative of a segment, in a remembered
+ * transcript, the last one if `entry_id` is `None`.
  * 
- * Main three are left, middle, and right
+ * ### Returns
+ * The corrected text of the transcript
  */
-export type MouseButtonType = "Left" | "Middle" | "Right"
+async correctTranscript(original: string, replacement: string, entryId: number | null) : Promise<Result<string, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("correct_transcript", { original, replacement, entryId }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Tauri event representing mouse click globally
- * 
- * ### Payload
- * 
- * [`MouseButtonType`] : Which button was pressed
+ * Get how often misrecognized words were corrected, most frequent first.
  */
-export type MouseClickEvent = MouseButtonType
+async getCorrections() : Promise<Result<CorrectionCount[], AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_corrections") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Basic information about the current system
+ * Replace all application profiles, saving them in the settings
  */
-export type SystemInfo = { 
+async setApplicationProfiles(profiles: ApplicationProfile[]) : Promise<Result<null, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_application_profiles", { profiles }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Total number of cores on this system
+ * Get all application profiles
  */
-cpu_core_count: number; 
+async getApplicationProfiles() : Promise<Result<ApplicationProfile[], AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_application_profiles") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Total amount of system memory (RAM) in the system in GB
+ * Get the currently focused window, `None` if it cannot be detected on this system
  */
-total_memory_gb: number; 
+async getActiveWindow() : Promise<Result<WindowInfo | null, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_active_window") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Total amount of graphic memory (VRAM) in the system in GB
+ * Get the window and profile selected when the current (or last) dictation started
  */
-total_vram_gb: number }
+async getActiveProfile() : Promise<Result<ActiveProfile, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_active_profile") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Ways a text can be decorated
+ * Send the text to the focused application.
+ * 
+ * Uses the method from the options, or the active profile's method if not given, then the
+ * one from the settings.
  */
-export type TextDecoration = "Bold" | "Italics" | "Underline" | "Strikethrough" | "Mark"
-export type TextPostProcessing = "Skip" | "Default" | { Custom: TextProcessOptions }
+async outputText(text: string, options: OutputOptions | null) : Promise<Result<null, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("output_text", { text, options }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Options for the text post-processing function.
+ * Start the local HTTP/WebSocket API on `127.0.0.1`, replacing a running one.
  * 
- * All items are optional.
+ * A random token is generated if none is given. The API is saved in the settings as enabled,
+ * so it is started with the app from now on.
  */
-export type TextProcessOptions = { 
+async startApiServer(port: number | null, token: string | null) : Promise<Result<ApiServerInfo, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("start_api_server", { port, token }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Words that will be removed (or striken) from the string
+ * Stop the local API, returns whether it was running.
+ * 
+ * The API is saved in the settings as disabled, so it is no longer started with the app.
  */
-removed_words: string[] | null; 
+async stopApiServer() : Promise<Result<boolean, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("stop_api_server") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Words to modify in someway that does not change meaning of word,
- * but adds some decoration
+ * Get the port and token of the local API, `None` if it is not running
  */
-decorated_words: ([TextDecoration, string])[] | null; replace_inter_sentence_newlines: boolean | null }
+async getApiServerInfo() : Promise<Result<ApiServerInfo | null, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_api_server_info") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Options for the transcribing function.
- * 
- * All items are optional. Based on arguments for [`crate::mutter::Model::transcribe_audio`].
+ * Replace all global hotkey and mouse-button bindings, saving them in the settings
  */
-export type TranscribeOptions = { translate: boolean | null; individual_word_timestamps: boolean | null; threads: number | null; initial_prompt: string | null; language: string | null; format: TranscriptionFormat | null; patience: number | null; include_callback: boolean | null }
+async setHotkeyBindings(bindings: HotkeyBinding[]) : Promise<Result<null, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_hotkey_bindings", { bindings }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Format type for a transcription
+ * Get all global hotkey and mouse-button bindings
  */
-export type TranscriptionFormat = "Text" | "SRT" | "VTT"
+async getHotkeyBindings() : Promise<Result<HotkeyBinding[], AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_hotkey_bindings") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Tauri event representing mouse movement
- * 
- * ### Payload
- * 
- * x [i32] : Absolute X value of mosue (from 0 to `SCREEN_WIDTH`)
- * y [i32] : Absolute Y value of mouse (from 0 to `SCREEN_HEIGHT`)
- * Event representing the progress for the current transcription
- * 
- * ### Payload
+ * Set the options used for dictations run by hotkey bindings, saving them in the settings.
+ * 
+ * They are also the defaults of the other commands and the local API.
+ */
+async setDictationOptions(options: DictationOptions) : Promise<Result<null, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_dictation_options", { options }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Get the settings shared by the commands, the local API, hotkey dictation and the CLI
+ */
+async getSettings() : Promise<Result<Settings, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_settings") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Replace all settings, `None` to reset them to the defaults.
+ * 
+ * The settings are validated and saved, then the hotkey bindings, model and local API are
+ * updated to match.
+ * 
+ * ### Returns
+ * The settings after the change
+ */
+async updateSettings(settings: Settings | null) : Promise<Result<Settings, AppError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("update_settings", { settings }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+}
+}
+
+/** user-defined events **/
+
+
+export const events = __makeEvents__<{
+activeProfileChangedEvent: ActiveProfileChangedEvent,
+dictationCompletedEvent: DictationCompletedEvent,
+hotkeyTriggeredEvent: HotkeyTriggeredEvent,
+inputDevicesChangedEvent: InputDevicesChangedEvent,
+inputLevelEvent: InputLevelEvent,
+inputStreamErrorEvent: InputStreamErrorEvent,
+languageDetectedEvent: LanguageDetectedEvent,
+modKeyEvent: ModKeyEvent,
+mouseClickEvent: MouseClickEvent,
+mouseReleaseEvent: MouseReleaseEvent,
+recordingLimitReachedEvent: RecordingLimitReachedEvent,
+recordingStateChangedEvent: RecordingStateChangedEvent,
+settingsChangedEvent: SettingsChangedEvent,
+silenceWarningEvent: SilenceWarningEvent,
+transcriptionProgressEvent: TranscriptionProgressEvent,
+transcriptionSegmentEvent: TranscriptionSegmentEvent
+}>({
+activeProfileChangedEvent: "active-profile-changed-event",
+dictationCompletedEvent: "dictation-completed-event",
+hotkeyTriggeredEvent: "hotkey-triggered-event",
+inputDevicesChangedEvent: "input-devices-changed-event",
+inputLevelEvent: "input-level-event",
+inputStreamErrorEvent: "input-stream-error-event",
+languageDetectedEvent: "language-detected-event",
+modKeyEvent: "mod-key-event",
+mouseClickEvent: "mouse-click-event",
+mouseReleaseEvent: "mouse-release-event",
+recordingLimitReachedEvent: "recording-limit-reached-event",
+recordingStateChangedEvent: "recording-state-changed-event",
+settingsChangedEvent: "settings-changed-event",
+silenceWarningEvent: "silence-warning-event",
+transcriptionProgressEvent: "transcription-progress-event",
+transcriptionSegmentEvent: "transcription-segment-event"
+})
+
+/** user-defined constants **/
+
+
+
+/** user-defined types **/
+
+/**
+ * The focused window and matching profile at the start of a dictation
+ */
+export type ActiveProfile = { window: WindowInfo | null; profile: ApplicationProfile | null }
+/**
+ * Event representing the application profile chosen when a dictation starts
+ * 
+ * ### Payload
+ * 
+ * [`ActiveProfile`] : The focused window and its matching profile (if any)
+ */
+export type ActiveProfileChangedEvent = ActiveProfile
+/**
+ * Information about the running API server
+ */
+export type ApiServerInfo = { 
+/**
+ * Port the server listens on (always on `127.0.0.1`)
+ */
+port: number; 
+/**
+ * Token clients must send with each request
+ */
+token: string }
+/**
+ * Settings of the local HTTP/WebSocket API
+ */
+export type ApiServerSettings = { 
+/**
+ * Start the API with the app
+ */
+enabled: boolean; 
+/**
+ * Port on `127.0.0.1`, defaults to [`crate::server::DEFAULT_API_PORT`]
+ */
+port: number | null; 
+/**
+ * Token clients must send, a random one is generated on every start if `None`
+ */
+token: string | null }
+/**
+ * Error returned by a command
+ */
+export type AppError = { code: ErrorCode; 
+/**
+ * Description of what went wrong
+ */
+message: string; 
+/**
+ * What the user can do about it, see [`ErrorCode::hint`]
+ */
+hint: string }
+/**
+ * Settings that replace the global ones when dictating into a matching window.
+ */
+export type ApplicationProfile = { 
+/**
+ * Display name of the profile
+ */
+name: string; 
+/**
+ * Which windows use this profile
+ */
+matcher: ProfileMatcher; 
+/**
+ * Transcription options, each given item overrides the global value
+ */
+transcribe_options: TranscribeOptions | null; 
+/**
+ * Text post-processing options, replaces the global options if given
+ */
+processing_options: TextProcessOptions | null; 
+/**
+ * How the final text is sent to the application
+ */
+output_method: OutputMethod | null; 
+/**
+ * Where the final text is sent, the focused application if `None`
+ */
+output_target: OutputTarget | null }
+/**
+ * Options for the processing audio for [`crate::mutter::decode_and_denoise`] function.
+ * 
+ * All items are optional.
+ */
+export type AudioProcessingOptions = { 
+/**
+ * Wheter to normalize audio, defaults to `false`
+ */
+normalize_result: boolean | null; 
+/**
+ * Wheter to denoise audio, defaults to `true`
+ */
+denoise_audio: boolean | null; 
+/**
+ * Value for low pass filter, this represents maximum frequency allowed, default is `3000`
+ */
+low_pass_value: number | null; 
+/**
+ * Value for high pass filter, this represents minimum frequency allowed, default is `200`
+ */
+high_pass_value: number | null }
+/**
+ * Settings the reference clip is transcribed with
+ */
+export type BenchmarkConfiguration = { threads: number; beam_size: number; use_gpu: boolean }
+/**
+ * Options for benchmarking transcription settings on this machine.
+ * 
+ * All items are optional.
+ */
+export type BenchmarkOptions = { 
+/**
+ * WAV file to transcribe, defaults to a synthetic speech-like clip that only measures speed
+ */
+audio_path: string | null; 
+/**
+ * Text spoken in `audio_path`, required with it
+ */
+reference_text: string | null; 
+/**
+ * Language of the audio, defaults to `en` for the synthetic clip and detection otherwise
+ */
+language: string | null; 
+/**
+ * Thread counts to try, defaults to half and all cores
+ */
+threads: number[] | null; 
+/**
+ * Beam sizes to try, defaults to `1` and `5`
+ */
+beam_sizes: number[] | null; 
+/**
+ * Also try with GPU if built with GPU support, defaults to `true`
+ */
+try_gpu: boolean | null; 
+/**
+ * Use the recommended settings for hotkey dictation and reload the model, defaults to `false`
+ */
+apply: boolean | null }
+/**
+ * Results of a benchmark with the recommended settings
+ */
+export type BenchmarkReport = { results: BenchmarkResult[]; 
+/**
+ * Fastest configuration about as accurate as the most accurate one
+ */
+recommended: BenchmarkConfiguration | null; 
+/**
+ * Whether the recommended settings were applied
+ */
+applied: boolean }
+/**
+ * Speed and accuracy of one configuration
+ */
+export type BenchmarkResult = { configuration: BenchmarkConfiguration; 
+/**
+ * Seconds of processing per second of audio, below `1` is faster than real time
+ */
+real_time_factor: number; 
+/**
+ * Word edits needed per reference word, `None` without a reference text
+ */
+word_error_rate: number | null }
+/**
+ * Action done when a binding is activated
+ */
+export type BindingAction = "ToggleRecording" | "StartRecording" | "StopRecording" | "PushToTalk" | "Cancel" | "RePaste"
+/**
+ * How pressing a chord activates its action
+ */
+export type BindingMode = "Toggle" | "Hold" | "DoubleClick"
+/**
+ * Limits of a single recording.
+ * 
+ * All items are optional.
+ */
+export type CaptureLimits = { 
+/**
+ * Maximum seconds of audio kept, unbounded if not set
+ */
+max_duration_seconds: number | null; 
+/**
+ * Defaults to [`OverflowPolicy::Stop`]
+ */
+overflow: OverflowPolicy | null }
+/**
+ * Where captured audio comes from
+ */
+export type CaptureSource = "Microphone" | "System"
+/**
+ * A trigger together with the exact set of modifiers that must be held
+ */
+export type Chord = { modifiers: Modifier[]; trigger: Trigger }
+/**
+ * Options for transcribing long audio in chunks.
+ * 
+ * All items are optional.
+ */
+export type ChunkingOptions = { 
+/**
+ * Maximum seconds of audio per chunk, defaults to `30`
+ */
+window_seconds: number | null; 
+/**
+ * Seconds repeated in the next chunk when no pause is found, defaults to `2`
+ */
+overlap_seconds: number | null }
+/**
+ * How often a misrecognized text was corrected to another
+ */
+export type CorrectionCount = { 
+/**
+ * Words as recognized
+ */
+original: string; 
+/**
+ * Words they were corrected to
+ */
+replacement: string; count: number }
+/**
+ * How to tell apart the speakers of a transcript
+ */
+export type Diarization = "SpeakerTurns" | { Clustering: { max_speakers: number } }
+/**
+ * Event representing a dictation finished by a hotkey binding
+ * 
+ * ### Payload
+ * 
+ * - `text` [`String`] : The processed transcript
+ * - `processing_time` [f64] : Seconds spent transcribing and processing
+ * - `archive_path` [`Option<String>`] : File the recording was archived to, if archiving
+ * - `confidence` [`Option<f32>`] : Confidence of the transcript between 0 and 1, if known
+ */
+export type DictationCompletedEvent = { text: string; processing_time: number; archive_path: string | null; confidence: number | null }
+/**
+ * Options used when a dictation is started and finished by a hotkey binding.
+ * 
+ * All items are optional.
+ */
+export type DictationOptions = { transcribe_options: TranscribeOptions | null; 
+/**
+ * Text post-processing, defaults to [`TextPostProcessing::Default`]
+ */
+processing_options: TextPostProcessing | null; decode_options: AudioProcessingOptions | null; output_options: OutputOptions | null; 
+/**
+ * Show a notification instead of outputting transcripts less confident than this (0 to 1)
+ */
+min_confidence: number | null }
+/**
+ * Kind of failure, telling the frontend what the user can do about it
+ */
+export type ErrorCode = "ModelLoad" | "Decode" | "Transcription" | "Device" | "Permission" | "Busy" | "Cancelled" | "NotRecording" | "Io" | "InputInjection" | "InvalidSettings" | "InvalidInput" | "NotFound" | "Internal"
+/**
+ * Layout of each entry appended to an output file
+ */
+export type FileEntryFormat = "Text" | "Markdown"
+/**
+ * Options for filtering segments whisper made up, e.g. on silence or when looping a phrase.
+ * 
+ * All items are optional.
+ */
+export type HallucinationFilterOptions = { 
+/**
+ * No-speech probability above which an unsure segment counts as silence, defaults to `0.6`
+ */
+no_speech_threshold: number | null; 
+/**
+ * Average token log probability below which a segment is unsure, defaults to `-1.0`
+ */
+logprob_threshold: number | null; 
+/**
+ * Compression ratio above which a segment counts as repetition, defaults to `2.4`
+ */
+compression_ratio_threshold: number | null; 
+/**
+ * Phrases rejected in addition to the ones whisper is known to invent
+ */
+blacklist: string[] | null; 
+/**
+ * Decode again with increasing temperature while segments look bad, defaults to `true`
+ */
+temperature_fallback: boolean | null; 
+/**
+ * Keep bad segments and only flag them instead of dropping them, defaults to `false`
+ */
+flag_only: boolean | null }
+/**
+ * A chord bound to an action
+ */
+export type HotkeyBinding = { chord: Chord; mode: BindingMode; action: BindingAction; 
+/**
+ * Ignore presses within this many milliseconds of the previous one, defaults to `50`
+ */
+debounce_ms: number | null; 
+/**
+ * Maximum milliseconds between presses of a double-click, defaults to `400`
+ */
+double_click_ms: number | null; 
+/**
+ * Minimum milliseconds a [`BindingMode::Hold`] chord must be held, shorter holds are
+ * reported as [`TriggerPhase::Cancelled`], defaults to `250`
+ */
+min_hold_ms: number | null }
+/**
+ * A binding's action that should be executed
+ */
+export type HotkeyTrigger = { action: BindingAction; phase: TriggerPhase }
+/**
+ * Event representing a hotkey binding that was activated
+ * 
+ * ### Payload
+ * 
+ * [`HotkeyTrigger`] : The bound action and whether the chord was pressed or released
+ */
+export type HotkeyTriggeredEvent = HotkeyTrigger
+/**
+ * Event representing a change of the available or selected input devices
+ * 
+ * ### Payload
+ * 
+ * - `devices` [`Vec<String>`] : Names of all available input devices
+ * - `current` [`Option<String>`] : Name of the selected input device
+ */
+export type InputDevicesChangedEvent = { devices: string[]; current: string | null }
+/**
+ * Levels of the input over one window, all values are linear from `0.0` to `1.0`
+ */
+export type InputLevel = { 
+/**
+ * Source the level was measured on
+ */
+source: CaptureSource; rms: number; peak: number; 
+/**
+ * Whether any sample in the window reached full scale
+ */
+clipping: boolean }
+/**
+ * Event representing the input level while recording, sent at most every 100 ms
+ * 
+ * ### Payload
+ * 
+ * [`InputLevel`] : RMS and peak level, and whether the input clipped
+ */
+export type InputLevelEvent = InputLevel
+/**
+ * Sample formats that can be recorded, all are converted to `f32`
+ */
+export type InputSampleFormat = "F32" | "I16" | "U16" | "I32"
+/**
+ * Stream configuration chosen by the user for recording
+ */
+export type InputStreamConfig = { sample_format: InputSampleFormat; sample_rate: number; channels: number; 
+/**
+ * Frames per buffer, uses the device's default when `None`
+ */
+buffer_size: number | null }
+/**
+ * Event representing an error of the input stream, e.g. an unsupported configuration
+ * 
+ * ### Payload
+ * 
+ * [`AppError`] : Code, description and hint of the error
+ */
+export type InputStreamErrorEvent = AppError
+/**
+ * Event representing the detected spoken language of a transcription
+ * 
+ * ### Payload
+ * 
+ * [`LanguageDetection`] : Language used, and the most likely languages with probabilities
+ */
+export type LanguageDetectedEvent = LanguageDetection
+/**
+ * Result of detecting the spoken language
+ */
+export type LanguageDetection = { 
+/**
+ * Language the audio was transcribed in
+ */
+language: string; 
+/**
+ * Most likely languages, most likely first
+ */
+candidates: LanguageProbability[] }
+/**
+ * Probability of the audio being in a language
+ */
+export type LanguageProbability = { 
+/**
+ * Whisper language code, e.g. `en` or `de`
+ */
+language: string; probability: number }
+/**
+ * Tauri event representing a modifier key press globally
+ * 
+ * ### Payload
+ * 
+ * [`ModKeyPayload`] : The modifer key that is pressed/released
+ */
+export type ModKeyEvent = ModKeyPayload
+/**
+ * Information about modifier key event (pressed or released)
+ */
+export type ModKeyPayload = { key: string; is_pressed: boolean }
+/**
+ * Modifier keys, left and right variants are treated the same
+ */
+export type Modifier = "Control" | "Shift" | "Alt" | "Meta"
+/**
+ * Enum representing mouse button type
+ * 
+ * Main three are left, middle, and right
+ */
+export type MouseButtonType = "Left" | "Middle" | "Right"
+/**
+ * Tauri event representing mouse click globally
+ * 
+ * ### Payload
+ * 
+ * [`MouseButtonType`] : Which button was pressed
+ */
+export type MouseClickEvent = MouseButtonType
+/**
+ * Tauri event representing mouse button release globally
+ * 
+ * ### Payload
+ * 
+ * [`MouseButtonType`] : Which button was released
+ */
+export type MouseReleaseEvent = MouseButtonType
+/**
+ * How the final text is sent to the focused application
+ */
+export type OutputMethod = "Paste" | "Type" | "PasteAndRestore" | "TerminalPaste"
+/**
+ * Options for sending text to the focused application or another target.
+ * 
+ * All items are optional.
+ */
+export type OutputOptions = { 
+/**
+ * Where to send the text, defaults to [`OutputTarget::FocusedApplication`]
+ */
+target: OutputTarget | null; 
+/**
+ * How to send the text, defaults to [`OutputMethod::Paste`]
+ */
+method: OutputMethod | null; 
+/**
+ * Milliseconds to wait between typed chunks, only used by [`OutputMethod::Type`]
+ */
+key_delay_ms: number | null; 
+/**
+ * Number of characters typed at once, defaults to `1` with a key delay, otherwise the whole text
+ */
+chunk_size: number | null; 
+/**
+ * Milliseconds to wait before restoring the clipboard, defaults to `200`
+ */
+restore_delay_ms: number | null }
+/**
+ * Where the final text is sent
+ */
+export type OutputTarget = "FocusedApplication" | { File: { path: string; format: FileEntryFormat } } | "Stdout" | { NamedPipe: { path: string } } | { UnixSocket: { path: string } } | { Command: { program: string; args: string[] } }
+/**
+ * What to do when a recording reaches its maximum duration
+ */
+export type OverflowPolicy = "Stop" | "DropOldest"
+/**
+ * Rule deciding whether a window belongs to a profile.
+ * 
+ * Every given item must match, comparison ignores case.
+ */
+export type ProfileMatcher = { 
+/**
+ * Process name must be exactly this value
+ */
+process_name: string | null; 
+/**
+ * Window title must contain this value
+ */
+title_contains: string | null }
+/**
+ * Options for carrying previous transcripts into the next prompt.
+ * 
+ * All items are optional.
+ */
+export type PromptContextOptions = { 
+/**
+ * Number of previous transcripts to use, defaults to `3`
+ */
+previous_transcripts: number | null; 
+/**
+ * Only use transcripts created within this many seconds, no limit if `None`
+ */
+max_age_seconds: number | null; 
+/**
+ * Only use transcripts dictated into the same application, defaults to `true`
+ */
+same_application_only: boolean | null; 
+/**
+ * Maximum number of characters taken from previous transcripts, defaults to `500`
+ */
+max_characters: number | null }
+/**
+ * Event representing a recording that reached its maximum duration
+ * 
+ * ### Payload
+ * 
+ * - `max_duration_seconds` [u32] : The configured limit
+ * - `policy` [`OverflowPolicy`] : Whether recording stopped or keeps dropping the oldest audio
+ */
+export type RecordingLimitReachedEvent = { max_duration_seconds: number; policy: OverflowPolicy }
+/**
+ * Settings of the microphone recording
+ */
+export type RecordingSettings = { 
+/**
+ * Input device selected whenever it is available, the default device if `None`
+ */
+preferred_device: string | null; capture_limits: CaptureLimits; 
+/**
+ * Directory the raw audio of every recording is archived to, not archived if `None`
+ */
+archive_directory: string | null; 
+/**
+ * System audio recorded along with the microphone, if any
+ */
+system_audio: SystemAudioOptions | null }
+/**
+ * Current state of the microphone
+ */
+export type RecordingState = "Stopped" | "Recording" | "Paused" | "Processing"
+/**
+ * Event representing a change of the recording state
+ * 
+ * ### Payload
+ * 
+ * [`RecordingState`] : The new state
+ */
+export type RecordingStateChangedEvent = RecordingState
+/**
+ * Settings of the app, every item missing from the file takes its default
+ */
+export type Settings = { 
+/**
+ * Schema version, see [`SETTINGS_VERSION`]
+ */
+version: number; 
+/**
+ * Run the model on the GPU, defaults to `true` on builds with GPU support
+ */
+use_gpu: boolean | null; 
+/**
+ * Defaults for the options of commands, the local API and hotkey dictation.
+ * 
+ * Options given with a call take precedence.
+ */
+dictation: DictationOptions; 
+/**
+ * Global hotkey and mouse-button bindings
+ */
+hotkey_bindings: HotkeyBinding[]; api_server: ApiServerSettings; 
+/**
+ * Application profiles, the first one matching the focused window is used
+ */
+profiles: ApplicationProfile[]; recording: RecordingSettings }
+/**
+ * Event representing changed settings, e.g. by the frontend or by applying a benchmark
+ * 
+ * ### Payload
+ * 
+ * [`Settings`] : All settings after the change
+ */
+export type SettingsChangedEvent = Settings
+/**
+ * Event warning that the input has been silent while recording, e.g. because the wrong mic is selected
+ * 
+ * ### Payload
+ * 
+ * [f64] : Number of seconds the input has been silent
+ */
+export type SilenceWarningEvent = number
+/**
+ * A range of stream configurations supported by an input device
+ */
+export type SupportedInputConfig = { sample_format: InputSampleFormat; channels: number; min_sample_rate: number; max_sample_rate: number; 
+/**
+ * Smallest buffer size in frames, `None` if unknown
+ */
+min_buffer_size: number | null; 
+/**
+ * Largest buffer size in frames, `None` if unknown
+ */
+max_buffer_size: number | null }
+/**
+ * Options for recording the audio played on the machine
+ */
+export type SystemAudioOptions = { 
+/**
+ * Monitor source to record, e.g. `alsa_output.pci-0000_00_1f.3.analog-stereo.monitor`
+ */
+source: string; 
+/**
+ * Mix into the microphone recording, otherwise both are transcribed on their own and merged.
+ * 
+ * Utterances are tagged with their source either way.
+ */
+mix_with_microphone: boolean }
+/**
+ * Basic information about the current system
+ */
+export type SystemInfo = { 
+/**
+ * Total number of cores on this system
+ */
+cpu_core_count: number; 
+/**
+ * Total amount of system memory (RAM) in the system in GB
+ */
+total_memory_gb: number; 
+/**
+ * Total amount of graphic memory (VRAM) in the system in GB
+ */
+total_vram_gb: number }
+/**
+ * Ways a text can be decorated
+ */
+export type TextDecoration = "Bold" | "Italics" | "Underline" | "Strikethrough" | "Mark"
+/**
+ * The kind of text post-processing to use from [`TextProcessOptions`]
+ */
+export type TextPostProcessing = "Skip" | "Default" | { Custom: TextProcessOptions }
+/**
+ * Options for the text post-processing function.
+ * 
+ * All items are optional.
+ */
+export type TextProcessOptions = { 
+/**
+ * Words that will be removed (or striken) from the string
+ */
+removed_words: string[] | null; 
+/**
+ * Words to modify in someway that does not change meaning of word,
+ * but adds some decoration
+ */
+decorated_words: ([TextDecoration, string])[] | null; replace_inter_sentence_newlines: boolean | null }
+/**
+ * Options for the transcribing function.
+ * 
+ * All items are optional. Based on arguments for [`crate::mutter::Model::transcribe_audio`].
+ */
+export type TranscribeOptions = { translate: boolean | null; individual_word_timestamps: boolean | null; threads: number | null; initial_prompt: string | null; language: string | null; format: TranscriptionFormat | null; patience: number | null; 
+/**
+ * Number of beams searched, defaults to `5`
+ */
+beam_size: number | null; include_callback: boolean | null; 
+/**
+ * Carry the tail of previous transcripts into the prompt, skipped if `None`
+ */
+previous_context: PromptContextOptions | null; 
+/**
+ * Name of the application the text is dictated into, used to group context
+ */
+target_application: string | null; 
+/**
+ * Label the segments with their speakers, skipped if `None`
+ */
+diarization: Diarization | null; 
+/**
+ * Languages to pick from when detecting the language, any language if `None`
+ */
+allowed_languages: string[] | null; 
+/**
+ * Transcribe long audio in chunks with progress for each, in one pass if `None`
+ */
+chunking: ChunkingOptions | null; 
+/**
+ * Drop or flag hallucinated segments, keep every segment if `None`
+ */
+hallucination_filter: HallucinationFilterOptions | null; 
+/**
+ * Number of alternative texts to look for per segment, e.g. for corrections, none if `None`.
+ * 
+ * Each one decodes the audio again, so at most `5` are looked for. Only kept in JSON
+ * transcripts of the recorded audio.
+ */
+alternatives: number | null }
+/**
+ * Format type for a transcription
+ */
+export type TranscriptionFormat = "Text" | "SRT" | "VTT" | "Json"
+/**
+ * Tauri event representing mouse movement
+ * 
+ * ### Payload
+ * 
+ * x [i32] : Absolute X value of mosue (from 0 to `SCREEN_WIDTH`)
+ * y [i32] : Absolute Y value of mouse (from 0 to `SCREEN_HEIGHT`)
+ * Event representing the progress for the current transcription
+ * 
+ * ### Payload
  * 
  * [i32] : The integer percentage value (0-100)
  * _NOTE: Whisper Transcription progress is not very granular._
  */
 export type TranscriptionProgressEvent = number
+/**
+ * Transcript of the recorded audio, processed if asked for
+ */
+export type TranscriptionResult = { 
+/**
+ * Transcript in `format`
+ */
+text: string; 
+/**
+ * Format of the text
+ */
+format: TranscriptionFormat; 
+/**
+ * Seconds spent transcribing and processing the text
+ */
+processing_time: number; 
+/**
+ * Path of the archived recording, if it was archived
+ */
+archive_path: string | null; 
+/**
+ * Confidence of the transcript between 0 and 1, if known
+ */
+confidence: number | null }
 /**
  * Event representing the progress for the current transcription
  * 
@@ -321,6 +1325,26 @@ export type TranscriptionProgressEvent = number
  * - `text` [`String`]: The text segment
  */
 export type TranscriptionSegmentEvent = { is_lossy: boolean; segment: number; start_timestamp: number; end_timestamp: number; text: string }
+/**
+ * The key or button that activates a chord
+ */
+export type Trigger = { MouseButton: number } | { Key: string }
+/**
+ * Whether the chord was pressed or released
+ */
+export type TriggerPhase = "Pressed" | "Released" | "Cancelled"
+/**
+ * Information about the currently focused window
+ */
+export type WindowInfo = { 
+/**
+ * Name of the process that owns the window (e.g. `code`, `firefox`)
+ */
+process_name: string | null; 
+/**
+ * Title of the window
+ */
+title: string | null }
 
 /** tauri-specta globals **/
 
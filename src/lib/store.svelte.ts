@@ -8,11 +8,39 @@ import type { UnlistenFn } from "@tauri-apps/api/event";
 import { debug, error, info, trace, warn } from "@tauri-apps/plugin-log";
 import { open } from "@tauri-apps/plugin-fs";
 import { BASE_LOCAL_APP_DIR } from "./constants.ts";
-import { commands } from "./bindings.ts";
+import {
+  commands,
+  events,
+  type Settings,
+  type TranscribeOptions,
+} from "./bindings.ts";
 
 /** Auto-save every given millisecond, or never if set to `false` */
 const AUTO_SAVE_FREQUENCY: false | number = 2000;
 
+/** Milliseconds without changes before options are saved to the backend settings */
+const SETTINGS_SAVE_DELAY = 500;
+
+/** Transcription options leaving every value to the backend */
+const EMPTY_TRANSCRIBE_OPTIONS: TranscribeOptions = {
+  translate: null,
+  individual_word_timestamps: null,
+  threads: null,
+  initial_prompt: null,
+  language: null,
+  format: null,
+  patience: null,
+  beam_size: null,
+  include_callback: null,
+  previous_context: null,
+  target_application: null,
+  diarization: null,
+  allowed_languages: null,
+  chunking: null,
+  hallucination_filter: null,
+  alternatives: null,
+};
+
 /**
  * An "Enum" for config item to allow auto-completion
  * and decouple from string representation.
@@ -55,10 +83,12 @@ class StoreStateOption<T> {
   #value = $state() as T;
   #name;
   #config: Store | undefined;
+  #onSet: ((value: T) => void) | undefined;
 
-  constructor(initial: T, name: string) {
+  constructor(initial: T, name: string, onSet?: (value: T) => void) {
     this.#value = initial;
     this.#name = name;
+    this.#onSet = onSet;
     trace(
       `Construct new runic store named ${this.#name} with value = ${this.#value}`,
     );
@@ -82,6 +112,13 @@ class StoreStateOption<T> {
   set value(v: T) {
     this.#value = v;
     this.saveToStore();
+    this.#onSet?.(v);
+  }
+
+  /** Set the value from elsewhere, e.g. the backend, without notifying `onSet` */
+  sync(v: T): void {
+    this.#value = v;
+    this.saveToStore();
   }
 
   get name(): string {
@@ -173,7 +210,12 @@ export class ConfigStore {
   fileStore: Store | null = null;
   cleanup: () => void;
   keyChangeUnlistener: UnlistenFn | undefined;
+  settingsUnlistener: UnlistenFn | undefined;
   #version = 3;
+  /** Settings shared with the backend, `null` until loaded */
+  settings: Settings | null = null;
+  #settingsSave: ReturnType<typeof setTimeout> | undefined;
+  #isSavingSettings = false;
 
   // Private Config data
   theme = new StoreStateOption<ThemeKind>("system", ConfigItem.THEME);
@@ -183,22 +225,32 @@ export class ConfigStore {
     "Shift+Alt+KeyR",
     ConfigItem.SHORTCUT,
   );
-  threads = new StoreStateOption<number>(1, ConfigItem.THREADS);
+  threads = new StoreStateOption<number>(
+    1,
+    ConfigItem.THREADS,
+    () => this.scheduleSettingsSave(),
+  );
   enabledSound = new StoreStateOption<boolean>(true, ConfigItem.SOUND);
   testNotify = new StoreStateOption<boolean>(true, ConfigItem.TEST_NOTIFY);
   useSystemNotification = new StoreStateOption<boolean>(
     true,
     ConfigItem.SYSTEM_NOTIFY,
   );
-  initialPrompt = new StoreStateOption<string>("", ConfigItem.PROMPT);
+  initialPrompt = new StoreStateOption<string>(
+    "",
+    ConfigItem.PROMPT,
+    () => this.scheduleSettingsSave(),
+  );
   ignoredWords = new StoreStateOption<string>(
     "[BLANK_AUDIO]\n[NO_AUDIO]\n[SILENCE]",
     ConfigItem.IGNORED_WORDS,
+    () => this.scheduleSettingsSave(),
   );
   windowFloat = new StoreStateOption<boolean>(false, ConfigItem.FLOAT_WINDOW);
   interNLRemove = new StoreStateOption<boolean>(
     true,
     ConfigItem.INTER_SENTENCE_NEWLINE_REMOVE,
+    () => this.scheduleSettingsSave(),
   );
   autoPaste = new StoreStateOption<boolean>(true, ConfigItem.AUTO_PASTE);
   pasteViaKeys = new StoreStateOption<boolean>(
@@ -228,6 +280,7 @@ export class ConfigStore {
   useGPU = new StoreStateOption<boolean>(
     true,
     ConfigItem.USE_GPU,
+    () => this.scheduleSettingsSave(),
   );
   enableCrashReport = new StoreStateOption<boolean | null>(
     null,
@@ -236,6 +289,7 @@ export class ConfigStore {
   patience = new StoreStateOption<number>(
     2.0,
     ConfigItem.PATIENCE,
+    () => this.scheduleSettingsSave(),
   );
   autoGainControl = new StoreStateOption<boolean>(
     false,
@@ -252,10 +306,12 @@ export class ConfigStore {
   denoise_audio = new StoreStateOption<boolean>(
     true,
     ConfigItem.AUDIO_PROCESS_DENOISE,
+    () => this.scheduleSettingsSave(),
   );
   normalize_result = new StoreStateOption<boolean>(
     true,
     ConfigItem.AUDIO_PROCESS_NORMALIZE,
+    () => this.scheduleSettingsSave(),
   );
 
   // Private config data not backed by file
@@ -332,6 +388,8 @@ export class ConfigStore {
         return () => {
           debug(`Clean up store to file`);
           this.keyChangeUnlistener?.();
+          this.settingsUnlistener?.();
+          clearTimeout(this.#settingsSave);
           this.transcriptions.cleanUp();
           this.fileStore?.close();
         };
@@ -380,6 +438,7 @@ export class ConfigStore {
     });
     if (oldVersion >= 0) await this.transitionDeprecatedOptions(oldVersion);
     await this.transcriptions.load();
+    await this.loadSettings();
     this.keyChangeUnlistener = await this.fileStore.onChange((k, v) =>
       trace(`STORE CHANGE: ${k} => ${v}`)
     );
@@ -436,6 +495,113 @@ export class ConfigStore {
     await this.fileStore.set(ConfigItem.VERSION, this.#version);
   }
 
+  /** Take over the settings shared with the backend, and follow their changes */
+  private async loadSettings(): Promise<void> {
+    const result = await commands.getSettings();
+    if (result.status === "error") {
+      error(`Could not load settings: ${result.error.message}`);
+      return;
+    }
+    this.applySettings(result.data);
+    this.settingsUnlistener = await events.settingsChangedEvent.listen(
+      (event) => this.applySettings(event.payload),
+    );
+  }
+
+  /** Show the backend settings in the options, unless they were changed since */
+  private applySettings(settings: Settings): void {
+    this.settings = settings;
+    if (this.#settingsSave !== undefined || this.#isSavingSettings) {
+      trace(`Keep options changed while settings were updated`);
+      return;
+    }
+    debug(`Apply settings to options`);
+    const { transcribe_options, decode_options, processing_options } =
+      settings.dictation;
+    this.threads.sync(transcribe_options?.threads ?? this.threads.value);
+    this.initialPrompt.sync(transcribe_options?.initial_prompt ?? "");
+    this.patience.sync(transcribe_options?.patience ?? this.patience.value);
+    this.denoise_audio.sync(
+      decode_options?.denoise_audio ?? this.denoise_audio.value,
+    );
+    this.normalize_result.sync(
+      decode_options?.normalize_result ?? this.normalize_result.value,
+    );
+    if (typeof processing_options === "object" && processing_options) {
+      const custom = processing_options.Custom;
+      this.ignoredWords.sync(
+        custom.removed_words?.join("\n") ?? this.ignoredWords.value,
+      );
+      this.interNLRemove.sync(
+        custom.replace_inter_sentence_newlines ?? this.interNLRemove.value,
+      );
+    }
+    this.useGPU.sync(settings.use_gpu ?? this.useGPU.value);
+  }
+
+  /** Save the options to the backend settings once they stop changing */
+  scheduleSettingsSave(): void {
+    clearTimeout(this.#settingsSave);
+    this.#settingsSave = setTimeout(
+      () => this.saveSettings(),
+      SETTINGS_SAVE_DELAY,
+    );
+  }
+
+  /** Save the options kept by the frontend through the backend settings */
+  async saveSettings(): Promise<void> {
+    this.#settingsSave = undefined;
+    if (!this.settings) {
+      warn(`Settings not loaded yet, options are not saved to them`);
+      return;
+    }
+    const { dictation } = this.settings;
+    const custom = typeof dictation.processing_options === "object" &&
+        dictation.processing_options
+      ? dictation.processing_options.Custom
+      : null;
+    this.#isSavingSettings = true;
+    try {
+      const result = await commands.updateSettings({
+        ...this.settings,
+        use_gpu: this.useGPU.value,
+        dictation: {
+          ...dictation,
+          transcribe_options: {
+            ...(dictation.transcribe_options ?? EMPTY_TRANSCRIBE_OPTIONS),
+            threads: this.threads.value > 0 ? this.threads.value : null,
+            initial_prompt: this.initialPrompt.value || null,
+            patience: this.patience.value,
+          },
+          decode_options: {
+            low_pass_value: dictation.decode_options?.low_pass_value ?? null,
+            high_pass_value: dictation.decode_options?.high_pass_value ?? null,
+            denoise_audio: this.denoise_audio.value,
+            normalize_result: this.normalize_result.value,
+          },
+          processing_options: {
+            Custom: {
+              removed_words: this.ignoredWordsList.filter((word) =>
+                word.trim()
+              ),
+              decorated_words: custom?.decorated_words ?? null,
+              replace_inter_sentence_newlines: this.interNLRemove.value,
+            },
+          },
+        },
+      });
+      if (result.status === "ok") {
+        this.settings = result.data;
+      } else {
+        error(
+          `Could not save settings: ${result.error.message}. ${result.error.hint}`,
+        );
+      }
+    } finally {
+      this.#isSavingSettings = false;
+    }
+  }
+
   saveAll(): Promise<PromiseSettledResult<void>[]> {
     debug(`Save all configured options to file`);
     this.transcriptions.save();